@@ -5,7 +5,7 @@ A simple grpc client that prints the aggregated orderbook summary on every updat
 use clap::Parser;
 use tokio::{sync::broadcast, task};
 
-use orderbook::{Empty, orderbook_aggregator_client, Summary};
+use orderbook::{BookSummaryRequest, orderbook_aggregator_client, Summary};
 use terminal_ui::start;
 
 mod terminal_ui;
@@ -19,6 +19,15 @@ pub mod orderbook {
 struct Args {
     #[arg(short, long, default_value_t = String::from("http://[::1]:50051"))]
     address: String,
+
+    /// The maximum number of levels per side to stream. 0 streams the full depth.
+    #[arg(short, long, default_value_t = 0)]
+    depth: u32,
+
+    /// The canonical symbol of the instrument to stream, e.g. `ethbtc`.
+    /// Streams every symbol the server serves if it is omitted.
+    #[arg(short, long, default_value_t = String::new())]
+    symbol: String,
 }
 
 #[tokio::main]
@@ -30,23 +39,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .await
             .unwrap();
 
-    let mut stream = client.book_summary(Empty {}).await.unwrap().into_inner();
+    let mut stream = client.book_summary(BookSummaryRequest { depth: args.depth, symbol: args.symbol }).await.unwrap().into_inner();
 
     let (tx_summary, rx_summary) = broadcast::channel::<Summary>(1000);
 
     let stream_handle = task::spawn(async move {
         while let Ok(Some(message)) = stream.message().await {
-            match tx_summary.send(message) {
-                Ok(_) => (),
-                Err(_) => (),
-            }
+            // A send error will only occur if the terminal ui has stopped receiving
+            let _ = tx_summary.send(message);
         }
     });
 
     let ui_handle = start(rx_summary);
 
-    stream_handle.await;
-    ui_handle.await;
+    stream_handle.await?;
+    ui_handle.await?;
 
     Ok(())
 }
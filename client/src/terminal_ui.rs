@@ -18,23 +18,23 @@ use crate::orderbook::Summary;
 
 pub fn start(mut rx_summary: Receiver<Summary>) -> JoinHandle<()> {
     tokio::spawn(async move {
-        enable_raw_mode();
+        let _ = enable_raw_mode();
 
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen);
+        let _ = execute!(stdout, EnterAlternateScreen);
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend).expect("");
 
-        disable_raw_mode();
+        let _ = disable_raw_mode();
 
-        execute!(
+        let _ = execute!(
             terminal.backend_mut(),
             LeaveAlternateScreen,
             DisableMouseCapture
         );
 
         while let Ok(mut summary) = rx_summary.recv().await {
-            terminal.draw(|f| draw(f, &mut summary));
+            let _ = terminal.draw(|f| draw(f, &mut summary));
         }
     })
 }
@@ -51,15 +51,13 @@ fn draw<B: Backend>(f: &mut Frame<B>, summary: &mut Summary) {
 
     let header = Row::new(header_cells).height(2).bottom_margin(1);
 
-    let spread = summary.asks[0].price - summary.bids[0].price;
-
-    let mut asks_row_cnt = 20;
+    let mut asks_row_cnt = summary.asks.len();
     let asks = summary.asks.iter_mut().rev().map(|level| {
         let cells = vec![
             Cell::from(asks_row_cnt.to_string()),
             Cell::from(level.exchange.to_string()),
-            Cell::from(level.price.to_string()),
-            Cell::from(level.amount.to_string()),
+            Cell::from(level.exact_price.clone()),
+            Cell::from(level.exact_amount.clone()),
         ];
         asks_row_cnt -= 1;
         Row::new(cells).height(1).bottom_margin(1)
@@ -67,9 +65,9 @@ fn draw<B: Backend>(f: &mut Frame<B>, summary: &mut Summary) {
 
     let spread = vec![Row::new(vec![
         Cell::from("-"),
-        Cell::from("-"),
-        Cell::from("-"),
-        Cell::from("-"),
+        Cell::from("Spread / Mid"),
+        Cell::from(summary.spread.to_string()),
+        Cell::from(summary.mid_price.to_string()),
     ])
         .height(1)
         .bottom_margin(1)];
@@ -79,8 +77,8 @@ fn draw<B: Backend>(f: &mut Frame<B>, summary: &mut Summary) {
         let cells = vec![
             Cell::from(bids_row_cnt.to_string()),
             Cell::from(level.exchange.to_string()),
-            Cell::from(level.price.to_string()),
-            Cell::from(level.amount.to_string()),
+            Cell::from(level.exact_price.clone()),
+            Cell::from(level.exact_amount.clone()),
         ];
         bids_row_cnt += 1;
         Row::new(cells).height(1).bottom_margin(1)
@@ -88,14 +86,14 @@ fn draw<B: Backend>(f: &mut Frame<B>, summary: &mut Summary) {
 
     let t = Table::new(
         asks.into_iter()
-            .chain(spread.into_iter())
-            .chain(bids.into_iter()),
+            .chain(spread)
+            .chain(bids),
     )
         .header(header)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Aggregated Orderbook")
+                .title(format!("Aggregated Orderbook {}", summary.symbol))
                 .title_alignment(tui::layout::Alignment::Center),
         )
         .widths(&[
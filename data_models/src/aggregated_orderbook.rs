@@ -1,23 +1,287 @@
 
+use crate::book_condition::BookCondition;
+use crate::consolidated_levels::ConsolidatedLevels;
 use crate::exchange_level::ExchangeLevel;
+use crate::exchange_summary::ExchangeSummary;
+use crate::level::{Decimal, Level};
 
 /// Represents the aggregated orderbook.
+///
+/// The bids are expected to be sorted in descending and the asks
+/// in ascending price order, so that the first level of each side
+/// is the top of the book. The analytics below rely on this ordering.
+/// If the levels are consolidated by price, the analytics are calculated from
+/// the consolidated levels, so that they agree with the streamed orderbook.
 pub struct AggregatedOrderbook {
+    /// The canonical symbol of the instrument, see [crate::instrument::Instrument::symbol].
+    pub symbol: String,
     pub bids: Vec<ExchangeLevel>,
-    pub asks: Vec<ExchangeLevel>
+    pub asks: Vec<ExchangeLevel>,
+    /// The best bid and ask of each contributing exchange, ordered by [crate::exchange::Exchange].
+    pub exchanges: Vec<ExchangeSummary>,
+    /// The bids and asks merged by price, with a per exchange breakdown.
+    /// Only set by aggregators that consolidate levels with the same price.
+    pub consolidated: Option<ConsolidatedLevels>,
+    /// The condition of the merged orderbook before the aggregator resolved any crossed levels,
+    /// so it may differ from the condition of the bids and asks above.
+    pub condition: BookCondition,
 }
 
 impl AggregatedOrderbook {
-    /// Constructs a new [AggregatedOrderbook]. 
+    /// Constructs a new [AggregatedOrderbook] without a symbol.
     /// Called in the initialization of the Orderbook Aggregator.
+    ///
+    /// The exchange summaries are derived from the given levels and carry no
+    /// timing information. Use [AggregatedOrderbook::with_exchanges] to replace them.
+    /// The [BookCondition] is derived from the given levels. Use
+    /// [AggregatedOrderbook::with_condition] to replace it.
     pub fn new(bids: Vec::<ExchangeLevel>, asks: Vec::<ExchangeLevel>) -> Self {
+        let exchanges = summarize_exchanges(&bids, &asks);
+        let condition = BookCondition::from_top_of_book(
+            bids.first().map(|el| &el.level), asks.first().map(|el| &el.level));
+
         AggregatedOrderbook {
+            symbol: String::new(),
             bids,
-            asks
+            asks,
+            exchanges,
+            consolidated: None,
+            condition,
+        }
+    }
+
+    /// Sets the canonical symbol of the instrument.
+    pub fn with_symbol(mut self, symbol: String) -> Self {
+        self.symbol = symbol;
+        self
+    }
+
+    /// Replaces the exchange summaries, e.g. with summaries built from the
+    /// latest [crate::exchange_orderbook::OrderbookSnapshot] of each exchange.
+    pub fn with_exchanges(mut self, mut exchanges: Vec<ExchangeSummary>) -> Self {
+        exchanges.sort_by_key(|summary| summary.exchange);
+        self.exchanges = exchanges;
+        self
+    }
+
+    /// Sets the bids and asks consolidated by price.
+    pub fn with_consolidated(mut self, consolidated: ConsolidatedLevels) -> Self {
+        self.consolidated = Some(consolidated);
+        self
+    }
+
+    /// Sets the condition of the merged orderbook before any crossed levels were resolved.
+    pub fn with_condition(mut self, condition: BookCondition) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    /// Returns the highest bid of the aggregated orderbook.
+    pub fn best_bid(&self) -> Option<&ExchangeLevel> {
+        self.bids.first()
+    }
+
+    /// Returns the lowest ask of the aggregated orderbook.
+    pub fn best_ask(&self) -> Option<&ExchangeLevel> {
+        self.asks.first()
+    }
+
+    /// Returns the difference between the best ask and the best bid prices.
+    /// Returns [None] if either side of the orderbook is empty.
+    pub fn spread(&self) -> Option<Decimal> {
+        let (bid, ask) = self.top_of_book()?;
+        Some(ask.price - bid.price)
+    }
+
+    /// Returns the average of the best bid and the best ask prices.
+    /// Returns [None] if either side of the orderbook is empty.
+    pub fn mid_price(&self) -> Option<Decimal> {
+        let (bid, ask) = self.top_of_book()?;
+        Some((bid.price + ask.price) / Decimal::TWO)
+    }
+
+    /// Returns the size weighted mid price of the top of the book.
+    ///
+    /// The best bid price is weighted with the best ask amount and vice versa,
+    /// so the microprice leans towards the side with the smaller queue.
+    /// Falls back to the mid price if both top levels have zero amount and
+    /// returns [None] if either side of the orderbook is empty.
+    pub fn microprice(&self) -> Option<Decimal> {
+        let (bid, ask) = self.top_of_book()?;
+        let total_amount = bid.amount + ask.amount;
+
+        if total_amount.is_zero() {
+            return self.mid_price();
+        }
+
+        Some((bid.price * ask.amount + ask.price * bid.amount) / total_amount)
+    }
+
+    /// Returns the order imbalance of the top levels of the orderbook.
+    ///
+    /// The imbalance is `(bid_amount - ask_amount) / (bid_amount + ask_amount)`
+    /// over the first `depth` price levels of each side, so it ranges from -1.0
+    /// (only asks) to 1.0 (only bids). Returns [None] if there is no amount
+    /// on either side.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The number of levels per side to take into account.
+    pub fn imbalance(&self, depth: usize) -> Option<Decimal> {
+        let bid_amount: Decimal = self.levels(true).take(depth).map(|level| level.amount).sum();
+        let ask_amount: Decimal = self.levels(false).take(depth).map(|level| level.amount).sum();
+        let total_amount = bid_amount + ask_amount;
+
+        if total_amount.is_zero() {
+            return None;
+        }
+
+        Some((bid_amount - ask_amount) / total_amount)
+    }
+
+    /// Returns the best bid and the best ask price levels of the whole orderbook.
+    fn top_of_book(&self) -> Option<(&Level, &Level)> {
+        Some((self.levels(true).next()?, self.levels(false).next()?))
+    }
+
+    /// Returns the price levels of one side of the orderbook, the consolidated levels
+    /// if they are set and the levels of each exchange otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `is_bid` - Whether to return the bids or the asks.
+    fn levels(&self, is_bid: bool) -> Box<dyn Iterator<Item = &Level> + '_> {
+        match (&self.consolidated, is_bid) {
+            (Some(consolidated), true) => Box::new(consolidated.bids.iter().map(|cl| &cl.level)),
+            (Some(consolidated), false) => Box::new(consolidated.asks.iter().map(|cl| &cl.level)),
+            (None, true) => Box::new(self.bids.iter().map(|el| &el.level)),
+            (None, false) => Box::new(self.asks.iter().map(|el| &el.level)),
+        }
+    }
+}
+
+/// Returns the best bid and the best ask of every exchange that
+/// has at least one level in the given bids or asks,
+/// ordered by [crate::exchange::Exchange].
+fn summarize_exchanges(bids: &[ExchangeLevel], asks: &[ExchangeLevel]) -> Vec<ExchangeSummary> {
+    let mut summaries = Vec::<ExchangeSummary>::new();
+
+    for (levels, is_bid) in [(bids, true), (asks, false)] {
+        for exchange_level in levels.iter() {
+            let summary = match summaries.iter_mut().find(|s| s.exchange == exchange_level.exchange) {
+                Some(summary) => summary,
+                None => {
+                    summaries.push(ExchangeSummary::new(exchange_level.exchange, None, None));
+                    summaries.last_mut().unwrap()
+                }
+            };
+
+            let best = if is_bid { &mut summary.best_bid } else { &mut summary.best_ask };
+            if best.is_none() {
+                *best = Some(exchange_level.level);
+            }
         }
     }
 
-    pub fn spread(&self) -> f64 {
-        return 0.0
+    summaries.sort_by_key(|summary| summary.exchange);
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+    use crate::aggregated_orderbook::AggregatedOrderbook;
+    use crate::consolidated_level::ConsolidatedLevel;
+    use crate::consolidated_levels::ConsolidatedLevels;
+    use crate::exchange_level::{Exchange, ExchangeLevel, Level};
+    use crate::exchange_summary::ExchangeSummary;
+
+    fn orderbook() -> AggregatedOrderbook {
+        AggregatedOrderbook::new(
+            vec![
+                ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(10.0), dec!(3.0))),
+                ExchangeLevel::new(Exchange::Binance, Level::new(dec!(9.0), dec!(1.0))),
+                ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(8.0), dec!(4.0))),
+            ],
+            vec![
+                ExchangeLevel::new(Exchange::Binance, Level::new(dec!(12.0), dec!(1.0))),
+                ExchangeLevel::new(Exchange::Binance, Level::new(dec!(13.0), dec!(2.0))),
+                ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(14.0), dec!(5.0))),
+            ],
+        )
+    }
+
+    #[test]
+    fn top_of_book_analytics() {
+        let orderbook = orderbook();
+
+        assert_eq!(orderbook.spread(), Some(dec!(2.0)));
+        assert_eq!(orderbook.mid_price(), Some(dec!(11.0)));
+        // (10 * 1 + 12 * 3) / (3 + 1)
+        assert_eq!(orderbook.microprice(), Some(dec!(11.5)));
+    }
+
+    #[test]
+    fn imbalance() {
+        let orderbook = orderbook();
+
+        // (3 - 1) / (3 + 1)
+        assert_eq!(orderbook.imbalance(1), Some(dec!(0.5)));
+        // (3 + 1 + 4 - 1 - 2 - 5) / 16
+        assert_eq!(orderbook.imbalance(3), Some(dec!(0.0)));
+        assert_eq!(orderbook.imbalance(0), None);
+    }
+
+    #[test]
+    fn exchange_summaries() {
+        let orderbook = orderbook();
+
+        assert_eq!(orderbook.exchanges, vec![
+            ExchangeSummary::new(Exchange::Binance, Some(Level::new(dec!(9.0), dec!(1.0))), Some(Level::new(dec!(12.0), dec!(1.0)))),
+            ExchangeSummary::new(Exchange::Bitstamp, Some(Level::new(dec!(10.0), dec!(3.0))), Some(Level::new(dec!(14.0), dec!(5.0)))),
+        ]);
+    }
+
+    #[test]
+    fn empty_side() {
+        let orderbook = AggregatedOrderbook::new(
+            vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(9.0), dec!(1.0)))],
+            vec![],
+        );
+
+        assert_eq!(orderbook.spread(), None);
+        assert_eq!(orderbook.mid_price(), None);
+        assert_eq!(orderbook.microprice(), None);
+        assert_eq!(orderbook.imbalance(5), Some(dec!(1.0)));
+        assert_eq!(orderbook.exchanges, vec![
+            ExchangeSummary::new(Exchange::Binance, Some(Level::new(dec!(9.0), dec!(1.0))), None),
+        ]);
+    }
+
+    #[test]
+    fn consolidated_analytics() {
+        let bids = vec![
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(10.0), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(10.0), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(9.0), dec!(4.0))),
+        ];
+        let asks = vec![
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(12.0), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(13.0), dec!(2.0))),
+        ];
+        let consolidated = ConsolidatedLevels::new(
+            vec![ConsolidatedLevel::new(bids[0..2].to_vec()), ConsolidatedLevel::new(bids[2..].to_vec())],
+            vec![ConsolidatedLevel::new(asks[0..1].to_vec()), ConsolidatedLevel::new(asks[1..].to_vec())],
+        );
+        let orderbook = AggregatedOrderbook::new(bids, asks).with_consolidated(consolidated);
+
+        assert_eq!(orderbook.spread(), Some(dec!(2.0)));
+        assert_eq!(orderbook.mid_price(), Some(dec!(11.0)));
+        // (10 * 1 + 12 * (1 + 2)) / (1 + 2 + 1)
+        assert_eq!(orderbook.microprice(), Some(dec!(11.5)));
+        // (1 + 2 - 1) / (1 + 2 + 1), the first price level holds the amounts of both exchanges
+        assert_eq!(orderbook.imbalance(1), Some(dec!(0.5)));
+        // (1 + 2 + 4 - 1 - 2) / 10
+        assert_eq!(orderbook.imbalance(2), Some(dec!(0.4)));
     }
 }
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::level::Level;
+
+/// The condition of the top of an orderbook that merges the levels of several exchanges.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BookCondition {
+    /// The best bid is below the best ask, or either side is empty.
+    Normal,
+    /// The best bid is equal to the best ask.
+    Locked,
+    /// The best bid is above the best ask.
+    Crossed,
+}
+
+impl BookCondition {
+    /// Returns the [BookCondition] of the given top of the book.
+    ///
+    /// # Arguments
+    ///
+    /// * `best_bid` - The highest bid, if any.
+    /// * `best_ask` - The lowest ask, if any.
+    pub fn from_top_of_book(best_bid: Option<&Level>, best_ask: Option<&Level>) -> Self {
+        match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) if bid.price > ask.price => BookCondition::Crossed,
+            (Some(bid), Some(ask)) if bid.price == ask.price => BookCondition::Locked,
+            _ => BookCondition::Normal,
+        }
+    }
+}
+
+impl fmt::Display for BookCondition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BookCondition::Normal => write!(f, "Normal"),
+            BookCondition::Locked => write!(f, "Locked"),
+            BookCondition::Crossed => write!(f, "Crossed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use crate::book_condition::BookCondition;
+    use crate::level::Level;
+
+    #[test]
+    fn from_top_of_book() {
+        let level = |price| Level::new(price, dec!(1.0));
+
+        assert_eq!(BookCondition::from_top_of_book(Some(&level(dec!(1.0))), Some(&level(dec!(2.0)))), BookCondition::Normal);
+        assert_eq!(BookCondition::from_top_of_book(Some(&level(dec!(2.0))), Some(&level(dec!(2.00)))), BookCondition::Locked);
+        assert_eq!(BookCondition::from_top_of_book(Some(&level(dec!(2.1))), Some(&level(dec!(2.0)))), BookCondition::Crossed);
+        assert_eq!(BookCondition::from_top_of_book(Some(&level(dec!(2.1))), None), BookCondition::Normal);
+        assert_eq!(BookCondition::from_top_of_book(None, None), BookCondition::Normal);
+    }
+}
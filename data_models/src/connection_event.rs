@@ -0,0 +1,52 @@
+use std::fmt;
+
+use crate::connection_state::ConnectionState;
+use crate::exchange::Exchange;
+use crate::timestamp;
+
+/// A change of the [ConnectionState] of the exchange client of an [Exchange] and symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionEvent {
+    pub exchange: Exchange,
+    /// The canonical symbol the exchange client subscribes to.
+    pub symbol: String,
+    pub state: ConnectionState,
+    /// Why the state changed, e.g. the error that dropped the connection.
+    pub reason: Option<String>,
+    /// The time the state changed in microseconds since the unix epoch.
+    pub timestamp: u64,
+}
+
+impl ConnectionEvent {
+    /// Constructs a new [ConnectionEvent] without a reason, timestamped with the current time.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The given [Exchange].
+    /// * `symbol` - The canonical symbol the exchange client subscribes to.
+    /// * `state` - The new [ConnectionState] of the exchange client.
+    pub fn new(exchange: Exchange, symbol: String, state: ConnectionState) -> Self {
+        ConnectionEvent {
+            exchange,
+            symbol,
+            state,
+            reason: None,
+            timestamp: timestamp::now_micros(),
+        }
+    }
+
+    /// Sets why the state changed.
+    pub fn with_reason(mut self, reason: Option<String>) -> Self {
+        self.reason = reason;
+        self
+    }
+}
+
+impl fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "`{}` : `{}` {} : `{}`", self.exchange, self.symbol, self.state, reason),
+            None => write!(f, "`{}` : `{}` {}", self.exchange, self.symbol, self.state),
+        }
+    }
+}
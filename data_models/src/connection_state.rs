@@ -0,0 +1,68 @@
+use std::{fmt, str::FromStr};
+
+/// The state of the connection of an exchange client to its exchange.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The client is connecting and subscribing to the exchange.
+    Connecting,
+    /// The client is subscribed and publishes orderbook snapshots.
+    Subscribed,
+    /// The connection dropped, the client reconnects right away.
+    Disconnected,
+    /// A connection attempt failed, the client waits before the next attempt.
+    Reconnecting,
+    /// A connection attempt failed with an error.
+    Error,
+}
+
+impl ConnectionState {
+    /// Returns true if the exchange client is subscribed, i.e. the levels of the exchange are live.
+    /// The levels of an exchange that is not subscribed are cleared from the aggregated orderbook.
+    pub fn is_subscribed(&self) -> bool {
+        matches!(self, ConnectionState::Subscribed)
+    }
+}
+
+impl FromStr for ConnectionState {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ConnectionState, Self::Err> {
+        match input {
+            "Connecting" => Ok(ConnectionState::Connecting),
+            "Subscribed" => Ok(ConnectionState::Subscribed),
+            "Disconnected" => Ok(ConnectionState::Disconnected),
+            "Reconnecting" => Ok(ConnectionState::Reconnecting),
+            "Error" => Ok(ConnectionState::Error),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "Connecting"),
+            ConnectionState::Subscribed => write!(f, "Subscribed"),
+            ConnectionState::Disconnected => write!(f, "Disconnected"),
+            ConnectionState::Reconnecting => write!(f, "Reconnecting"),
+            ConnectionState::Error => write!(f, "Error"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::connection_state::ConnectionState;
+
+    #[test]
+    fn round_trip() {
+        for state in [ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected,
+            ConnectionState::Reconnecting, ConnectionState::Error] {
+            assert_eq!(ConnectionState::from_str(&state.to_string()), Ok(state));
+            assert_eq!(state.is_subscribed(), state == ConnectionState::Subscribed);
+        }
+        assert!(ConnectionState::from_str("Closed").is_err());
+    }
+}
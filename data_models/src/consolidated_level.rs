@@ -0,0 +1,57 @@
+use std::fmt;
+
+use crate::exchange_level::ExchangeLevel;
+use crate::level::{Decimal, Level};
+
+/// Contains a single price [Level] of the aggregated orderbook that merges
+/// the levels of one or more exchanges with the same price.
+/// The amount of the level is the total amount of the breakdown.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConsolidatedLevel {
+    pub level: Level,
+    pub breakdown: Vec<ExchangeLevel>,
+}
+
+impl ConsolidatedLevel {
+    /// Constructs a new [ConsolidatedLevel].
+    ///
+    /// # Arguments
+    ///
+    /// * `breakdown` - The [ExchangeLevel]s that have the same price. It must not be empty.
+    ///
+    /// This method will panic if the breakdown is empty.
+    pub fn new(breakdown: Vec<ExchangeLevel>) -> Self {
+        let price = breakdown
+            .first()
+            .expect("A consolidated level requires at least one exchange level")
+            .level
+            .price;
+        let amount: Decimal = breakdown.iter().map(|el| el.level.amount).sum();
+
+        ConsolidatedLevel {
+            level: Level::new(price, amount),
+            breakdown,
+        }
+    }
+}
+
+impl fmt::Display for ConsolidatedLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(L={}, B=[", self.level)?;
+        for (index, exchange_level) in self.breakdown.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", exchange_level)?;
+        }
+        write!(f, "])")
+    }
+}
+
+impl fmt::Debug for ConsolidatedLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsolidatedLevel")
+            .field("level", &self.level)
+            .field("breakdown", &self.breakdown).finish()
+    }
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+use crate::consolidated_level::ConsolidatedLevel;
+
+/// Represents the bids and asks of an orderbook consolidated by price.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ConsolidatedLevels {
+    pub bids: Vec<ConsolidatedLevel>,
+    pub asks: Vec<ConsolidatedLevel>,
+}
+
+impl ConsolidatedLevels {
+    /// Constructs a new [ConsolidatedLevels].
+    ///
+    /// # Arguments
+    ///
+    /// * `bids` - A [`Vec<ConsolidatedLevel>`] containing the bids of the orderbook.
+    /// * `asks` - A [`Vec<ConsolidatedLevel>`] containing the asks of the orderbook.
+    pub fn new(bids: Vec<ConsolidatedLevel>, asks: Vec<ConsolidatedLevel>) -> Self {
+        ConsolidatedLevels {
+            bids,
+            asks,
+        }
+    }
+}
+
+impl fmt::Debug for ConsolidatedLevels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConsolidatedLevels")
+            .field("bids", &self.bids)
+            .field("asks", &self.asks)
+            .finish()
+    }
+}
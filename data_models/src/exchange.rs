@@ -7,7 +7,12 @@ use std::{fmt, str::FromStr};
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Exchange {
     Binance,
+    Bitfinex,
     Bitstamp,
+    Coinbase,
+    Huobi,
+    Kraken,
+    Kucoin,
 }
 
 impl FromStr for Exchange {
@@ -16,7 +21,12 @@ impl FromStr for Exchange {
     fn from_str(input: &str) -> Result<Exchange, Self::Err> {
         match input {
             "Binance" => Ok(Exchange::Binance),
+            "Bitfinex" => Ok(Exchange::Bitfinex),
             "Bitstamp" => Ok(Exchange::Bitstamp),
+            "Coinbase" => Ok(Exchange::Coinbase),
+            "Huobi" => Ok(Exchange::Huobi),
+            "Kraken" => Ok(Exchange::Kraken),
+            "Kucoin" => Ok(Exchange::Kucoin),
             _ => Err(()),
         }
     }
@@ -26,7 +36,12 @@ impl fmt::Display for Exchange {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Exchange::Binance => write!(f, "Binance"),
+            Exchange::Bitfinex => write!(f, "Bitfinex"),
             Exchange::Bitstamp => write!(f, "Bitstamp"),
+            Exchange::Coinbase => write!(f, "Coinbase"),
+            Exchange::Huobi => write!(f, "Huobi"),
+            Exchange::Kraken => write!(f, "Kraken"),
+            Exchange::Kucoin => write!(f, "Kucoin"),
         }
     }
 }
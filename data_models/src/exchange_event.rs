@@ -0,0 +1,11 @@
+use crate::connection_event::ConnectionEvent;
+use crate::exchange_orderbook::OrderbookSnapshot;
+
+/// The events an exchange client publishes to the aggregator.
+#[derive(Clone)]
+pub enum ExchangeEvent {
+    /// A new orderbook snapshot of the exchange.
+    Snapshot(OrderbookSnapshot),
+    /// A change of the connection state of the exchange client.
+    Connection(ConnectionEvent),
+}
@@ -4,7 +4,7 @@ pub use crate::exchange::Exchange;
 pub use crate::level::Level;
 
 /// Contains a single [Level] of the orderbook of a given [Exchange].
-#[derive(Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ExchangeLevel {
     pub exchange: Exchange,
     pub level: Level,
@@ -3,25 +3,109 @@ use crate::{exchange_level::Exchange, levels::Levels};
 /// Contains a given exchange's orderbook snapshot.
 /// It is published by the exchange client(s) and 
 /// is consumed by the orderbook aggregator.
+///
+/// All timestamps are in microseconds since the unix epoch.
+#[derive(Clone)]
 pub struct OrderbookSnapshot {
     pub exchange: Exchange,
     pub symbol: String,
     pub levels: Levels,
+    /// The update id of the snapshot, if the exchange publishes one.
+    pub sequence: Option<u64>,
+    /// The time the exchange generated the snapshot, if the exchange publishes it.
+    pub exchange_timestamp: Option<u64>,
+    /// The local time the snapshot was received by the exchange client.
+    pub received_timestamp: u64,
 }
 
 impl OrderbookSnapshot {
-    /// Constructs a new [OrderbookSnapshot].
+    /// Constructs a new [OrderbookSnapshot] received at the current local time.
     ///
     /// # Arguments
     ///
     /// * `exchange` - The [Exchange] that published the orderbook update.
-    /// * `symbol` - A [`Vec<Level>`] containing the asks of the orderbook.
-    /// * `levels` - A [`Vec<Level>`] containing the asks of the orderbook.
+    /// * `symbol` - The symbol of the orderbook.
+    /// * `levels` - The [Levels] containing the bids and asks of the orderbook.
     pub fn new(exchange: Exchange, symbol: String, levels: Levels) -> OrderbookSnapshot {
         OrderbookSnapshot {
             exchange,
             symbol,
             levels,
+            sequence: None,
+            exchange_timestamp: None,
+            received_timestamp: crate::timestamp::now_micros(),
         }
     }
+
+    /// Sets the update id published by the exchange.
+    pub fn with_sequence(mut self, sequence: Option<u64>) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    /// Sets the time the exchange generated the snapshot.
+    pub fn with_exchange_timestamp(mut self, exchange_timestamp: Option<u64>) -> Self {
+        self.exchange_timestamp = exchange_timestamp;
+        self
+    }
+
+    /// Sets the local time the snapshot was received.
+    pub fn with_received_timestamp(mut self, received_timestamp: u64) -> Self {
+        self.received_timestamp = received_timestamp;
+        self
+    }
+
+    /// Returns true if this snapshot was published after the given previous snapshot
+    /// of the same exchange, i.e. it is neither a duplicate nor out of order.
+    ///
+    /// The sequence is compared if both snapshots carry one, otherwise the exchange
+    /// timestamps are compared. Snapshots without either are always considered newer.
+    ///
+    /// # Arguments
+    ///
+    /// * `previous` - The last accepted [OrderbookSnapshot] of the exchange.
+    pub fn supersedes(&self, previous: &OrderbookSnapshot) -> bool {
+        match (self.sequence, previous.sequence) {
+            (Some(sequence), Some(previous_sequence)) => sequence > previous_sequence,
+            _ => match (self.exchange_timestamp, previous.exchange_timestamp) {
+                (Some(timestamp), Some(previous_timestamp)) => timestamp > previous_timestamp,
+                _ => true,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exchange_level::Exchange;
+    use crate::exchange_orderbook::OrderbookSnapshot;
+    use crate::levels::Levels;
+
+    fn snapshot(sequence: Option<u64>, exchange_timestamp: Option<u64>) -> OrderbookSnapshot {
+        OrderbookSnapshot::new(Exchange::Binance, "ethbtc".to_string(), Levels::new(vec![], vec![]))
+            .with_sequence(sequence)
+            .with_exchange_timestamp(exchange_timestamp)
+    }
+
+    #[test]
+    fn supersedes_by_sequence() {
+        assert!(snapshot(Some(2), None).supersedes(&snapshot(Some(1), None)));
+        assert!(!snapshot(Some(1), None).supersedes(&snapshot(Some(1), None)));
+        assert!(!snapshot(Some(1), None).supersedes(&snapshot(Some(2), None)));
+        // The sequence takes precedence over the exchange timestamp
+        assert!(snapshot(Some(2), Some(1)).supersedes(&snapshot(Some(1), Some(2))));
+    }
+
+    #[test]
+    fn supersedes_by_exchange_timestamp() {
+        assert!(snapshot(None, Some(2)).supersedes(&snapshot(None, Some(1))));
+        assert!(!snapshot(None, Some(1)).supersedes(&snapshot(None, Some(1))));
+        assert!(!snapshot(None, Some(1)).supersedes(&snapshot(None, Some(2))));
+    }
+
+    #[test]
+    fn supersedes_without_ordering_information() {
+        assert!(snapshot(None, None).supersedes(&snapshot(None, None)));
+        assert!(snapshot(Some(1), None).supersedes(&snapshot(None, Some(2))));
+    }
 }
@@ -0,0 +1,91 @@
+use std::fmt;
+
+pub use crate::exchange::Exchange;
+pub use crate::level::Level;
+use crate::connection_state::ConnectionState;
+use crate::exchange_orderbook::OrderbookSnapshot;
+
+/// Contains the top of the book of a single [Exchange] that contributes
+/// to the aggregated orderbook, along with the timing information of its
+/// latest [OrderbookSnapshot]. All timestamps are in microseconds since the unix epoch.
+/// The levels of a stale exchange, or of an exchange whose client is not subscribed, are not part of the aggregated orderbook.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct ExchangeSummary {
+    pub exchange: Exchange,
+    pub best_bid: Option<Level>,
+    pub best_ask: Option<Level>,
+    pub sequence: Option<u64>,
+    pub exchange_timestamp: Option<u64>,
+    pub received_timestamp: Option<u64>,
+    pub stale: bool,
+    /// The [ConnectionState] of the exchange client, [ConnectionState::Subscribed] until the client reports otherwise.
+    pub connection_state: ConnectionState,
+}
+
+impl ExchangeSummary {
+    /// Constructs a new [ExchangeSummary] without timing information.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The given [Exchange].
+    /// * `best_bid` - The highest bid of the exchange, if any.
+    /// * `best_ask` - The lowest ask of the exchange, if any.
+    pub fn new(exchange: Exchange, best_bid: Option<Level>, best_ask: Option<Level>) -> Self {
+        ExchangeSummary {
+            exchange,
+            best_bid,
+            best_ask,
+            sequence: None,
+            exchange_timestamp: None,
+            received_timestamp: None,
+            stale: false,
+            connection_state: ConnectionState::Subscribed,
+        }
+    }
+
+    /// Constructs a new [ExchangeSummary] from the latest [OrderbookSnapshot] of an exchange.
+    /// The levels of the snapshot are expected to be sorted with the best price first.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The latest [OrderbookSnapshot] of the exchange.
+    pub fn from_snapshot(snapshot: &OrderbookSnapshot) -> Self {
+        ExchangeSummary {
+            exchange: snapshot.exchange,
+            best_bid: snapshot.levels.bids.first().copied(),
+            best_ask: snapshot.levels.asks.first().copied(),
+            sequence: snapshot.sequence,
+            exchange_timestamp: snapshot.exchange_timestamp,
+            received_timestamp: Some(snapshot.received_timestamp),
+            stale: false,
+            connection_state: ConnectionState::Subscribed,
+        }
+    }
+
+    /// Flags whether the exchange is stale, i.e. it stopped publishing snapshots.
+    pub fn with_stale(mut self, stale: bool) -> Self {
+        self.stale = stale;
+        self
+    }
+
+    /// Sets the [ConnectionState] of the exchange client.
+    pub fn with_connection_state(mut self, connection_state: ConnectionState) -> Self {
+        self.connection_state = connection_state;
+        self
+    }
+}
+
+impl fmt::Debug for ExchangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExchangeSummary")
+            .field("exchange", &self.exchange)
+            .field("best_bid", &self.best_bid)
+            .field("best_ask", &self.best_ask)
+            .field("sequence", &self.sequence)
+            .field("exchange_timestamp", &self.exchange_timestamp)
+            .field("received_timestamp", &self.received_timestamp)
+            .field("stale", &self.stale)
+            .field("connection_state", &self.connection_state)
+            .finish()
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::exchange::Exchange;
+use crate::level::Decimal;
+use crate::precision::Precision;
+use crate::symbol_format::SymbolFormat;
+
+/// Represents a tradable pair of a base and a quote asset.
+///
+/// The canonical symbol of an instrument, e.g. `ethbtc`, is used to key the
+/// aggregated orderbooks. Each exchange refers to the instrument by its native
+/// symbol, see [Instrument::native_symbol].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instrument {
+    pub base: String,
+    pub quote: String,
+    /// The minimum price increment.
+    pub tick_size: Decimal,
+    /// The minimum amount increment.
+    pub lot_size: Decimal,
+    /// The [Precision] of the exchanges that publish the instrument with their own decimal places.
+    precisions: BTreeMap<Exchange, Precision>,
+}
+
+impl Instrument {
+    /// Constructs a new [Instrument].
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The base asset code, e.g. `ETH`.
+    /// * `quote` - The quote asset code, e.g. `BTC`.
+    /// * `tick_size` - The minimum price increment.
+    /// * `lot_size` - The minimum amount increment.
+    pub fn new(base: &str, quote: &str, tick_size: Decimal, lot_size: Decimal) -> Self {
+        Instrument {
+            base: base.to_uppercase(),
+            quote: quote.to_uppercase(),
+            tick_size,
+            lot_size,
+            precisions: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the [Precision] the given exchange publishes the instrument with.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The [Exchange] that publishes the instrument.
+    /// * `precision` - The decimal places of its prices and amounts.
+    pub fn with_precision(mut self, exchange: Exchange, precision: Precision) -> Self {
+        self.precisions.insert(exchange, precision);
+        self
+    }
+
+    /// Returns the [Precision] the given exchange publishes the instrument with.
+    /// Defaults to the decimal places of the tick size and the lot size of the instrument.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The [Exchange] that publishes the instrument.
+    pub fn precision(&self, exchange: Exchange) -> Precision {
+        self.precisions
+            .get(&exchange)
+            .copied()
+            .unwrap_or_else(|| Precision::new(self.tick_size.normalize().scale(), self.lot_size.normalize().scale()))
+    }
+
+    /// Returns the canonical symbol of the instrument, e.g. `ethbtc`.
+    pub fn symbol(&self) -> String {
+        format!("{}{}", self.base, self.quote).to_lowercase()
+    }
+
+    /// Returns the symbol of the instrument in the given [SymbolFormat],
+    /// e.g. `ethbtc` for Binance, `ETH-BTC` for Coinbase or `ETH/XBT` for Kraken.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The [SymbolFormat] of the exchange.
+    pub fn native_symbol(&self, format: &SymbolFormat) -> String {
+        let symbol = format!("{}{}{}", format.asset(&self.base), format.separator, format.asset(&self.quote));
+
+        if format.lowercase {
+            symbol.to_lowercase()
+        } else {
+            symbol
+        }
+    }
+
+    /// Returns the symbol of the instrument on the given [Exchange].
+    pub fn exchange_symbol(&self, exchange: Exchange) -> String {
+        self.native_symbol(&exchange.symbol_format())
+    }
+}
+
+impl fmt::Display for Instrument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.base, self.quote)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use crate::exchange::Exchange;
+    use crate::instrument::Instrument;
+    use crate::precision::Precision;
+    use crate::symbol_format::SymbolFormat;
+
+    #[test]
+    fn native_symbol() {
+        let instrument = Instrument::new("eth", "btc", dec!(0.00001), dec!(0.0001));
+
+        assert_eq!(instrument.symbol(), "ethbtc");
+        assert_eq!(instrument.to_string(), "ETH/BTC");
+        assert_eq!(instrument.native_symbol(&SymbolFormat::BINANCE), "ethbtc");
+        assert_eq!(instrument.native_symbol(&SymbolFormat::BITSTAMP), "ethbtc");
+        assert_eq!(instrument.native_symbol(&SymbolFormat::KRAKEN), "ETH/XBT");
+        assert_eq!(instrument.native_symbol(&SymbolFormat::COINBASE), "ETH-BTC");
+        assert_eq!(instrument.exchange_symbol(Exchange::Binance), "ethbtc");
+        assert_eq!(instrument.exchange_symbol(Exchange::Kraken), "ETH/BTC");
+        assert_eq!(instrument.exchange_symbol(Exchange::Coinbase), "ETH-BTC");
+        assert_eq!(instrument.exchange_symbol(Exchange::Bitfinex), "ETHBTC");
+        assert_eq!(instrument.exchange_symbol(Exchange::Kucoin), "ETH-BTC");
+    }
+
+    #[test]
+    fn precision() {
+        let instrument = Instrument::new("eth", "btc", dec!(0.00001), dec!(0.0001))
+            .with_precision(Exchange::Kraken, Precision::new(5, 8));
+
+        assert_eq!(instrument.precision(Exchange::Kraken), Precision::new(5, 8));
+        assert_eq!(instrument.precision(Exchange::Binance), Precision::new(5, 4));
+    }
+}
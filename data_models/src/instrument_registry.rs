@@ -0,0 +1,97 @@
+use std::collections::BTreeMap;
+
+use crate::exchange::Exchange;
+use crate::level::Decimal;
+
+use crate::instrument::Instrument;
+use crate::precision::Precision;
+
+/// The registry of the instruments that can be aggregated, keyed by their canonical symbol.
+/// Callers should validate any requested symbol against the registry before
+/// subscribing to the exchanges.
+pub struct InstrumentRegistry {
+    instruments: BTreeMap<String, Instrument>,
+}
+
+impl InstrumentRegistry {
+    /// Constructs a new empty [InstrumentRegistry].
+    pub fn new() -> Self {
+        InstrumentRegistry {
+            instruments: BTreeMap::new(),
+        }
+    }
+
+    /// Adds the given [Instrument] to the registry, replacing any
+    /// instrument that has the same canonical symbol.
+    pub fn register(&mut self, instrument: Instrument) {
+        self.instruments.insert(instrument.symbol(), instrument);
+    }
+
+    /// Returns the [Instrument] with the given canonical symbol.
+    /// The lookup is case insensitive, so both `ethbtc` and `ETHBTC` match.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The canonical symbol of the instrument.
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(&symbol.to_lowercase())
+    }
+
+    /// Returns the canonical symbols of all the registered instruments in alphabetical order.
+    pub fn symbols(&self) -> Vec<String> {
+        self.instruments.keys().cloned().collect()
+    }
+}
+
+impl Default for InstrumentRegistry {
+    /// Constructs a new [InstrumentRegistry] with the instruments supported out of the box.
+    fn default() -> Self {
+        let mut registry = InstrumentRegistry::new();
+
+        // The decimal places of the `price_precision` and `qty_precision` of the Kraken instrument channel
+        registry.register(Instrument::new("ETH", "BTC", Decimal::new(1, 5), Decimal::new(1, 4))
+            .with_precision(Exchange::Kraken, Precision::new(5, 8)));
+        registry.register(Instrument::new("BTC", "USDT", Decimal::new(1, 2), Decimal::new(1, 5))
+            .with_precision(Exchange::Kraken, Precision::new(1, 8)));
+        registry.register(Instrument::new("ETH", "USDT", Decimal::new(1, 2), Decimal::new(1, 4))
+            .with_precision(Exchange::Kraken, Precision::new(2, 8)));
+        registry.register(Instrument::new("BTC", "USD", Decimal::new(1, 2), Decimal::new(1, 8))
+            .with_precision(Exchange::Kraken, Precision::new(1, 8)));
+        registry.register(Instrument::new("ETH", "USD", Decimal::new(1, 2), Decimal::new(1, 8))
+            .with_precision(Exchange::Kraken, Precision::new(2, 8)));
+
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use crate::exchange::Exchange;
+    use crate::instrument::Instrument;
+    use crate::instrument_registry::InstrumentRegistry;
+    use crate::precision::Precision;
+
+    #[test]
+    fn get() {
+        let registry = InstrumentRegistry::default();
+
+        assert_eq!(registry.get("ethbtc"), Some(&Instrument::new("ETH", "BTC", dec!(0.00001), dec!(0.0001))
+            .with_precision(Exchange::Kraken, Precision::new(5, 8))));
+        assert_eq!(registry.get("ETHBTC").map(Instrument::symbol), Some("ethbtc".to_string()));
+        assert_eq!(registry.get("ethxbt"), None);
+    }
+
+    #[test]
+    fn register() {
+        let mut registry = InstrumentRegistry::new();
+        assert!(registry.symbols().is_empty());
+
+        registry.register(Instrument::new("SOL", "USDT", dec!(0.01), dec!(0.001)));
+        registry.register(Instrument::new("ADA", "USDT", dec!(0.0001), dec!(0.1)));
+
+        assert_eq!(registry.symbols(), vec!["adausdt".to_string(), "solusdt".to_string()]);
+        assert_eq!(registry.get("solusdt").unwrap().tick_size, dec!(0.01));
+    }
+}
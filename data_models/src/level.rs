@@ -1,10 +1,16 @@
 use std::fmt;
 
+pub use rust_decimal::{Decimal, RoundingStrategy};
+
 /// Represents a single <Price, Amount> pair.
-#[derive(Copy, Clone, PartialEq, PartialOrd)]
+///
+/// Prices and amounts are stored as [Decimal]s that keep the exact
+/// representation published by the exchange, including its scale.
+/// Levels are totally ordered by price and then by amount.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Level {
-    pub price: f64,
-    pub amount: f64,
+    pub price: Decimal,
+    pub amount: Decimal,
 }
 
 impl Level {
@@ -12,11 +18,35 @@ impl Level {
     ///
     /// # Arguments
     ///
-    /// * `price` - The [`f64`] value of the price.
-    /// * `amount` - The [`f64`] value of the amount.
-    pub fn new(price: f64, amount: f64) -> Self {
+    /// * `price` - The [`Decimal`] value of the price.
+    /// * `amount` - The [`Decimal`] value of the amount.
+    pub fn new(price: Decimal, amount: Decimal) -> Self {
         Level { price, amount }
     }
+
+    /// Returns true if the price is an exact multiple of the given tick size.
+    ///
+    /// # Arguments
+    ///
+    /// * `tick_size` - The minimum price increment of the instrument.
+    pub fn is_on_tick(&self, tick_size: Decimal) -> bool {
+        tick_size.is_zero() || (self.price % tick_size).is_zero()
+    }
+
+    /// Returns a copy of the level with the price rounded to a multiple of the given tick size.
+    ///
+    /// # Arguments
+    ///
+    /// * `tick_size` - The minimum price increment of the instrument.
+    /// * `strategy` - The [RoundingStrategy], e.g. [RoundingStrategy::ToZero] to round bids down.
+    pub fn round_to_tick(&self, tick_size: Decimal, strategy: RoundingStrategy) -> Self {
+        if tick_size.is_zero() {
+            return *self;
+        }
+
+        let ticks = (self.price / tick_size).round_dp_with_strategy(0, strategy);
+        Level::new(ticks * tick_size, self.amount)
+    }
 }
 
 
@@ -38,42 +68,69 @@ impl fmt::Debug for Level {
 #[cfg(test)]
 mod tests {
     use std::cmp::Ordering;
+    use rust_decimal_macros::dec;
     use crate::exchange_level::Level;
+    use crate::level::RoundingStrategy;
 
     #[test]
     fn order_eq() {
-        let l1 = Level::new(1.0, 1.0);
-        let l2 = Level::new(1.0, 1.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Equal);
+        let l1 = Level::new(dec!(1.0), dec!(1.0));
+        let l2 = Level::new(dec!(1.0), dec!(1.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Equal);
+
+        // Equal values with a different scale are equal
+        let l1 = Level::new(dec!(1.0), dec!(1.0));
+        let l2 = Level::new(dec!(1.00000000), dec!(1));
+        assert_eq!(l1.cmp(&l2), Ordering::Equal);
+        assert_eq!(l1, l2);
     }
 
     #[test]
     fn order_gt() {
-        let l1 = Level::new(1.0, 2.0);
-        let l2 = Level::new(1.0, 1.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Greater);
+        let l1 = Level::new(dec!(1.0), dec!(2.0));
+        let l2 = Level::new(dec!(1.0), dec!(1.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Greater);
 
-        let l1 = Level::new(2.0, 1.0);
-        let l2 = Level::new(1.0, 1.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Greater);
+        let l1 = Level::new(dec!(2.0), dec!(1.0));
+        let l2 = Level::new(dec!(1.0), dec!(1.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Greater);
 
-        let l1 = Level::new(2.0, 2.0);
-        let l2 = Level::new(1.0, 1.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Greater);
+        let l1 = Level::new(dec!(2.0), dec!(2.0));
+        let l2 = Level::new(dec!(1.0), dec!(1.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Greater);
     }
 
     #[test]
     fn order_lt() {
-        let l1 = Level::new(1.0, 1.0);
-        let l2 = Level::new(2.0, 1.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Less);
+        let l1 = Level::new(dec!(1.0), dec!(1.0));
+        let l2 = Level::new(dec!(2.0), dec!(1.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Less);
+
+        let l1 = Level::new(dec!(1.0), dec!(1.0));
+        let l2 = Level::new(dec!(1.0), dec!(2.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Less);
+
+        let l1 = Level::new(dec!(1.0), dec!(1.0));
+        let l2 = Level::new(dec!(2.0), dec!(2.0));
+        assert_eq!(l1.cmp(&l2), Ordering::Less);
+    }
 
-        let l1 = Level::new(1.0, 1.0);
-        let l2 = Level::new(1.0, 2.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Less);
+    #[test]
+    fn exact_representation() {
+        // 0.1 + 0.2 is not 0.3 in binary floating point
+        let l1 = Level::new(dec!(0.1) + dec!(0.2), dec!(1));
+        let l2 = Level::new(dec!(0.3), dec!(1));
+        assert_eq!(l1, l2);
+        assert_eq!(Level::new(dec!(0.06870000), dec!(1)).price.to_string(), "0.06870000");
+    }
 
-        let l1 = Level::new(1.0, 1.0);
-        let l2 = Level::new(2.0, 2.0);
-        assert_eq!(l1.partial_cmp(&l2).unwrap(), Ordering::Less);
+    #[test]
+    fn tick_size() {
+        let level = Level::new(dec!(0.068715), dec!(1));
+        assert!(!level.is_on_tick(dec!(0.00001)));
+        assert!(level.is_on_tick(dec!(0.000005)));
+        assert_eq!(level.round_to_tick(dec!(0.00001), RoundingStrategy::ToZero).price, dec!(0.06871));
+        assert_eq!(level.round_to_tick(dec!(0.00001), RoundingStrategy::AwayFromZero).price, dec!(0.06872));
+        assert_eq!(level.round_to_tick(dec!(0.00001), RoundingStrategy::AwayFromZero).amount, dec!(1));
     }
-}
\ No newline at end of file
+}
@@ -3,7 +3,7 @@ use std::fmt;
 pub use crate::level::Level;
 
 /// Represents the bids and asks of an orderbook
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Levels {
     pub bids: Vec<Level>,
     pub asks: Vec<Level>,
@@ -18,8 +18,8 @@ impl Levels {
     /// * `asks` - A [`Vec<Level>`] containing the asks of the orderbook.
     pub fn new(bids: Vec<Level>, asks: Vec<Level>) -> Self {
         Levels {
-            bids: bids,
-            asks: asks,
+            bids,
+            asks,
         }
     }
 }
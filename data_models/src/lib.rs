@@ -3,8 +3,20 @@
 //! This crate includes the data models (structs) that are used by the workspaces
 
 pub mod aggregated_orderbook;
+pub mod book_condition;
+pub mod connection_event;
+pub mod connection_state;
+pub mod consolidated_level;
+pub mod consolidated_levels;
 pub mod exchange;
+pub mod exchange_event;
 pub mod exchange_level;
 pub mod exchange_orderbook;
+pub mod exchange_summary;
+pub mod instrument;
+pub mod instrument_registry;
 pub mod level;
 pub mod levels;
+pub mod precision;
+pub mod symbol_format;
+pub mod timestamp;
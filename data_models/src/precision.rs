@@ -0,0 +1,53 @@
+use crate::level::Decimal;
+
+/// The number of decimal places an exchange publishes the prices and the amounts of an instrument with.
+/// Exchanges that checksum their orderbooks, e.g. Kraken, format the levels with these
+/// decimal places, regardless of the trailing zeros of the published numbers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Precision {
+    /// The decimal places of the prices.
+    pub price: u32,
+    /// The decimal places of the amounts.
+    pub amount: u32,
+}
+
+impl Precision {
+    /// Constructs a new [Precision].
+    ///
+    /// # Arguments
+    ///
+    /// * `price` - The decimal places of the prices.
+    /// * `amount` - The decimal places of the amounts.
+    pub fn new(price: u32, amount: u32) -> Self {
+        Precision { price, amount }
+    }
+
+    /// Formats the given value with exactly `decimal_places`, rounding it or padding it with zeros,
+    /// e.g. `10.0` with 8 decimal places is formatted as `10.00000000`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The price or the amount to format.
+    /// * `decimal_places` - Either [Precision::price] or [Precision::amount].
+    pub fn format(value: &Decimal, decimal_places: u32) -> String {
+        let mut value = value.round_dp(decimal_places);
+        value.rescale(decimal_places);
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use crate::precision::Precision;
+
+    #[test]
+    fn format() {
+        assert_eq!(Precision::format(&dec!(10.0), 8), "10.00000000");
+        assert_eq!(Precision::format(&dec!(0.0531), 5), "0.05310");
+        assert_eq!(Precision::format(&dec!(0.053104), 5), "0.05310");
+        assert_eq!(Precision::format(&dec!(2500), 1), "2500.0");
+        assert_eq!(Precision::format(&dec!(0), 0), "0");
+    }
+}
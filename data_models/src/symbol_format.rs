@@ -0,0 +1,54 @@
+use crate::exchange::Exchange;
+
+/// Describes how an exchange names its instruments, e.g. `ethbtc`, `ETH-BTC` or `ETH/XBT`.
+/// It is used by [crate::instrument::Instrument::native_symbol] to map an instrument
+/// to the symbol an exchange expects in its urls and subscription messages.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SymbolFormat {
+    /// The separator between the base and the quote asset.
+    pub separator: &'static str,
+    /// Whether the symbol is written in lowercase rather than uppercase.
+    pub lowercase: bool,
+    /// Pairs of <canonical asset, native asset> for exchanges that use their own asset codes.
+    pub aliases: &'static [(&'static str, &'static str)],
+}
+
+impl SymbolFormat {
+    pub const BINANCE: SymbolFormat = SymbolFormat { separator: "", lowercase: true, aliases: &[] };
+    pub const BITSTAMP: SymbolFormat = SymbolFormat { separator: "", lowercase: true, aliases: &[] };
+    pub const KRAKEN: SymbolFormat = SymbolFormat { separator: "/", lowercase: false, aliases: &[("BTC", "XBT")] };
+    /// The WebSocket v2 api of Kraken names bitcoin `BTC` instead of `XBT`, e.g. `ETH/BTC`.
+    pub const KRAKEN_V2: SymbolFormat = SymbolFormat { separator: "/", lowercase: false, aliases: &[] };
+    pub const COINBASE: SymbolFormat = SymbolFormat { separator: "-", lowercase: false, aliases: &[] };
+    pub const HUOBI: SymbolFormat = SymbolFormat { separator: "", lowercase: true, aliases: &[] };
+    /// Bitfinex names tether `UST`, e.g. `BTCUST`. Its api prefixes the trading pairs with `t`, e.g. `tBTCUST`.
+    pub const BITFINEX: SymbolFormat = SymbolFormat { separator: "", lowercase: false, aliases: &[("USDT", "UST")] };
+    pub const KUCOIN: SymbolFormat = SymbolFormat { separator: "-", lowercase: false, aliases: &[] };
+
+    /// Returns the native code of the given canonical asset code.
+    ///
+    /// # Arguments
+    ///
+    /// * `asset` - The canonical (uppercase) asset code, e.g. `BTC`.
+    pub fn asset<'a>(&self, asset: &'a str) -> &'a str {
+        self.aliases
+            .iter()
+            .find(|(canonical, _)| *canonical == asset)
+            .map_or(asset, |(_, native)| native)
+    }
+}
+
+impl Exchange {
+    /// Returns the [SymbolFormat] of the exchange.
+    pub fn symbol_format(&self) -> SymbolFormat {
+        match self {
+            Exchange::Binance => SymbolFormat::BINANCE,
+            Exchange::Bitfinex => SymbolFormat::BITFINEX,
+            Exchange::Bitstamp => SymbolFormat::BITSTAMP,
+            Exchange::Coinbase => SymbolFormat::COINBASE,
+            Exchange::Huobi => SymbolFormat::HUOBI,
+            Exchange::Kraken => SymbolFormat::KRAKEN_V2,
+            Exchange::Kucoin => SymbolFormat::KUCOIN,
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! Helpers for the timestamps carried by the data models.
+//! All timestamps are expressed in microseconds since the unix epoch.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Returns the current local time in microseconds since the unix epoch.
+pub fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_micros() as u64)
+        .unwrap_or_default()
+}
+
+/// Parses an RFC 3339 timestamp in UTC, e.g. `2023-10-06T17:35:55.440295Z`, into microseconds since the unix epoch.
+/// Fractions beyond microseconds are truncated. Returns [None] if the timestamp is not in UTC or is invalid.
+///
+/// # Arguments
+///
+/// * `timestamp` - The RFC 3339 timestamp.
+pub fn parse_rfc3339_micros(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.strip_suffix('Z')?.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<u64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let mut time = time.splitn(3, ':').map(str::parse::<u64>);
+    let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    if year < 1970 || !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    if !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let micros = format!("{:0<6}", &fraction[..fraction.len().min(6)]).parse::<u64>().ok()?;
+
+    // The days since the unix epoch of the proleptic Gregorian calendar, see http://howardhinnant.github.io/date_algorithms.html
+    let (year, month) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = year / 400;
+    let day_of_era = (year % 400) * 365 + (year % 400) / 4 - (year % 400) / 100 + (153 * month + 2) / 5 + day - 1;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    Some(((days * 24 + hours) * 60 + minutes) * 60_000_000 + seconds * 1_000_000 + micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::timestamp::parse_rfc3339_micros;
+
+    #[test]
+    fn parse_rfc3339_micros_test() {
+        assert_eq!(parse_rfc3339_micros("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_rfc3339_micros("2023-06-14T14:05:43.123456Z"), Some(1686751543123456));
+        assert_eq!(parse_rfc3339_micros("2024-02-29T23:59:59.5Z"), Some(1709251199500000));
+        assert_eq!(parse_rfc3339_micros("2023-10-06T17:35:55.440295123Z"), Some(1696613755440295));
+        assert_eq!(parse_rfc3339_micros("2023-10-06T17:35:55+02:00"), None);
+        assert_eq!(parse_rfc3339_micros("2023-13-06T17:35:55Z"), None);
+        assert_eq!(parse_rfc3339_micros("2023-10-06T17:35:55.4a0Z"), None);
+    }
+}
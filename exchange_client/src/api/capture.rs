@@ -0,0 +1,257 @@
+//! Captures the raw WebSocket frames the exchange clients receive, so that a production session can be
+//! replayed offline with [crate::api::replay::ReplaySource].
+//!
+//! A capture is a file with one JSON record per line, e.g.
+//! `{"received_timestamp":1686751543123456,"exchange":"Binance","symbol":"ethbtc","type":"text","data":"{...}"}`.
+//! The payloads of binary, ping and pong frames are hex encoded, close frames are captured without their reason.
+//! Every connection starts with a `connection` record and is captured from its first frame, including the subscribe
+//! handshake. The responses to the REST requests of the clients, e.g. the orderbook snapshots their local orderbooks
+//! are synchronized from, are captured as `response` records with their url, so that a replay can serve them.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use futures_util::{Stream, StreamExt};
+use serde_json::{json, Value};
+
+use data_models::exchange::Exchange;
+use data_models::timestamp;
+
+use crate::client_re_exports::{Error, Message};
+
+/// What a [CaptureRecord] captured.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    /// The client connected to the exchange. The frames up to the next connection were received on this connection.
+    Connection,
+    /// A WebSocket frame received from the exchange.
+    Frame(Message),
+    /// The body of the response to a REST request of the client, e.g. an orderbook snapshot.
+    Response { url: String, body: String },
+}
+
+/// A captured WebSocket frame, connection or REST response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureRecord {
+    /// The time the payload was received in microseconds since the unix epoch.
+    pub received_timestamp: u64,
+    pub exchange: Exchange,
+    /// The canonical symbol the client subscribed to.
+    pub symbol: String,
+    pub payload: Payload,
+}
+
+impl CaptureRecord {
+    /// Serializes the record into a line of a capture, without the line break.
+    pub fn serialize(&self) -> String {
+        let (record_type, data) = match &self.payload {
+            Payload::Connection => ("connection", String::new()),
+            Payload::Frame(Message::Text(text)) => ("text", text.clone()),
+            Payload::Frame(Message::Binary(data)) => ("binary", encode_hex(data)),
+            Payload::Frame(Message::Ping(data)) => ("ping", encode_hex(data)),
+            Payload::Frame(Message::Pong(data)) => ("pong", encode_hex(data)),
+            Payload::Frame(Message::Close(_)) => ("close", String::new()),
+            Payload::Frame(Message::Frame(frame)) => ("binary", encode_hex(frame.payload())),
+            Payload::Response { body, .. } => ("response", body.clone()),
+        };
+
+        let mut record = json!({
+            "received_timestamp": self.received_timestamp,
+            "exchange": self.exchange.to_string(),
+            "symbol": self.symbol,
+            "type": record_type,
+            "data": data,
+        });
+        if let Payload::Response { url, .. } = &self.payload {
+            record["url"] = json!(url);
+        }
+        record.to_string()
+    }
+
+    /// Deserializes a line of a capture.
+    /// Returns [None] if the line is not a valid record.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The line of the capture.
+    pub fn deserialize(line: &str) -> Option<CaptureRecord> {
+        let record: Value = serde_json::from_str(line).ok()?;
+        let data = record["data"].as_str()?;
+
+        let payload = match record["type"].as_str()? {
+            "connection" => Payload::Connection,
+            "text" => Payload::Frame(Message::Text(data.to_string())),
+            "binary" => Payload::Frame(Message::Binary(decode_hex(data)?)),
+            "ping" => Payload::Frame(Message::Ping(decode_hex(data)?)),
+            "pong" => Payload::Frame(Message::Pong(decode_hex(data)?)),
+            "close" => Payload::Frame(Message::Close(None)),
+            "response" => Payload::Response { url: record["url"].as_str()?.to_string(), body: data.to_string() },
+            _ => return None,
+        };
+
+        Some(CaptureRecord {
+            received_timestamp: record["received_timestamp"].as_u64()?,
+            exchange: Exchange::from_str(record["exchange"].as_str()?).ok()?,
+            symbol: record["symbol"].as_str()?.to_string(),
+            payload,
+        })
+    }
+}
+
+/// Writes the frames and REST responses of the exchange clients to a capture file. It is shared by all clients of a session.
+pub struct CaptureWriter {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl CaptureWriter {
+    /// Creates a new capture file, truncating the file if it already exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the capture file.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<CaptureWriter> {
+        Ok(CaptureWriter {
+            writer: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Appends a record to the capture. Every record is flushed, so that the capture of a
+    /// session that ended abruptly is complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The [CaptureRecord] of the frame.
+    pub fn write(&self, record: &CaptureRecord) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        writeln!(writer, "{}", record.serialize())?;
+        writer.flush()
+    }
+
+    /// Captures the given payload as received now. A payload that cannot be written is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The [Exchange] of the client.
+    /// * `symbol` - The canonical symbol the client subscribed to.
+    /// * `payload` - The captured [Payload].
+    pub(crate) fn capture(&self, exchange: Exchange, symbol: &str, payload: Payload) {
+        let record = CaptureRecord {
+            received_timestamp: timestamp::now_micros(),
+            exchange,
+            symbol: symbol.to_string(),
+            payload,
+        };
+        if let Err(error) = self.write(&record) {
+            println!("Could not capture a record of `{}` : `{}` : `{}`", exchange, symbol, error);
+        }
+    }
+}
+
+/// Reads all records of a capture file. Lines that are not valid records are skipped.
+///
+/// # Arguments
+///
+/// * `path` - The path of the capture file.
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<CaptureRecord>> {
+    let mut records = vec![];
+    for line in BufReader::new(File::open(path)?).lines() {
+        match CaptureRecord::deserialize(&line?) {
+            Some(record) => records.push(record),
+            None => continue,
+        }
+    }
+    Ok(records)
+}
+
+/// Captures every frame of a stream before it is handled. Nothing is captured if no [CaptureWriter]
+/// is configured. A frame that cannot be written is reported and still handled.
+///
+/// # Arguments
+///
+/// * `stream` - The stream of the connection.
+/// * `writer` - The [CaptureWriter] of the session, if capturing is enabled.
+/// * `exchange` - The [Exchange] of the client.
+/// * `symbol` - The canonical symbol the client subscribed to.
+pub(crate) fn tap(stream: impl Stream<Item=Result<Message, Error>> + Unpin,
+                  writer: Option<Arc<CaptureWriter>>,
+                  exchange: Exchange,
+                  symbol: String) -> impl Stream<Item=Result<Message, Error>> + Unpin {
+    stream.inspect(move |message| {
+        if let (Some(writer), Ok(message)) = (&writer, message) {
+            writer.capture(exchange, &symbol, Payload::Frame(message.clone()));
+        }
+    })
+}
+
+fn encode_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    if !data.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..data.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(data.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_util::{stream, StreamExt};
+
+    use data_models::exchange::Exchange;
+
+    use crate::api::capture::{self, CaptureRecord, CaptureWriter, Payload};
+    use crate::client_re_exports::Message;
+
+    #[test]
+    fn serialize_test() {
+        let records = [
+            Payload::Connection,
+            Payload::Frame(Message::text(r#"{"bids": [["0.0687", "1.0"]]}"#)),
+            Payload::Frame(Message::Binary(vec![0x1f, 0x8b, 0x00])),
+            Payload::Frame(Message::Ping(vec![])),
+            Payload::Frame(Message::Pong(vec![0xff])),
+            Payload::Frame(Message::Close(None)),
+            Payload::Response { url: "https://api.binance.com/api/v3/depth?symbol=ETHBTC".to_string(), body: r#"{"lastUpdateId":1}"#.to_string() },
+        ].into_iter().enumerate().map(|(index, payload)| CaptureRecord {
+            received_timestamp: index as u64,
+            exchange: Exchange::Bitstamp,
+            symbol: "ethbtc".to_string(),
+            payload,
+        });
+
+        for record in records {
+            assert_eq!(CaptureRecord::deserialize(&record.serialize()), Some(record));
+        }
+        assert_eq!(CaptureRecord::deserialize(r#"{"received_timestamp":1,"exchange":"Binance","symbol":"ethbtc","type":"binary","data":"1"}"#), None);
+        assert_eq!(CaptureRecord::deserialize(r#"{"received_timestamp":1,"exchange":"Binance","symbol":"ethbtc","type":"response","data":"{}"}"#), None);
+        assert_eq!(CaptureRecord::deserialize("{"), None);
+    }
+
+    #[tokio::test]
+    async fn tap_test() {
+        let path = std::env::temp_dir().join(format!("capture_tap_test_{}.jsonl", std::process::id()));
+        let writer = Arc::new(CaptureWriter::create(&path).unwrap());
+
+        let messages = vec![Ok(Message::text("first")), Ok(Message::Ping(vec![1])), Ok(Message::text("second"))];
+        let tapped: Vec<Message> = capture::tap(stream::iter(messages), Some(writer), Exchange::Binance, "ethbtc".to_string())
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        let records = capture::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records.iter().map(|record| record.payload.clone()).collect::<Vec<_>>(), tapped.into_iter().map(Payload::Frame).collect::<Vec<_>>());
+        assert!(records.iter().all(|record| record.exchange == Exchange::Binance && record.symbol == "ethbtc"));
+        assert!(records.windows(2).all(|pair| pair[0].received_timestamp <= pair[1].received_timestamp));
+    }
+}
@@ -2,32 +2,146 @@
 //! The configuration for each client must be instantiated by the 
 //! caller and then provided to [crate::api::provider].
 
+use std::{fmt, str::FromStr};
+use std::sync::{Arc, Mutex};
+
 use url::Url;
 
+use data_models::instrument::Instrument;
+
+use crate::api::capture::CaptureWriter;
+use crate::api::heartbeat::{HeartbeatPolicy, RoundTripTime};
+use crate::api::http_fetcher::{HttpFetcher, ReqwestFetcher};
+use crate::api::replay::{ReplayFetcher, ReplaySource};
+use crate::api::reconnect_policy::ReconnectPolicy;
+
 /// The exchange client configuration that is supplied to the [crate::api::provider].
 pub struct ExchangeClientConfig {
     pub base_url: Url,
     pub depth: usize,
-    pub symbol: String,
+    pub instrument: Instrument,
+    pub reconnect_policy: ReconnectPolicy,
+    /// The heartbeat of the connection. Dead connections are not detected if it is [None].
+    pub heartbeat: Option<HeartbeatPolicy>,
+    /// The round trip time of the heartbeat pings, clone it before starting the client to monitor it.
+    pub round_trip_time: Arc<RoundTripTime>,
+    /// The orderbook stream the client subscribes to, if the exchange publishes more than one.
+    pub depth_stream: DepthStream,
+    /// The base Url of the REST api of the exchange. Clients fall back to the public endpoint of their exchange if it is [None].
+    pub rest_url: Option<Url>,
+    /// The [HttpFetcher] that sends the REST requests of the client.
+    pub http_fetcher: Arc<dyn HttpFetcher>,
+    /// The [CaptureWriter] the raw frames of the client are written to. Frames are not captured if it is [None].
+    pub capture: Option<Arc<CaptureWriter>>,
+    /// The captured connections the client replays instead of connecting to the exchange, see [ExchangeClientConfig::with_replay].
+    pub replay: Option<Arc<Mutex<ReplaySource>>>,
+}
+
+/// Enumeration of the orderbook streams an exchange client can subscribe to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DepthStream {
+    /// Snapshots of the top levels of the orderbook, published by the exchange.
+    Partial,
+    /// Incremental updates that are applied to a local orderbook, which is synchronized from a REST snapshot.
+    /// Only supported by [data_models::exchange::Exchange::Binance], the other clients subscribe to [DepthStream::Partial]
+    /// or, like [data_models::exchange::Exchange::Kucoin], keep a local orderbook regardless of the stream.
+    Diff,
+}
+
+impl FromStr for DepthStream {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<DepthStream, Self::Err> {
+        match input {
+            "partial" => Ok(DepthStream::Partial),
+            "diff" => Ok(DepthStream::Diff),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for DepthStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DepthStream::Partial => write!(f, "partial"),
+            DepthStream::Diff => write!(f, "diff"),
+        }
+    }
 }
 
 impl ExchangeClientConfig {
-    /// Constructs a new [ExchangeClientConfig].
+    /// Constructs a new [ExchangeClientConfig] with the default [ReconnectPolicy] and [HeartbeatPolicy]
+    /// that subscribes to the [DepthStream::Partial] stream.
     ///
     /// # Arguments
     ///
     /// * `base_url` - A string that holds the base Url of the exchange.
     /// * `depth` - The maximum orderbook depth that will be streamed by the exchange client.
-    /// * `symbol` - The [Symbol] that the exchange client will subscribe.
+    /// * `instrument` - The [Instrument] that the exchange client will subscribe.
+    ///   It is expected to come from a [data_models::instrument_registry::InstrumentRegistry].
     ///
-    /// This method will panic if:
-    /// (1) the provided base_url is invalid and cannot be parsed
-    /// (2) the provided symbol has not been implemented in [Symbol].
-    pub fn new(base_url: String, depth: usize, symbol: String) -> Self {
+    /// This method will panic if the provided base_url is invalid and cannot be parsed.
+    pub fn new(base_url: String, depth: usize, instrument: Instrument) -> Self {
         ExchangeClientConfig {
             base_url: Url::parse(&base_url).unwrap(),
             depth,
-            symbol
+            instrument,
+            reconnect_policy: ReconnectPolicy::default(),
+            heartbeat: Some(HeartbeatPolicy::default()),
+            round_trip_time: Arc::new(RoundTripTime::default()),
+            depth_stream: DepthStream::Partial,
+            rest_url: None,
+            http_fetcher: Arc::new(ReqwestFetcher::default()),
+            capture: None,
+            replay: None,
         }
     }
+
+    /// Sets the [ReconnectPolicy] the client follows when the connection to the exchange fails.
+    pub fn with_reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = reconnect_policy;
+        self
+    }
+
+    /// Sets the [HeartbeatPolicy] of the connection, or disables the heartbeat if it is [None].
+    pub fn with_heartbeat(mut self, heartbeat: Option<HeartbeatPolicy>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Sets the orderbook stream the client subscribes to.
+    pub fn with_depth_stream(mut self, depth_stream: DepthStream) -> Self {
+        self.depth_stream = depth_stream;
+        self
+    }
+
+    /// Sets the base Url of the REST api of the exchange.
+    ///
+    /// This method will panic if the provided rest_url is invalid and cannot be parsed.
+    pub fn with_rest_url(mut self, rest_url: String) -> Self {
+        self.rest_url = Some(Url::parse(&rest_url).unwrap());
+        self
+    }
+
+    /// Sets the [HttpFetcher] that sends the REST requests of the client.
+    pub fn with_http_fetcher(mut self, http_fetcher: Arc<dyn HttpFetcher>) -> Self {
+        self.http_fetcher = http_fetcher;
+        self
+    }
+
+    /// Sets the [CaptureWriter] the raw frames of the client are written to, or disables capturing if it is [None].
+    pub fn with_capture(mut self, capture: Option<Arc<CaptureWriter>>) -> Self {
+        self.capture = capture;
+        self
+    }
+
+    /// Replays the connections of the given [ReplaySource] instead of connecting to the exchange,
+    /// see [crate::exchange_client::ExchangeClient::replay]. The REST requests of the client are
+    /// served with the captured responses and the heartbeat is disabled.
+    pub fn with_replay(mut self, mut source: ReplaySource) -> Self {
+        self.http_fetcher = Arc::new(ReplayFetcher::new(source.take_responses()));
+        self.heartbeat = None;
+        self.replay = Some(Arc::new(Mutex::new(source)));
+        self
+    }
 }
@@ -0,0 +1,129 @@
+//! The errors returned by the exchange clients implementing [crate::exchange_client::ExchangeClient].
+
+use std::{error, fmt};
+use std::time::Duration;
+
+use tokio::sync::mpsc::error::SendError;
+use tokio_tungstenite::tungstenite;
+
+use data_models::exchange_orderbook::OrderbookSnapshot;
+
+/// Enumeration of the failures of an exchange client.
+///
+/// Only [ExchangeClientError::ChannelClosed] is fatal, see [ExchangeClientError::is_fatal].
+/// The client recovers from every other error, either by skipping the message
+/// or by reconnecting to the exchange.
+#[derive(Debug)]
+pub enum ExchangeClientError {
+    /// The connection to the exchange could not be established.
+    Connect(Box<tungstenite::Error>),
+    /// The subscription message could not be sent, or the exchange did not confirm the subscription.
+    Subscribe(String),
+    /// The established connection failed.
+    Transport(Box<tungstenite::Error>),
+    /// The exchange sent a message that the client does not expect, e.g. a binary frame.
+    Protocol(String),
+    /// A message of the exchange could not be decoded.
+    Decode(Box<dyn error::Error + Send + Sync>),
+    /// A REST request to the exchange failed, e.g. the request for an orderbook snapshot.
+    Fetch(String),
+    /// The updates of the exchange do not follow on from the local orderbook, e.g. because an update was missed.
+    /// The client resynchronizes the local orderbook by reconnecting.
+    OutOfSync(String),
+    /// No message was received within the timeout of the [crate::api::heartbeat::HeartbeatPolicy].
+    HeartbeatTimeout(Duration),
+    /// The exchange asked the client to reconnect, e.g. ahead of a maintenance.
+    ReconnectRequested,
+    /// The receiver of the orderbook snapshots has been dropped.
+    ChannelClosed,
+}
+
+impl ExchangeClientError {
+    /// Returns true if the client cannot recover from the error and has to stop.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, ExchangeClientError::ChannelClosed)
+    }
+
+    /// Constructs a new [ExchangeClientError::Connect] from the given error.
+    pub fn connect(error: tungstenite::Error) -> Self {
+        ExchangeClientError::Connect(Box::new(error))
+    }
+
+    /// Constructs a new [ExchangeClientError::Transport] from the given error.
+    pub fn transport(error: tungstenite::Error) -> Self {
+        ExchangeClientError::Transport(Box::new(error))
+    }
+
+    /// Constructs a new [ExchangeClientError::Decode] from the given error.
+    pub fn decode(error: impl error::Error + Send + Sync + 'static) -> Self {
+        ExchangeClientError::Decode(Box::new(error))
+    }
+
+    /// Constructs a new [ExchangeClientError::Subscribe] for a subscription the exchange rejected.
+    pub fn rejected(reason: impl fmt::Display) -> Self {
+        ExchangeClientError::Subscribe(format!("Subscription rejected : `{}`", reason))
+    }
+}
+
+impl fmt::Display for ExchangeClientError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExchangeClientError::Connect(error) => write!(f, "Connect error: {}", error),
+            ExchangeClientError::Subscribe(reason) => write!(f, "Subscribe error: {}", reason),
+            ExchangeClientError::Transport(error) => write!(f, "Transport error: {}", error),
+            ExchangeClientError::Protocol(reason) => write!(f, "Protocol error: {}", reason),
+            ExchangeClientError::Decode(error) => write!(f, "Decode error: {}", error),
+            ExchangeClientError::Fetch(reason) => write!(f, "Fetch error: {}", reason),
+            ExchangeClientError::OutOfSync(reason) => write!(f, "Out of sync: {}", reason),
+            ExchangeClientError::HeartbeatTimeout(timeout) => write!(f, "No message received within {:?}", timeout),
+            ExchangeClientError::ReconnectRequested => write!(f, "The exchange requested a reconnect"),
+            ExchangeClientError::ChannelClosed => write!(f, "The orderbook snapshot channel is closed"),
+        }
+    }
+}
+
+impl error::Error for ExchangeClientError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ExchangeClientError::Connect(error) | ExchangeClientError::Transport(error) => Some(error.as_ref()),
+            ExchangeClientError::Decode(error) => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ExchangeClientError {
+    fn from(error: serde_json::Error) -> Self {
+        ExchangeClientError::decode(error)
+    }
+}
+
+impl From<SendError<OrderbookSnapshot>> for ExchangeClientError {
+    fn from(_: SendError<OrderbookSnapshot>) -> Self {
+        ExchangeClientError::ChannelClosed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use crate::api::error::ExchangeClientError;
+
+    #[test]
+    fn decode_error_test() {
+        let json_error = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        let error = ExchangeClientError::from(json_error);
+
+        assert!(!error.is_fatal());
+        assert!(error.to_string().starts_with("Decode error: "));
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn channel_closed_is_fatal_test() {
+        assert!(ExchangeClientError::ChannelClosed.is_fatal());
+        assert!(!ExchangeClientError::Protocol("binary frame".to_string()).is_fatal());
+        assert!(!ExchangeClientError::ReconnectRequested.is_fatal());
+    }
+}
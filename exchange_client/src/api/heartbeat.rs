@@ -0,0 +1,183 @@
+//! The heartbeat an exchange client keeps with the exchange to detect dead connections.
+//! The [HeartbeatPolicy] is part of the [crate::api::configuration::ExchangeClientConfig] of each client.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::Message;
+
+/// The client pings the exchange every [HeartbeatPolicy::ping_interval] and expects a pong
+/// or any other message within [HeartbeatPolicy::timeout] of the previous message.
+/// Otherwise the connection is considered dead and the client reconnects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    pub ping_interval: Duration,
+    pub timeout: Duration,
+}
+
+impl HeartbeatPolicy {
+    /// Constructs a new [HeartbeatPolicy].
+    ///
+    /// # Arguments
+    ///
+    /// * `ping_interval` - The interval between two pings.
+    /// * `timeout` - The maximum time without any message from the exchange.
+    ///   It should be longer than the ping interval.
+    pub fn new(ping_interval: Duration, timeout: Duration) -> Self {
+        HeartbeatPolicy { ping_interval, timeout }
+    }
+}
+
+impl Default for HeartbeatPolicy {
+    /// Pings every 15s and reconnects after 45s without any message.
+    fn default() -> Self {
+        HeartbeatPolicy::new(Duration::from_secs(15), Duration::from_secs(45))
+    }
+}
+
+/// The round trip time of the pings of an exchange client, shared with the caller for monitoring.
+#[derive(Debug, Default)]
+pub struct RoundTripTime {
+    last_micros: AtomicU64,
+    pongs: AtomicU64,
+}
+
+impl RoundTripTime {
+    /// Returns the round trip time of the latest answered ping, or [None] if no ping has been answered.
+    pub fn last(&self) -> Option<Duration> {
+        match self.pongs() {
+            0 => None,
+            _ => Some(Duration::from_micros(self.last_micros.load(Ordering::Relaxed))),
+        }
+    }
+
+    /// Returns the number of answered pings.
+    pub fn pongs(&self) -> u64 {
+        self.pongs.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record(&self, round_trip_time: Duration) {
+        self.last_micros.store(round_trip_time.as_micros() as u64, Ordering::Relaxed);
+        self.pongs.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Tracks the pings sent and the messages received on a single connection according to a [HeartbeatPolicy].
+pub(crate) struct Heartbeat {
+    policy: HeartbeatPolicy,
+    interval: Interval,
+    last_message: Instant,
+    pings: u64,
+    ping_sent: Option<Instant>,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(policy: &HeartbeatPolicy) -> Self {
+        let now = Instant::now();
+        let mut interval = tokio::time::interval_at(now + policy.ping_interval, policy.ping_interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Heartbeat {
+            policy: policy.clone(),
+            interval,
+            last_message: now,
+            pings: 0,
+            ping_sent: None,
+        }
+    }
+
+    /// Returns the time by which the next message has to be received.
+    pub(crate) fn deadline(&self) -> Instant {
+        self.last_message + self.policy.timeout
+    }
+
+    /// Waits for the next ping interval and returns the ping to send.
+    /// The payload of the ping is its sequence number, which the exchange echoes in the pong.
+    pub(crate) async fn next_ping(&mut self) -> Message {
+        self.interval.tick().await;
+        self.pings += 1;
+        self.ping_sent = Some(Instant::now());
+        Message::Ping(self.pings.to_be_bytes().to_vec())
+    }
+
+    /// Called for every message received from the exchange.
+    /// Returns the round trip time if the message is the pong frame of the latest ping.
+    pub(crate) fn on_message(&mut self, message: &Message) -> Option<Duration> {
+        self.last_message = Instant::now();
+
+        match message {
+            Message::Pong(payload) => payload.as_slice().try_into().ok()
+                .and_then(|payload| self.on_pong(u64::from_be_bytes(payload))),
+            _ => None,
+        }
+    }
+
+    /// Called for a pong that answers the ping with the given sequence number, either a pong frame
+    /// or an application level pong, e.g. the `{"id": <n>, "type": "pong"}` of KuCoin.
+    /// Returns the round trip time if the pong answers the latest ping.
+    pub(crate) fn on_pong(&mut self, sequence: u64) -> Option<Duration> {
+        if sequence != self.pings {
+            return None;
+        }
+        self.ping_sent.take().map(|ping_sent| self.last_message - ping_sent)
+    }
+}
+
+/// Waits for the next ping of the given [Heartbeat], or forever if the heartbeat is disabled.
+pub(crate) async fn next_ping(heartbeat: &mut Option<Heartbeat>) -> Message {
+    match heartbeat {
+        Some(heartbeat) => heartbeat.next_ping().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use crate::api::heartbeat::{Heartbeat, HeartbeatPolicy, RoundTripTime};
+
+    #[tokio::test(start_paused = true)]
+    async fn round_trip_time_test() {
+        let start = Instant::now();
+        let mut heartbeat = Heartbeat::new(&HeartbeatPolicy::new(Duration::from_secs(10), Duration::from_secs(30)));
+        assert_eq!(heartbeat.deadline(), start + Duration::from_secs(30));
+
+        let ping = heartbeat.next_ping().await;
+        assert_eq!(Instant::now(), start + Duration::from_secs(10));
+        assert_eq!(ping, Message::Ping(1u64.to_be_bytes().to_vec()));
+
+        tokio::time::advance(Duration::from_millis(20)).await;
+        // a pong that does not answer the latest ping only extends the deadline
+        assert_eq!(heartbeat.on_message(&Message::Pong(vec![])), None);
+        assert_eq!(heartbeat.deadline(), start + Duration::from_millis(40_020));
+
+        tokio::time::advance(Duration::from_millis(5)).await;
+        assert_eq!(heartbeat.on_message(&Message::Pong(1u64.to_be_bytes().to_vec())), Some(Duration::from_millis(25)));
+        assert_eq!(heartbeat.on_message(&Message::Pong(1u64.to_be_bytes().to_vec())), None);
+
+        heartbeat.next_ping().await;
+        tokio::time::advance(Duration::from_millis(7)).await;
+        heartbeat.on_message(&Message::text(r#"{"id":"2","type":"pong"}"#));
+        // an application level pong of an earlier ping is ignored
+        assert_eq!(heartbeat.on_pong(1), None);
+        assert_eq!(heartbeat.on_pong(2), Some(Duration::from_millis(7)));
+        assert_eq!(heartbeat.on_pong(2), None);
+    }
+
+    #[test]
+    fn record_round_trip_time_test() {
+        let round_trip_time = RoundTripTime::default();
+        assert_eq!(round_trip_time.last(), None);
+
+        round_trip_time.record(Duration::from_millis(3));
+        round_trip_time.record(Duration::from_millis(5));
+
+        assert_eq!(round_trip_time.last(), Some(Duration::from_millis(5)));
+        assert_eq!(round_trip_time.pongs(), 2);
+    }
+}
@@ -0,0 +1,60 @@
+//! The HTTP client of the exchange clients that request data over REST, e.g. orderbook snapshots or connection tokens.
+//! The default [ReqwestFetcher] can be replaced through [crate::api::configuration::ExchangeClientConfig::with_http_fetcher],
+//! e.g. by a stub that serves recorded responses in tests.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use reqwest::Method;
+use url::Url;
+
+use crate::api::error::ExchangeClientError;
+
+/// The future returned by an [HttpFetcher], which resolves to the body of the response.
+pub type FetchFuture<'a> = Pin<Box<dyn Future<Output=Result<String, ExchangeClientError>> + Send + 'a>>;
+
+/// Sends the REST requests of an exchange client.
+pub trait HttpFetcher: Send + Sync {
+    /// Sends a GET request and returns the body of the response.
+    /// Returns [ExchangeClientError::Fetch] if the request fails or the response status is not successful.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The [Url] of the request, including its query.
+    fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a>;
+
+    /// Sends a POST request without a body and returns the body of the response.
+    /// Returns [ExchangeClientError::Fetch] if the request fails or the response status is not successful.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The [Url] of the request, including its query.
+    fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a>;
+}
+
+/// An [HttpFetcher] that sends the requests with [reqwest].
+#[derive(Default)]
+pub struct ReqwestFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpFetcher for ReqwestFetcher {
+    fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+        Box::pin(self.send(Method::GET, url))
+    }
+
+    fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+        Box::pin(self.send(Method::POST, url))
+    }
+}
+
+impl ReqwestFetcher {
+    async fn send(&self, method: Method, url: &Url) -> Result<String, ExchangeClientError> {
+        let response = self.client.request(method.clone(), url.clone()).send().await
+            .and_then(|response| response.error_for_status())
+            .map_err(|error| ExchangeClientError::Fetch(format!("{} `{}` failed : `{}`", method, url, error)))?;
+
+        response.text().await
+            .map_err(|error| ExchangeClientError::Fetch(format!("Could not read the response of `{}` : `{}`", url, error)))
+    }
+}
@@ -0,0 +1,340 @@
+//! A bounded, conflating channel between the exchange clients and the consumer of their [ExchangeEvent]s.
+//!
+//! The mailbox keeps only the latest pending snapshot of each exchange and symbol. A newer snapshot replaces the
+//! pending one, which is counted as dropped, so a consumer that falls behind always receives the freshest orderbooks
+//! and the memory of the mailbox is bounded by the number of exchanges and symbols instead of by the backlog.
+//!
+//! Connection events are never conflated and are received before the pending snapshots. An event of a client that is
+//! no longer subscribed drops the pending snapshot of its exchange and symbol, which was published on the lost connection.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::Notify;
+
+use data_models::connection_event::ConnectionEvent;
+use data_models::exchange::Exchange;
+use data_models::exchange_event::ExchangeEvent;
+use data_models::exchange_orderbook::OrderbookSnapshot;
+
+/// The exchange and symbol of a snapshot, only the latest snapshot of each key is kept.
+type Key = (Exchange, String);
+
+/// The number of pending connection events per exchange client, older events are dropped if it is exceeded.
+const EVENTS_PER_CLIENT: usize = 16;
+
+/// Creates a new mailbox and returns its sender and receiver.
+///
+/// # Arguments
+///
+/// * `capacity` - The maximum number of exchanges and symbols with a pending snapshot, e.g. the number of
+///   exchange clients. If it is exceeded, the oldest pending snapshot is dropped.
+///
+/// This method will panic if the capacity is zero.
+pub fn channel(capacity: usize) -> (MailboxSender, MailboxReceiver) {
+    assert!(capacity > 0, "The capacity of the mailbox must be positive");
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            pending: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+            events: VecDeque::new(),
+            dropped: HashMap::new(),
+        }),
+        capacity,
+        notify: Notify::new(),
+        senders: AtomicUsize::new(1),
+        receiver_closed: AtomicBool::new(false),
+    });
+
+    (MailboxSender { shared: shared.clone() }, MailboxReceiver { shared })
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    notify: Notify,
+    senders: AtomicUsize,
+    receiver_closed: AtomicBool,
+}
+
+struct State {
+    pending: HashMap<Key, OrderbookSnapshot>,
+    /// The keys of the pending snapshots, in the order their first pending snapshot was sent.
+    order: VecDeque<Key>,
+    events: VecDeque<ConnectionEvent>,
+    dropped: HashMap<Key, u64>,
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The sending half of the mailbox, cloned for every exchange client.
+pub struct MailboxSender {
+    shared: Arc<Shared>,
+}
+
+impl MailboxSender {
+    /// Sends an orderbook snapshot, replacing the pending snapshot of the same exchange and symbol.
+    /// Never waits for the consumer.
+    ///
+    /// Returns the snapshot in a [SendError] if the [MailboxReceiver] has been dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The [OrderbookSnapshot] to send.
+    pub fn send(&self, snapshot: OrderbookSnapshot) -> Result<(), SendError<OrderbookSnapshot>> {
+        if self.shared.receiver_closed.load(Ordering::Acquire) {
+            return Err(SendError(snapshot));
+        }
+
+        let key = (snapshot.exchange, snapshot.symbol.clone());
+        let mut state = self.shared.state();
+
+        if state.pending.insert(key.clone(), snapshot).is_some() {
+            *state.dropped.entry(key).or_default() += 1;
+        } else {
+            if state.order.len() == self.shared.capacity {
+                if let Some(oldest) = state.order.pop_front() {
+                    state.pending.remove(&oldest);
+                    *state.dropped.entry(oldest).or_default() += 1;
+                }
+            }
+            state.order.push_back(key);
+        }
+
+        drop(state);
+        self.shared.notify.notify_one();
+        Ok(())
+    }
+
+    /// Sends a connection event. If the client is no longer subscribed, the pending snapshot
+    /// of the same exchange and symbol is dropped.
+    /// Never waits for the consumer.
+    ///
+    /// Returns the event in a [SendError] if the [MailboxReceiver] has been dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The [ConnectionEvent] to send.
+    pub fn send_event(&self, event: ConnectionEvent) -> Result<(), SendError<ConnectionEvent>> {
+        if self.shared.receiver_closed.load(Ordering::Acquire) {
+            return Err(SendError(event));
+        }
+
+        let key = (event.exchange, event.symbol.clone());
+        let mut state = self.shared.state();
+
+        // The pending snapshot of a client that is no longer subscribed is outdated
+        if !event.state.is_subscribed() && state.pending.remove(&key).is_some() {
+            state.order.retain(|pending| *pending != key);
+            *state.dropped.entry(key).or_default() += 1;
+        }
+        if state.events.len() == self.shared.capacity * EVENTS_PER_CLIENT {
+            state.events.pop_front();
+        }
+        state.events.push_back(event);
+
+        drop(state);
+        self.shared.notify.notify_one();
+        Ok(())
+    }
+}
+
+impl Clone for MailboxSender {
+    fn clone(&self) -> Self {
+        self.shared.senders.fetch_add(1, Ordering::AcqRel);
+        MailboxSender { shared: self.shared.clone() }
+    }
+}
+
+impl Drop for MailboxSender {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            // Wakes the receiver, so that it returns [None] once the pending snapshots are received
+            self.shared.notify.notify_one();
+        }
+    }
+}
+
+/// The receiving half of the mailbox.
+pub struct MailboxReceiver {
+    shared: Arc<Shared>,
+}
+
+impl MailboxReceiver {
+    /// Receives the pending connection event or snapshot that was sent first, waiting until one is sent.
+    /// Connection events are received before snapshots.
+    /// Returns [None] once all senders have been dropped and no event is pending.
+    pub async fn recv(&mut self) -> Option<ExchangeEvent> {
+        loop {
+            if let Some(event) = self.try_recv() {
+                return Some(event);
+            }
+            if self.shared.senders.load(Ordering::Acquire) == 0 {
+                // A snapshot may have been sent right before the last sender was dropped
+                return self.try_recv();
+            }
+            self.shared.notify.notified().await;
+        }
+    }
+
+    /// Receives the pending connection event or snapshot that was sent first, or returns [None] if no event is pending.
+    pub fn try_recv(&mut self) -> Option<ExchangeEvent> {
+        let mut state = self.shared.state();
+        if let Some(event) = state.events.pop_front() {
+            return Some(ExchangeEvent::Connection(event));
+        }
+        let key = state.order.pop_front()?;
+        state.pending.remove(&key).map(ExchangeEvent::Snapshot)
+    }
+
+    /// Receives the next snapshot, skipping connection events.
+    #[cfg(test)]
+    pub(crate) async fn recv_snapshot(&mut self) -> Option<OrderbookSnapshot> {
+        loop {
+            match self.recv().await? {
+                ExchangeEvent::Snapshot(snapshot) => return Some(snapshot),
+                ExchangeEvent::Connection(_) => continue,
+            }
+        }
+    }
+
+    /// Returns the number of snapshots of the given exchange and symbol that were replaced
+    /// by a newer snapshot before they were received.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The [Exchange] of the snapshots.
+    /// * `symbol` - The canonical symbol of the snapshots.
+    pub fn dropped(&self, exchange: Exchange, symbol: &str) -> u64 {
+        self.shared.state().dropped.get(&(exchange, symbol.to_string())).copied().unwrap_or_default()
+    }
+
+    /// Returns the number of dropped snapshots of every exchange and symbol that dropped at least one.
+    pub fn dropped_all(&self) -> Vec<(Exchange, String, u64)> {
+        let mut dropped: Vec<(Exchange, String, u64)> = self.shared.state().dropped.iter()
+            .map(|((exchange, symbol), count)| (*exchange, symbol.clone(), *count))
+            .collect();
+        dropped.sort();
+        dropped
+    }
+}
+
+impl Drop for MailboxReceiver {
+    fn drop(&mut self) {
+        self.shared.receiver_closed.store(true, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use data_models::connection_event::ConnectionEvent;
+    use data_models::connection_state::ConnectionState;
+    use data_models::exchange::Exchange;
+    use data_models::exchange_event::ExchangeEvent;
+    use data_models::exchange_orderbook::OrderbookSnapshot;
+    use data_models::levels::Levels;
+
+    use crate::api::mailbox::channel;
+
+    fn snapshot(exchange: Exchange, symbol: &str, sequence: u64) -> OrderbookSnapshot {
+        OrderbookSnapshot::new(exchange, symbol.to_string(), Levels::new(vec![], vec![])).with_sequence(Some(sequence))
+    }
+
+    #[tokio::test]
+    async fn conflates_snapshots_test() {
+        let (sender, mut receiver) = channel(4);
+
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).unwrap();
+        sender.send(snapshot(Exchange::Bitstamp, "ethbtc", 1)).unwrap();
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 2)).unwrap();
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 3)).unwrap();
+        sender.send(snapshot(Exchange::Binance, "btcusdt", 1)).unwrap();
+
+        // The latest snapshot of each exchange and symbol, in the order the first pending snapshot was sent
+        let received = receiver.recv_snapshot().await.unwrap();
+        assert_eq!((received.exchange, received.sequence), (Exchange::Binance, Some(3)));
+        let received = receiver.recv_snapshot().await.unwrap();
+        assert_eq!((received.exchange, received.sequence), (Exchange::Bitstamp, Some(1)));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().symbol, "btcusdt");
+        assert!(receiver.try_recv().is_none());
+
+        assert_eq!(receiver.dropped(Exchange::Binance, "ethbtc"), 2);
+        assert_eq!(receiver.dropped(Exchange::Bitstamp, "ethbtc"), 0);
+        assert_eq!(receiver.dropped_all(), vec![(Exchange::Binance, "ethbtc".to_string(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn drops_oldest_snapshot_over_capacity_test() {
+        let (sender, mut receiver) = channel(2);
+
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).unwrap();
+        sender.send(snapshot(Exchange::Bitstamp, "ethbtc", 1)).unwrap();
+        sender.send(snapshot(Exchange::Binance, "btcusdt", 1)).unwrap();
+
+        assert_eq!(receiver.recv_snapshot().await.unwrap().exchange, Exchange::Bitstamp);
+        assert_eq!(receiver.recv_snapshot().await.unwrap().symbol, "btcusdt");
+        assert_eq!(receiver.dropped(Exchange::Binance, "ethbtc"), 1);
+    }
+
+    #[tokio::test]
+    async fn closes_test() {
+        let (sender, mut receiver) = channel(2);
+        let other_sender = sender.clone();
+
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).unwrap();
+        drop(sender);
+        drop(other_sender);
+        // The pending snapshot is received before the closed mailbox returns None
+        assert!(receiver.recv_snapshot().await.is_some());
+        assert!(receiver.recv_snapshot().await.is_none());
+
+        let (sender, receiver) = channel(2);
+        drop(receiver);
+        assert!(sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn wakes_receiver_test() {
+        let (sender, mut receiver) = channel(2);
+
+        let received = tokio::spawn(async move { receiver.recv_snapshot().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).unwrap();
+
+        assert_eq!(received.await.unwrap().unwrap().sequence, Some(1));
+    }
+
+    #[tokio::test]
+    async fn connection_events_test() {
+        let (sender, mut receiver) = channel(2);
+
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 1)).unwrap();
+        sender.send(snapshot(Exchange::Bitstamp, "ethbtc", 1)).unwrap();
+        sender.send_event(ConnectionEvent::new(Exchange::Binance, "ethbtc".to_string(), ConnectionState::Disconnected)).unwrap();
+        sender.send_event(ConnectionEvent::new(Exchange::Binance, "ethbtc".to_string(), ConnectionState::Connecting)).unwrap();
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 2)).unwrap();
+
+        // Connection events are received first and in order, the snapshot of the previous connection is dropped
+        assert!(matches!(receiver.recv().await, Some(ExchangeEvent::Connection(event)) if event.state == ConnectionState::Disconnected));
+        assert!(matches!(receiver.recv().await, Some(ExchangeEvent::Connection(event)) if event.state == ConnectionState::Connecting));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().exchange, Exchange::Bitstamp);
+        assert_eq!(receiver.recv_snapshot().await.unwrap().sequence, Some(2));
+        assert_eq!(receiver.dropped(Exchange::Binance, "ethbtc"), 1);
+
+        // The snapshots published while subscribing are kept
+        sender.send(snapshot(Exchange::Binance, "ethbtc", 3)).unwrap();
+        sender.send_event(ConnectionEvent::new(Exchange::Binance, "ethbtc".to_string(), ConnectionState::Subscribed)).unwrap();
+        assert!(matches!(receiver.recv().await, Some(ExchangeEvent::Connection(event)) if event.state == ConnectionState::Subscribed));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().sequence, Some(3));
+        assert_eq!(receiver.dropped(Exchange::Binance, "ethbtc"), 1);
+    }
+}
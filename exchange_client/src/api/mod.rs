@@ -1,2 +1,9 @@
+pub mod capture;
 pub mod configuration;
+pub mod error;
+pub mod heartbeat;
+pub mod http_fetcher;
+pub mod mailbox;
 pub mod provider;
+pub mod reconnect_policy;
+pub mod replay;
\ No newline at end of file
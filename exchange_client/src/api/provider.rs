@@ -1,10 +1,13 @@
 //! A provider that starts a new exchange client.
 //! This is the main entry point for any caller that wants to start a new client.
 
-use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio::task::JoinHandle;
 
-use data_models::{exchange::Exchange, exchange_orderbook::OrderbookSnapshot};
+use data_models::exchange::Exchange;
 
+use crate::api::error::ExchangeClientError;
+use crate::api::mailbox::MailboxSender;
+use crate::api::replay::ReplaySource;
 use crate::exchange_client::ExchangeClient;
 
 use super::configuration::ExchangeClientConfig;
@@ -19,15 +22,18 @@ use super::configuration::ExchangeClientConfig;
 ///
 /// * `exchange` - The given [Exchange].
 /// * `client_config` - The [ExchangeClientConfig] of the client.
-/// * `sender` - The [`UnboundedSender<OrderbookSnapshot>`] channel where the client will publish
-/// orderbook updates.
+/// * `sender` - The [MailboxSender] of the mailbox where the client will publish orderbook updates,
+///   see [crate::api::mailbox::channel].
+///
+/// Returns the [JoinHandle] of the client task. The task only completes if the client
+/// stopped because of a fatal [ExchangeClientError], see [ExchangeClientError::is_fatal].
 ///
 /// The method will panic if a client implementation for the provided exchange does not exist.
 pub fn start(
     exchange: Exchange,
     client_config: ExchangeClientConfig,
-    sender: UnboundedSender<OrderbookSnapshot>,
-) -> JoinHandle<()> {
+    sender: MailboxSender,
+) -> JoinHandle<Result<(), ExchangeClientError>> {
     match exchange {
         Exchange::Binance => {
             let client = crate::implementation::binance::client::Binance::new(client_config, sender);
@@ -37,5 +43,80 @@ pub fn start(
             let client = crate::implementation::bitstamp::client::Bitstamp::new(client_config, sender);
             tokio::spawn(async move { client.start().await })
         }
+        Exchange::Bitfinex => {
+            let client = crate::implementation::bitfinex::client::Bitfinex::new(client_config, sender);
+            tokio::spawn(async move { client.start().await })
+        }
+        Exchange::Coinbase => {
+            let client = crate::implementation::coinbase::client::Coinbase::new(client_config, sender);
+            tokio::spawn(async move { client.start().await })
+        }
+        Exchange::Huobi => {
+            let client = crate::implementation::huobi::client::Huobi::new(client_config, sender);
+            tokio::spawn(async move { client.start().await })
+        }
+        Exchange::Kraken => {
+            let client = crate::implementation::kraken::client::Kraken::new(client_config, sender);
+            tokio::spawn(async move { client.start().await })
+        }
+        Exchange::Kucoin => {
+            let client = crate::implementation::kucoin::client::Kucoin::new(client_config, sender);
+            tokio::spawn(async move { client.start().await })
+        }
+    }
+}
+
+/// Calling this method will:
+///
+/// (1) Instantiate a new [ExchangeClient] that replays the given source, see [ExchangeClientConfig::with_replay]
+///
+/// (2) Replay the captured connections through the client, see [ExchangeClient::replay]
+///
+/// # Arguments
+///
+/// * `exchange` - The given [Exchange].
+/// * `client_config` - The [ExchangeClientConfig] of the client that was captured.
+/// * `sender` - The [MailboxSender] of the mailbox where the client will publish orderbook updates.
+/// * `source` - The [ReplaySource] of the captured connections and REST responses.
+///
+/// Returns the [JoinHandle] of the client task, which completes once the source has been replayed.
+///
+/// The method will panic if a client implementation for the provided exchange does not exist.
+pub fn replay(
+    exchange: Exchange,
+    client_config: ExchangeClientConfig,
+    sender: MailboxSender,
+    source: ReplaySource,
+) -> JoinHandle<Result<(), ExchangeClientError>> {
+    let client_config = client_config.with_replay(source);
+    match exchange {
+        Exchange::Binance => {
+            let client = crate::implementation::binance::client::Binance::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Bitstamp => {
+            let client = crate::implementation::bitstamp::client::Bitstamp::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Bitfinex => {
+            let client = crate::implementation::bitfinex::client::Bitfinex::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Coinbase => {
+            let client = crate::implementation::coinbase::client::Coinbase::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Huobi => {
+            let client = crate::implementation::huobi::client::Huobi::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Kraken => {
+            let client = crate::implementation::kraken::client::Kraken::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
+        Exchange::Kucoin => {
+            let client = crate::implementation::kucoin::client::Kucoin::new(client_config, sender);
+            tokio::spawn(async move { client.replay().await })
+        }
     }
 }
@@ -0,0 +1,181 @@
+//! The policy an exchange client follows to reconnect after the connection to the exchange failed.
+//! It is part of the [crate::api::configuration::ExchangeClientConfig] of each client.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff with jitter and a circuit breaker.
+///
+/// After each consecutive failed connection attempt the retry interval is multiplied by
+/// [ReconnectPolicy::multiplier], up to [ReconnectPolicy::max_interval]. Each interval is
+/// randomized by up to [ReconnectPolicy::jitter] of its length, so that clients that lost
+/// their connection at the same time do not reconnect at the same time.
+///
+/// After [ReconnectPolicy::max_failures] consecutive failures the circuit opens, and the
+/// client waits [ReconnectPolicy::circuit_open_interval] before every further attempt,
+/// until one of them succeeds.
+///
+/// A connection that drops within [ReconnectPolicy::min_connected_time] counts as a failed attempt,
+/// so that an exchange that accepts the connection and then drops it is not reconnected in a tight loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_interval: Duration,
+    pub max_interval: Duration,
+    pub multiplier: f64,
+    /// The maximum fraction, between 0.0 and 1.0, by which an interval is randomized.
+    pub jitter: f64,
+    pub max_failures: u32,
+    pub circuit_open_interval: Duration,
+    /// The time a connection has to stay up before the backoff is restarted.
+    pub min_connected_time: Duration,
+}
+
+impl ReconnectPolicy {
+    /// Constructs a new [ReconnectPolicy].
+    ///
+    /// # Arguments
+    ///
+    /// * `initial_interval` - The interval before the first retry.
+    /// * `max_interval` - The maximum interval between two retries while the circuit is closed.
+    /// * `multiplier` - The factor the interval grows by after every failed attempt.
+    /// * `jitter` - The maximum fraction, between 0.0 and 1.0, by which an interval is randomized.
+    /// * `max_failures` - The number of consecutive failures after which the circuit opens.
+    /// * `circuit_open_interval` - The interval between two retries while the circuit is open.
+    pub fn new(initial_interval: Duration,
+               max_interval: Duration,
+               multiplier: f64,
+               jitter: f64,
+               max_failures: u32,
+               circuit_open_interval: Duration) -> Self {
+        ReconnectPolicy {
+            initial_interval,
+            max_interval,
+            multiplier,
+            jitter: jitter.clamp(0.0, 1.0),
+            max_failures,
+            circuit_open_interval,
+            min_connected_time: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the time a connection has to stay up before the backoff is restarted, 10s by default.
+    pub fn with_min_connected_time(mut self, min_connected_time: Duration) -> Self {
+        self.min_connected_time = min_connected_time;
+        self
+    }
+}
+
+impl Default for ReconnectPolicy {
+    /// Retries after 500ms, doubling the interval up to 30s,
+    /// and opens the circuit for 5 minutes after 10 consecutive failures.
+    /// Connections that drop within 10s count as failures.
+    fn default() -> Self {
+        ReconnectPolicy::new(
+            Duration::from_millis(500),
+            Duration::from_secs(30),
+            2.0,
+            0.2,
+            10,
+            Duration::from_secs(300),
+        )
+    }
+}
+
+/// The delay before the next connection attempt, returned by [Backoff::on_failure].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) enum Retry {
+    /// Retry after the given delay.
+    After(Duration),
+    /// The circuit is open, retry after the given delay.
+    CircuitOpen(Duration),
+}
+
+/// Tracks the consecutive failed connection attempts of a client according to a [ReconnectPolicy].
+pub(crate) struct Backoff {
+    policy: ReconnectPolicy,
+    failures: u32,
+    interval: Duration,
+}
+
+impl Backoff {
+    pub(crate) fn new(policy: ReconnectPolicy) -> Self {
+        let interval = policy.initial_interval;
+        Backoff { policy, failures: 0, interval }
+    }
+
+    /// Returns the number of consecutive failed connection attempts.
+    pub(crate) fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Called when a connection attempt failed.
+    /// Returns when the next attempt should be made.
+    pub(crate) fn on_failure(&mut self) -> Retry {
+        self.failures += 1;
+
+        if self.failures >= self.policy.max_failures {
+            return Retry::CircuitOpen(self.with_jitter(self.policy.circuit_open_interval));
+        }
+
+        let delay = self.with_jitter(self.interval);
+        self.interval = self.interval.mul_f64(self.policy.multiplier).min(self.policy.max_interval);
+        Retry::After(delay)
+    }
+
+    /// Called when a connection stayed up for [ReconnectPolicy::min_connected_time]. Closes the circuit and restarts the backoff.
+    pub(crate) fn reset(&mut self) {
+        self.failures = 0;
+        self.interval = self.policy.initial_interval;
+    }
+
+    fn with_jitter(&self, interval: Duration) -> Duration {
+        if self.policy.jitter == 0.0 {
+            return interval;
+        }
+        let jitter = rand::thread_rng().gen_range(-self.policy.jitter..=self.policy.jitter);
+        interval.mul_f64(1.0 + jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::api::reconnect_policy::{Backoff, ReconnectPolicy, Retry};
+
+    fn policy(jitter: f64) -> ReconnectPolicy {
+        ReconnectPolicy::new(Duration::from_secs(1), Duration::from_secs(5), 2.0, jitter, 5, Duration::from_secs(60))
+    }
+
+    #[test]
+    fn exponential_backoff_test() {
+        let mut backoff = Backoff::new(policy(0.0));
+
+        assert_eq!(backoff.on_failure(), Retry::After(Duration::from_secs(1)));
+        assert_eq!(backoff.on_failure(), Retry::After(Duration::from_secs(2)));
+        assert_eq!(backoff.on_failure(), Retry::After(Duration::from_secs(4)));
+        // capped at the max interval
+        assert_eq!(backoff.on_failure(), Retry::After(Duration::from_secs(5)));
+        assert_eq!(backoff.on_failure(), Retry::CircuitOpen(Duration::from_secs(60)));
+        assert_eq!(backoff.on_failure(), Retry::CircuitOpen(Duration::from_secs(60)));
+        assert_eq!(backoff.failures(), 6);
+
+        backoff.reset();
+        assert_eq!(backoff.failures(), 0);
+        assert_eq!(backoff.on_failure(), Retry::After(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn jitter_test() {
+        let mut backoff = Backoff::new(policy(0.5));
+
+        for _ in 0..100 {
+            match backoff.on_failure() {
+                Retry::After(delay) => assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1500)),
+                Retry::CircuitOpen(_) => panic!("The circuit should be closed"),
+            }
+            backoff.reset();
+        }
+    }
+}
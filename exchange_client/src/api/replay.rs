@@ -0,0 +1,350 @@
+//! Replays a capture written by [crate::api::capture::CaptureWriter], e.g. to reproduce a production incident
+//! offline or to benchmark the pipeline deterministically.
+//!
+//! A [ReplaySource] replays the captured connections of a client through the same handshake and
+//! [crate::exchange_client::ExchangeClient::process_stream] as its live connections, while a [ReplayFetcher]
+//! serves the captured REST responses, see [crate::api::provider::replay].
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+use tokio::time::{Duration, Instant, Sleep};
+use url::Url;
+
+use data_models::exchange::Exchange;
+
+use crate::api::capture::{self, CaptureRecord, Payload};
+use crate::api::error::ExchangeClientError;
+use crate::api::http_fetcher::{FetchFuture, HttpFetcher};
+use crate::client_re_exports::{Error, Message};
+
+/// Enumeration of the speeds a capture can be replayed at.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ReplaySpeed {
+    /// The frames are replayed with the intervals they were received at.
+    Original,
+    /// The intervals between the frames are divided by the multiple, e.g. 10 replays ten times faster.
+    Multiple(f64),
+    /// The frames are replayed without waiting.
+    AsFastAsPossible,
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<ReplaySpeed, Self::Err> {
+        match input {
+            "original" => Ok(ReplaySpeed::Original),
+            "max" => Ok(ReplaySpeed::AsFastAsPossible),
+            multiple => match multiple.parse::<f64>() {
+                Ok(multiple) if multiple.is_finite() && multiple > 0.0 => Ok(ReplaySpeed::Multiple(multiple)),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplaySpeed::Original => write!(f, "original"),
+            ReplaySpeed::Multiple(multiple) => write!(f, "{}", multiple),
+            ReplaySpeed::AsFastAsPossible => write!(f, "max"),
+        }
+    }
+}
+
+/// The captured connections of one exchange and symbol. Each connection is a stream of its frames,
+/// paced according to a [ReplaySpeed], see [ReplaySource::next_connection].
+pub struct ReplaySource {
+    /// The received timestamp and the frame of each record, [None] marks the start of a connection.
+    frames: VecDeque<(u64, Option<Message>)>,
+    /// The url and the body of the captured REST responses, see [ReplayFetcher].
+    responses: Vec<(String, String)>,
+    /// Whether a connection is replayed.
+    connected: bool,
+    speed: ReplaySpeed,
+    /// The received timestamp of the first frame and the instant it was replayed at.
+    origin: Option<(u64, Instant)>,
+    /// The frame that is replayed once the delay has elapsed.
+    pending: Option<Message>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl ReplaySource {
+    /// Constructs a new [ReplaySource] from the given records.
+    ///
+    /// # Arguments
+    ///
+    /// * `records` - The [CaptureRecord]s to replay, in the order they were received.
+    /// * `speed` - The [ReplaySpeed] of the replay.
+    pub fn new(records: Vec<CaptureRecord>, speed: ReplaySpeed) -> Self {
+        let mut frames = VecDeque::new();
+        let mut responses = vec![];
+        for record in records {
+            match record.payload {
+                Payload::Connection => frames.push_back((record.received_timestamp, None)),
+                Payload::Frame(message) => frames.push_back((record.received_timestamp, Some(message))),
+                Payload::Response { url, body } => responses.push((url, body)),
+            }
+        }
+
+        ReplaySource {
+            frames,
+            responses,
+            connected: false,
+            speed,
+            origin: None,
+            pending: None,
+            delay: None,
+        }
+    }
+
+    /// Reads the frames of an exchange and symbol from a capture file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the capture file.
+    /// * `exchange` - The [Exchange] of the frames to replay.
+    /// * `symbol` - The canonical symbol of the frames to replay.
+    /// * `speed` - The [ReplaySpeed] of the replay.
+    pub fn open(path: impl AsRef<Path>, exchange: Exchange, symbol: &str, speed: ReplaySpeed) -> io::Result<Self> {
+        let records = capture::read(path)?
+            .into_iter()
+            .filter(|record| record.exchange == exchange && record.symbol == symbol)
+            .collect();
+        Ok(ReplaySource::new(records, speed))
+    }
+
+    /// Moves to the next captured connection, skipping the frames of the current connection that were not replayed.
+    /// The frames captured before the first connection record, e.g. of a capture without connection records,
+    /// belong to the first connection.
+    /// Returns false once every connection has been replayed.
+    pub fn next_connection(&mut self) -> bool {
+        self.pending = None;
+        self.delay = None;
+        if self.connected {
+            while matches!(self.frames.front(), Some((_, Some(_)))) {
+                self.frames.pop_front();
+            }
+        }
+        self.connected = !self.frames.is_empty();
+        if matches!(self.frames.front(), Some((_, None))) {
+            self.frames.pop_front();
+        }
+        self.connected
+    }
+
+    /// Takes the captured REST responses, which are served by a [ReplayFetcher].
+    pub(crate) fn take_responses(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Returns the instant a frame received at the given timestamp is due, or [None] if it is due immediately.
+    fn due(&mut self, received_timestamp: u64) -> Option<Instant> {
+        let multiple = match self.speed {
+            ReplaySpeed::Original => 1.0,
+            ReplaySpeed::Multiple(multiple) => multiple,
+            ReplaySpeed::AsFastAsPossible => return None,
+        };
+        let (first_timestamp, start) = *self.origin.get_or_insert_with(|| (received_timestamp, Instant::now()));
+        let elapsed = Duration::from_micros(received_timestamp.saturating_sub(first_timestamp));
+        Some(start + elapsed.div_f64(multiple))
+    }
+}
+
+/// Replays the frames of the current connection and ends at the start of the next connection.
+impl Stream for ReplaySource {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(delay) = self.delay.as_mut() {
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.delay = None;
+            return Poll::Ready(self.pending.take().map(Ok));
+        }
+
+        // The record of the next connection ends the stream of the current connection
+        if !matches!(self.frames.front(), Some((_, Some(_)))) {
+            return Poll::Ready(None);
+        }
+        let Some((received_timestamp, Some(message))) = self.frames.pop_front() else {
+            return Poll::Ready(None);
+        };
+        match self.due(received_timestamp) {
+            Some(deadline) if deadline > Instant::now() => {
+                self.pending = Some(message);
+                self.delay = Some(Box::pin(tokio::time::sleep_until(deadline)));
+                // Registers the waker of the delay
+                self.poll_next(cx)
+            }
+            _ => Poll::Ready(Some(Ok(message))),
+        }
+    }
+}
+
+/// The stream of a replayed connection, which replays the current connection of a [ReplaySource]
+/// that is shared with the replaying client, see [crate::exchange_client::ExchangeClient::replay].
+pub(crate) struct ReplayStream {
+    source: Arc<Mutex<ReplaySource>>,
+}
+
+impl ReplayStream {
+    pub(crate) fn new(source: Arc<Mutex<ReplaySource>>) -> Self {
+        ReplayStream { source }
+    }
+}
+
+impl Stream for ReplayStream {
+    type Item = Result<Message, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut source = self.source.lock().unwrap_or_else(PoisonError::into_inner);
+        Pin::new(&mut *source).poll_next(cx)
+    }
+}
+
+/// An [HttpFetcher] that serves the captured REST responses of a [ReplaySource] instead of sending the requests.
+/// Every response is served once, to the first request of its url, in the order the responses were captured.
+pub(crate) struct ReplayFetcher {
+    responses: Mutex<Vec<(String, String)>>,
+}
+
+impl ReplayFetcher {
+    pub(crate) fn new(responses: Vec<(String, String)>) -> Self {
+        ReplayFetcher { responses: Mutex::new(responses) }
+    }
+
+    /// Returns [ExchangeClientError::Fetch] if no response of the url is left.
+    fn fetch(&self, url: &Url) -> Result<String, ExchangeClientError> {
+        let mut responses = self.responses.lock().unwrap_or_else(PoisonError::into_inner);
+        match responses.iter().position(|(captured_url, _)| captured_url == url.as_str()) {
+            Some(index) => Ok(responses.remove(index).1),
+            None => Err(ExchangeClientError::Fetch(format!("No response of `{}` was captured", url))),
+        }
+    }
+}
+
+impl HttpFetcher for ReplayFetcher {
+    fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+        Box::pin(std::future::ready(self.fetch(url)))
+    }
+
+    fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+        Box::pin(std::future::ready(self.fetch(url)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use futures_util::StreamExt;
+    use rstest::rstest;
+    use tokio::time::{Duration, Instant};
+
+    use url::Url;
+
+    use data_models::exchange::Exchange;
+
+    use crate::api::capture::{CaptureRecord, Payload};
+    use crate::api::error::ExchangeClientError;
+    use crate::api::http_fetcher::HttpFetcher;
+    use crate::api::replay::{ReplayFetcher, ReplaySource, ReplaySpeed};
+    use crate::client_re_exports::Message;
+
+    fn record(received_timestamp: u64, payload: Payload) -> CaptureRecord {
+        CaptureRecord {
+            received_timestamp,
+            exchange: Exchange::Binance,
+            symbol: "ethbtc".to_string(),
+            payload,
+        }
+    }
+
+    fn records() -> Vec<CaptureRecord> {
+        [0, 1_000_000, 3_000_000].into_iter()
+            .map(|received_timestamp| record(received_timestamp, Payload::Frame(Message::text(received_timestamp.to_string()))))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(ReplaySpeed::Original, vec![0, 1_000, 3_000])]
+    #[case(ReplaySpeed::Multiple(2.0), vec![0, 500, 1_500])]
+    #[case(ReplaySpeed::AsFastAsPossible, vec![0, 0, 0])]
+    #[tokio::test(start_paused = true)]
+    async fn replay_test(#[case] speed: ReplaySpeed, #[case] expected_millis: Vec<u64>) {
+        let start = Instant::now();
+        let mut source = ReplaySource::new(records(), speed);
+
+        let mut replayed = vec![];
+        while let Some(message) = source.next().await {
+            replayed.push((message.unwrap(), start.elapsed()));
+        }
+
+        let expected: Vec<(Message, Duration)> = [0, 1_000_000, 3_000_000].into_iter()
+            .zip(expected_millis)
+            .map(|(received_timestamp, millis)| (Message::text(received_timestamp.to_string()), Duration::from_millis(millis)))
+            .collect();
+        assert_eq!(replayed, expected);
+    }
+
+    #[tokio::test]
+    async fn next_connection_test() {
+        let frame = |text: &str| Payload::Frame(Message::text(text));
+        let mut source = ReplaySource::new(vec![
+            record(0, Payload::Connection),
+            record(1, frame("first")),
+            record(2, frame("second")),
+            record(3, Payload::Response { url: "https://localhost/depth".to_string(), body: "{}".to_string() }),
+            record(4, Payload::Connection),
+            record(5, frame("third")),
+        ], ReplaySpeed::AsFastAsPossible);
+
+        assert!(source.next_connection());
+        assert_eq!(source.next().await.unwrap().unwrap(), Message::text("first"));
+        // The frames the connection did not replay are skipped
+        assert!(source.next_connection());
+        assert_eq!(source.next().await.unwrap().unwrap(), Message::text("third"));
+        assert!(source.next().await.is_none());
+        assert!(!source.next_connection());
+        assert_eq!(source.take_responses(), vec![("https://localhost/depth".to_string(), "{}".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn replay_fetcher_test() {
+        let url = Url::parse("https://localhost/depth?symbol=ETHBTC").unwrap();
+        let fetcher = ReplayFetcher::new(vec![
+            ("https://localhost/token".to_string(), "token".to_string()),
+            (url.to_string(), "first".to_string()),
+            (url.to_string(), "second".to_string()),
+        ]);
+
+        assert_eq!(fetcher.get(&url).await.unwrap(), "first");
+        assert_eq!(fetcher.post(&url).await.unwrap(), "second");
+        assert!(matches!(fetcher.get(&url).await, Err(ExchangeClientError::Fetch(reason)) if reason.contains("No response")));
+    }
+
+    #[rstest]
+    #[case("original", Ok(ReplaySpeed::Original))]
+    #[case("max", Ok(ReplaySpeed::AsFastAsPossible))]
+    #[case("2.5", Ok(ReplaySpeed::Multiple(2.5)))]
+    #[case("0", Err(()))]
+    #[case("fast", Err(()))]
+    fn from_str_test(#[case] input: &str, #[case] expected: Result<ReplaySpeed, ()>) {
+        assert_eq!(ReplaySpeed::from_str(input), expected);
+        if let Ok(speed) = expected {
+            assert_eq!(ReplaySpeed::from_str(&speed.to_string()), Ok(speed));
+        }
+    }
+}
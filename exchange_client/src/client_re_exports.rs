@@ -1,25 +1,22 @@
 //! Common re exports clients implementing the [crate::exchange_client::ExchangeClient] trait.
 
 
-pub use futures_util::{
-    SinkExt,
-    stream::{SplitSink, SplitStream}, Stream, StreamExt,
-};
-pub use tokio::{net::TcpStream, sync::mpsc::UnboundedSender};
+pub use futures_util::{Stream, StreamExt};
 pub use tokio_tungstenite::{
     connect_async,
-    MaybeTlsStream,
-    tungstenite::{Error, Message}, WebSocketStream,
+    tungstenite::{Error, Message},
 };
 pub use url::Url;
 
 pub use data_models::{
-    exchange_level::Exchange, exchange_orderbook::OrderbookSnapshot,
+    exchange_level::Exchange, exchange_orderbook::OrderbookSnapshot, timestamp,
 };
 
 pub use crate::{
     api::configuration::ExchangeClientConfig,
-    deserialization::levels,
+    api::error::ExchangeClientError,
+    api::mailbox::MailboxSender,
+    deserialization::{levels, metadata},
     exchange_client::ExchangeClient,
 };
 
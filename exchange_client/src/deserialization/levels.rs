@@ -1,7 +1,7 @@
 //! Methods to deserialize exchange full orderbook snapshots into [Levels]
 //! The methods would be called by [crate::exchange_client::ExchangeClient::deserialize]
 
-use serde::{de::Expected, Deserializer};
+use serde::{de::Expected, de::Unexpected, Deserializer};
 use serde_json::Value;
 
 use data_models::levels::{Level, Levels};
@@ -24,40 +24,81 @@ impl Expected for InvalidJsonArrayLength {
 }
 
 /// Deserializes the bids and asks of exchange orderbook updates.
+/// The update is parsed into a [Value] by the caller, so that the caller
+/// can also read other fields of the update, e.g. its [super::metadata].
 ///
 /// # Arguments
 ///
 /// * `depth` - the number of levels to be deserialize.
-/// * `json_data` - the bids and asks in json format.
+/// * `json_value` - the root [Value] of the update.
 /// * `bids` - a closure that accepts the root [Value] and returns a [Value] containing only the bids.
 /// * `asks` - a closure that accepts the root [Value] and returns a [Value] containing only the asks.
 pub fn deserialize(
     depth: usize,
-    json_data: &String,
+    json_value: &Value,
     bids: impl Fn(&Value) -> &Value + Sync,
     asks: impl Fn(&Value) -> &Value + Sync,
 ) -> LevelDeserializationResult {
-    let json_value: Value = serde_json::from_str(json_data)?;
+    let (bids, asks) = rayon::join(
+        || deserialize_level(depth, bids(json_value)),
+        || deserialize_level(depth, asks(json_value)),
+    );
 
+    combine(bids, asks)
+}
+
+/// Deserializes every bid and ask of exchange orderbook updates, e.g. of the incremental
+/// updates that are applied to a [crate::local_book::LocalBook].
+///
+/// # Arguments
+///
+/// * `json_value` - the root [Value] of the update.
+/// * `bids` - a closure that accepts the root [Value] and returns a [Value] containing only the bids.
+/// * `asks` - a closure that accepts the root [Value] and returns a [Value] containing only the asks.
+pub fn deserialize_all(
+    json_value: &Value,
+    bids: impl Fn(&Value) -> &Value + Sync,
+    asks: impl Fn(&Value) -> &Value + Sync,
+) -> LevelDeserializationResult {
     let (bids, asks) = rayon::join(
-        || deserialize_level(depth, bids(&json_value)),
-        || deserialize_level(depth, asks(&json_value)),
+        || deserialize_array(bids(json_value)),
+        || deserialize_array(asks(json_value)),
     );
 
+    combine(bids, asks)
+}
+
+/// Deserializes all levels of a json array of bids or asks.
+///
+/// # Arguments
+///
+/// * `json_value` - the [Value] that stores the bids or asks.
+fn deserialize_array(json_value: &Value) -> Result<Vec<Level>, serde_json::Error> {
+    match json_value.as_array() {
+        Some(levels) => deserialize_level(levels.len(), json_value),
+        None => Err(serde::de::Error::invalid_type(Unexpected::Other(&json_value.to_string()), &"a json array")),
+    }
+}
+
+/// Combines the deserialized bids and asks into [Levels], or their errors into a single error.
+fn combine(
+    bids: Result<Vec<Level>, serde_json::Error>,
+    asks: Result<Vec<Level>, serde_json::Error>,
+) -> LevelDeserializationResult {
     match (bids, asks) {
         (Ok(bids), Ok(asks)) => Ok(Levels::new(bids, asks)),
         (Ok(_), Err(asks_err)) => Err(serde::de::Error::custom(format!(
             "Asks error: {}",
-            asks_err.to_string()
+            asks_err
         ))),
         (Err(bids_err), Ok(_)) => Err(serde::de::Error::custom(format!(
             "Bids error: {}",
-            bids_err.to_string()
+            bids_err
         ))),
         (Err(bids_err), Err(asks_err)) => Err(serde::de::Error::custom(format!(
             "Bids error: {}, Asks error: {}",
-            bids_err.to_string(),
-            asks_err.to_string()
+            bids_err,
+            asks_err
         ))),
     }
 }
@@ -94,27 +135,36 @@ fn deserialize_level(depth: usize, json_value: &Value) -> Result<Vec<Level>, ser
 #[cfg(test)]
 mod tests {
     use rstest::*;
+    use rust_decimal_macros::dec;
     use serde_json::json;
 
     use super::*;
 
     #[rstest]
-    #[case(1, json ! ([["1.0", "100"]]), vec ! [Level::new(1.0, 100.0)])]
-    #[case(1, json ! ([["1.0", "100"], ["-1.0", "-100"]]), vec ! [Level::new(1.0, 100.0)])]
+    #[case(1, json ! ([["1.0", "100"]]), vec ! [Level::new(dec!(1.0), dec!(100.0))])]
+    #[case(1, json ! ([["1.0", "100"], ["-1.0", "-100"]]), vec ! [Level::new(dec!(1.0), dec!(100.0))])]
     #[case(
-        2, json ! ([["1.0", "100"], ["-1.0", "-100"]]), vec ! [Level::new(1.0, 100.0), Level::new(- 1.0, - 100.0)]
+        2, json ! ([["1.0", "100"], ["-1.0", "-100"]]), vec ! [Level::new(dec!(1.0), dec!(100.0)), Level::new(dec!(-1.0), dec!(-100.0))]
     )]
+    #[case(1, json ! ([["0.06870000", "12.30450000"]]), vec ! [Level::new(dec!(0.06870000), dec!(12.30450000))])]
     fn deserialize_level_succeeds(
         #[case] depth: usize,
         #[case] json_value: Value,
         #[case] expected: Vec<Level>,
     ) {
         let result = deserialize_level(depth, &json_value);
-        assert_eq!(result.is_ok(), true);
+        assert!(result.is_ok());
         assert_eq!(result.as_ref().unwrap(), &expected);
         assert_eq!(result.as_ref().unwrap().capacity(), depth);
     }
 
+    #[test]
+    fn deserialize_level_keeps_exact_representation() {
+        let result = deserialize_level(1, &json ! ([["0.06870000", "12.30450000"]])).unwrap();
+        assert_eq!(result[0].price.to_string(), "0.06870000");
+        assert_eq!(result[0].amount.to_string(), "12.30450000");
+    }
+
     #[rstest]
     #[case(
         2, json ! ([["1.0", "100"]]), serde::de::Error::invalid_length(1, & InvalidJsonArrayLength { expected_length: 2 } )
@@ -130,31 +180,55 @@ mod tests {
         #[case] expected: serde_json::Error,
     ) {
         let result = deserialize_level(depth, &json_value);
-        assert_eq!(result.is_err(), true);
+        assert!(result.is_err());
         assert_eq!(result.err().unwrap().to_string(), expected.to_string());
     }
 
     #[rstest]
     #[case(
-        1, json ! ( { "bids": [["1.0", "99"]], "asks": [["2.0", "101"]] } ).to_string(), Levels::new(vec ! [Level::new(1.0, 99.0)], vec ! [Level::new(2.0, 101.0)])
+        1, json ! ( { "bids": [["1.0", "99"]], "asks": [["2.0", "101"]] } ).to_string(), Levels::new(vec ! [Level::new(dec!(1.0), dec!(99.0))], vec ! [Level::new(dec!(2.0), dec!(101.0))])
     )]
     #[case(
         2, json ! ( { "bids": [["1.0", "99"], ["0.9", "98"]], "asks": [["2.0", "101"], ["2.1", "102"]] } ).to_string(),
-        Levels::new(vec ! [Level::new(1.0, 99.0), Level::new(0.9, 98.0)], vec ! [Level::new(2.0, 101.0), Level::new(2.1, 102.0)])
+        Levels::new(vec ! [Level::new(dec!(1.0), dec!(99.0)), Level::new(dec!(0.9), dec!(98.0))], vec ! [Level::new(dec!(2.0), dec!(101.0)), Level::new(dec!(2.1), dec!(102.0))])
     )]
     fn deserialize_succeeds(#[case] depth: usize, #[case] msg: String, #[case] expected: Levels) {
         let result = deserialize(
             depth,
-            &msg,
+            &serde_json::from_str(&msg).unwrap(),
             |value: &Value| -> &Value { &value["bids"] },
             |value: &Value| -> &Value { &value["asks"] },
         );
 
-        assert_eq!(result.is_ok(), true);
+        assert!(result.is_ok());
         assert_eq!(result.as_ref().unwrap().asks, expected.asks);
         assert_eq!(result.as_ref().unwrap().bids, expected.bids);
     }
 
+    #[rstest]
+    #[case(json ! ( { "b": [["1.0", "99"], ["0.9", "0"]], "a": [] } ), Levels::new(vec ! [Level::new(dec!(1.0), dec!(99.0)), Level::new(dec!(0.9), dec!(0))], vec ! []))]
+    #[case(json ! ( { "b": [], "a": [["2.0", "101"]] } ), Levels::new(vec ! [], vec ! [Level::new(dec!(2.0), dec!(101.0))]))]
+    fn deserialize_all_succeeds(#[case] json_value: Value, #[case] expected: Levels) {
+        let result = deserialize_all(
+            &json_value,
+            |value: &Value| -> &Value { &value["b"] },
+            |value: &Value| -> &Value { &value["a"] },
+        );
+
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn deserialize_all_fails_without_array() {
+        let result = deserialize_all(
+            &json ! ( { "b": [["1.0", "99"]] } ),
+            |value: &Value| -> &Value { &value["b"] },
+            |value: &Value| -> &Value { &value["a"] },
+        );
+
+        assert_eq!(result.unwrap_err().to_string(), "Asks error: invalid type: null, expected a json array");
+    }
+
     #[rstest]
     #[case(
         1, json ! ( { "bids": [["1.0", "99"]], "asks": [["2.0"]] } ).to_string(), serde::de::Error::custom("Asks error: missing field `amount`".to_string())
@@ -172,12 +246,12 @@ mod tests {
     ) {
         let result = deserialize(
             depth,
-            &msg,
+            &serde_json::from_str(&msg).unwrap(),
             |value: &Value| -> &Value { &value["bids"] },
             |value: &Value| -> &Value { &value["asks"] },
         );
 
-        assert_eq!(result.as_ref().is_err(), true);
+        assert!(result.as_ref().is_err());
         assert_eq!(
             result.as_ref().err().unwrap().to_string(),
             expected.to_string()
@@ -0,0 +1,36 @@
+//! Methods to read the metadata of exchange orderbook updates, e.g. update ids and timestamps.
+//! Exchanges publish these either as json numbers or as numeric strings.
+
+use serde_json::Value;
+
+/// Reads an unsigned integer that may be encoded as a json number or a numeric string.
+/// Returns [None] if the value is missing or is not an unsigned integer.
+///
+/// # Arguments
+///
+/// * `json_value` - the [Value] of the field.
+pub fn as_u64(json_value: &Value) -> Option<u64> {
+    match json_value {
+        Value::Number(number) => number.as_u64(),
+        Value::String(string) => string.parse::<u64>().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[rstest]
+    #[case(json!(1027024), Some(1027024))]
+    #[case(json!("1686751543123456"), Some(1686751543123456))]
+    #[case(json!(-1), None)]
+    #[case(json!("1.5"), None)]
+    #[case(json!(null), None)]
+    fn as_u64_test(#[case] json_value: Value, #[case] expected: Option<u64>) {
+        assert_eq!(as_u64(&json_value), expected);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod levels;
-mod serde;
+pub mod metadata;
+pub(crate) mod serde;
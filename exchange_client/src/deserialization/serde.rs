@@ -1,11 +1,15 @@
 //! A [serde] visitor that deserializes an array of bids or asks.
 //! The visitor is called indirectly by [super::levels::deserialize] during the 
 //! deserialization of the exchange orderbook updates.
+//! Prices and amounts are parsed into [Decimal]s without any loss of precision.
+//! Exchanges that publish JSON numbers rather than strings are parsed from their raw representation, see [decimal].
 
 use std::fmt;
 
 use serde::de::{self, Unexpected, Visitor};
+use serde_json::value::RawValue;
 
+use data_models::level::Decimal;
 use data_models::levels::Level;
 
 pub struct OrderbookPriceSizeVisitor;
@@ -22,15 +26,13 @@ impl<'de> Visitor<'de> for OrderbookPriceSizeVisitor {
         A: de::SeqAccess<'de>,
     {
         let price = match seq.next_element::<String>()? {
-            Some(val) => val
-                .parse::<f64>()
+            Some(val) => Decimal::from_str_exact(&val)
                 .map_err(|_| de::Error::invalid_type(Unexpected::Str(&val), &self)),
             None => Err(de::Error::missing_field("price")),
         }?;
 
         let amount = match seq.next_element::<String>()? {
-            Some(val) => val
-                .parse::<f64>()
+            Some(val) => Decimal::from_str_exact(&val)
                 .map_err(|_| de::Error::invalid_type(Unexpected::Str(&val), &self)),
             None => Err(de::Error::missing_field("amount")),
         }?;
@@ -38,3 +40,11 @@ impl<'de> Visitor<'de> for OrderbookPriceSizeVisitor {
         Ok(Level { price, amount })
     }
 }
+
+/// Parses a raw JSON number into a [Decimal] with the scale it was published with, e.g. `0.50000000`.
+pub fn decimal(value: &RawValue) -> Result<Decimal, serde_json::Error> {
+    let number = value.get();
+    Decimal::from_str_exact(number)
+        .or_else(|_| Decimal::from_scientific(number))
+        .map_err(|_| de::Error::invalid_value(Unexpected::Other(number), &"a decimal number"))
+}
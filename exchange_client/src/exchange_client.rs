@@ -2,15 +2,30 @@
 //! Example implemetations can be found here: [crate::binance::client::Binance]
 //! and here: [crate::bitstamp::client::Bitstamp].
 
-use futures_util::{Sink, Stream, StreamExt};
-use tokio::sync::mpsc::error::SendError;
-use tokio::sync::mpsc::UnboundedSender;
-use url::Url;
+use std::sync::PoisonError;
+use std::time::Duration;
 
+use futures_util::future::Either;
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use tokio::time::Instant;
+use url::{Position, Url};
+
+use data_models::connection_event::ConnectionEvent;
+use data_models::connection_state::ConnectionState;
+use data_models::exchange::Exchange;
 use data_models::exchange_orderbook::OrderbookSnapshot;
 
+use crate::api::capture::{self, Payload};
 use crate::api::configuration::ExchangeClientConfig;
-use crate::client_re_exports::{Error, Message};
+use crate::api::error::ExchangeClientError;
+use crate::api::heartbeat::{self, Heartbeat, HeartbeatPolicy};
+use crate::api::mailbox::MailboxSender;
+use crate::api::reconnect_policy::{Backoff, Retry};
+use crate::api::replay::ReplayStream;
+use crate::client_re_exports::{connect_async, Error, Message};
+
+/// The time to wait for an exchange to confirm a subscription, see [ExchangeClient::await_confirmation].
+pub const SUBSCRIPTION_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub trait ExchangeClient: Sized {
     /// Constructs a new exchange client.
@@ -18,15 +33,103 @@ pub trait ExchangeClient: Sized {
     /// # Arguments
     ///
     /// * `client_config` - The [ExchangeClientConfig] for the client.
-    /// * `sender` - The [`UnboundedSender<OrderbookSnapshot>`] channel where the client will publish orderbook updates.
-    fn new(config: ExchangeClientConfig, sender: UnboundedSender<OrderbookSnapshot>) -> Self;
+    /// * `sender` - The [MailboxSender] of the mailbox where the client will publish orderbook updates.
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self;
+
+    /// Returns the [ExchangeClientConfig] of the client.
+    fn config(&self) -> &ExchangeClientConfig;
+
+    /// Returns the [Exchange] of the client.
+    fn exchange(&self) -> Exchange;
+
+    /// Returns the [MailboxSender] where the client publishes orderbook updates and connection events.
+    fn sender(&self) -> &MailboxSender;
 
     /// This is the entry point for an exchange client implementation.
-    async fn start(&self) {
-        let url = self.build_url();
+    ///
+    /// The client reconnects whenever the stream ends or fails. Every connection attempt is prepared by
+    /// [ExchangeClient::bootstrap]. Failed bootstraps, connection attempts and dropped connections are retried
+    /// according to the [crate::api::reconnect_policy::ReconnectPolicy] of the client. The backoff is only restarted
+    /// once a connection stayed up for [crate::api::reconnect_policy::ReconnectPolicy::min_connected_time].
+    /// Every change of the [ConnectionState] of the client is published as a [ConnectionEvent].
+    /// Returns only if a fatal [ExchangeClientError] occurred, see [ExchangeClientError::is_fatal].
+    async fn start(&self) -> Result<(), ExchangeClientError> {
+        let policy = self.config().reconnect_policy.clone();
+        let mut backoff = Backoff::new(policy.clone());
+
+        loop {
+            self.on_connection_state(ConnectionState::Connecting, None)?;
+            let (state, reason) = match self.bootstrap().await {
+                Ok(connection_url) => match self.connect(&connection_url).await {
+                    Ok((sink, stream)) => {
+                        let connected_at = Instant::now();
+                        self.on_connection_state(ConnectionState::Subscribed, None)?;
+                        let reason = match self.process_stream(sink, stream).await {
+                            Ok(()) => "The stream ended".to_string(),
+                            Err(error) if error.is_fatal() => return Err(error),
+                            Err(error) => error.to_string(),
+                        };
+                        println!("Disconnected from `{}` : `{}`", self.exchange(), reason);
+                        if connected_at.elapsed() >= policy.min_connected_time {
+                            backoff.reset();
+                        }
+                        (ConnectionState::Disconnected, reason)
+                    }
+                    Err(error) if error.is_fatal() => return Err(error),
+                    Err(error) => (ConnectionState::Error, error.to_string()),
+                },
+                Err(error) if error.is_fatal() => return Err(error),
+                Err(error) => (ConnectionState::Error, error.to_string()),
+            };
+            self.on_connection_state(state, Some(reason.clone()))?;
+
+            let delay = match backoff.on_failure() {
+                Retry::After(delay) => {
+                    println!("Connection attempt {} to `{}` failed : `{}`. Retrying in {:?}",
+                             backoff.failures(), self.exchange(), reason, delay);
+                    delay
+                }
+                Retry::CircuitOpen(delay) => {
+                    println!("Connection attempt {} to `{}` failed : `{}`. Circuit open, retrying in {:?}",
+                             backoff.failures(), self.exchange(), reason, delay);
+                    delay
+                }
+            };
+            self.on_connection_state(ConnectionState::Reconnecting, Some(format!("Retrying in {:?}", delay)))?;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Replays the captured connections of the [ExchangeClientConfig::replay] source instead of connecting to
+    /// the exchange, publishing the orderbook updates as if the frames were received now.
+    ///
+    /// Every captured connection is bootstrapped, subscribed and processed like a live connection, but nothing is
+    /// sent to the exchange and the REST requests are served with the captured responses, see
+    /// [ExchangeClientConfig::with_replay]. A replayed connection that fails is reported and the replay continues
+    /// with the next captured connection. Returns once every connection has been replayed,
+    /// or if a fatal [ExchangeClientError] occurred.
+    async fn replay(&self) -> Result<(), ExchangeClientError> {
+        let Some(source) = self.config().replay.clone() else {
+            return Err(ExchangeClientError::Protocol(format!("No capture of `{}` is configured for the replay", self.exchange())));
+        };
+
         loop {
-            let (sink, stream) = self.connect(&url).await;
-            self.process_stream(sink, stream).await
+            let next_connection = source.lock().unwrap_or_else(PoisonError::into_inner).next_connection();
+            if !next_connection {
+                return Ok(());
+            }
+            let result = match self.bootstrap().await {
+                Ok(connection_url) => match self.connect(&connection_url).await {
+                    Ok((sink, stream)) => self.process_stream(sink, stream).await,
+                    Err(error) => Err(error),
+                },
+                Err(error) => Err(error),
+            };
+            match result {
+                Ok(()) => {}
+                Err(error) if error.is_fatal() => return Err(error),
+                Err(error) => println!("Replayed connection to `{}` failed : `{}`", self.exchange(), error),
+            }
         }
     }
 
@@ -34,50 +137,250 @@ pub trait ExchangeClient: Sized {
     /// url using the given [ExchangeClientConfig].
     fn build_url(&self) -> Url;
 
+    /// Prepares a connection attempt and returns the url to connect to, see [ExchangeClient::connect].
+    /// Returns [ExchangeClient::build_url] by default. The exchanges that hand out the websocket endpoint over REST,
+    /// e.g. KuCoin, request it here through the [crate::api::http_fetcher::HttpFetcher] of the client.
+    ///
+    /// It is called before every connection attempt of [ExchangeClient::start], an error fails the attempt.
+    async fn bootstrap(&self) -> Result<Url, ExchangeClientError> {
+        Ok(self.build_url())
+    }
+
     /// The implementation will establish a connection to the given exchange
     /// and return a stream that can be processed by [ExchangeClient::process_stream].
-    async fn connect(&self, url: &Url) -> (impl Sink<Message>, impl Stream<Item=Result<Message, Error>> + Unpin);
+    /// Returns the error of a failed connection attempt, which will be retried by [ExchangeClient::start].
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError>;
+
+    /// Opens a websocket connection to the given url and splits it into its sink and its stream.
+    /// The query of the url is not logged, since it may carry a connection token, e.g. the token of KuCoin.
+    /// Every frame of the connection is captured from the start, including the subscribe handshake,
+    /// see [ExchangeClientConfig::capture].
+    /// If the client replays a capture, the current captured connection is returned instead and the sink discards
+    /// every message, see [ExchangeClient::replay].
+    /// Returns [ExchangeClientError::Connect] if the connection could not be established.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The url to connect to, see [ExchangeClient::bootstrap].
+    async fn connect_websocket(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let endpoint = &url[..Position::AfterPath];
+        if let Some(source) = &self.config().replay {
+            println!("Replaying `{}` : `{}`", self.exchange(), endpoint);
+            let sink = futures_util::sink::drain().sink_map_err(|_| Error::ConnectionClosed);
+            return Ok((Either::Right(sink), Either::Right(ReplayStream::new(source.clone()))));
+        }
+        println!("Connecting to `{}` : `{}`", self.exchange(), endpoint);
+
+        match connect_async(url.as_str()).await {
+            Ok((ws_stream, _)) => {
+                println!("Connected to `{}` : `{}`", self.exchange(), endpoint);
+                self.capture(Payload::Connection);
+                let (sink, stream) = ws_stream.split();
+                let stream = capture::tap(stream, self.config().capture.clone(), self.exchange(), self.config().instrument.symbol());
+                Ok((Either::Left(sink), Either::Left(stream)))
+            }
+            Err(error) => {
+                println!("Error connecting to `{}` : `{}`", self.exchange(), error);
+                Err(ExchangeClientError::connect(error))
+            }
+        }
+    }
+
+    /// Sends a GET request through the [crate::api::http_fetcher::HttpFetcher] of the client and returns the body of
+    /// the response. The response is captured, so that a replay can serve it, see [ExchangeClientConfig::capture].
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The [Url] of the request, including its query.
+    async fn http_get(&self, url: &Url) -> Result<String, ExchangeClientError> {
+        let body = self.config().http_fetcher.get(url).await?;
+        self.capture(Payload::Response { url: url.to_string(), body: body.clone() });
+        Ok(body)
+    }
+
+    /// Sends a POST request without a body through the [crate::api::http_fetcher::HttpFetcher] of the client and
+    /// returns the body of the response. The response is captured like the response of [ExchangeClient::http_get].
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The [Url] of the request, including its query.
+    async fn http_post(&self, url: &Url) -> Result<String, ExchangeClientError> {
+        let body = self.config().http_fetcher.post(url).await?;
+        self.capture(Payload::Response { url: url.to_string(), body: body.clone() });
+        Ok(body)
+    }
+
+    /// Writes the given [Payload] to the [ExchangeClientConfig::capture] of the client, if capturing is enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `payload` - The captured connection, frame or REST response.
+    fn capture(&self, payload: Payload) {
+        if let Some(writer) = &self.config().capture {
+            writer.capture(self.exchange(), &self.config().instrument.symbol(), payload);
+        }
+    }
+
+    /// Sends a message of the subscribe handshake, e.g. the subscription to the orderbook channel.
+    /// Returns [ExchangeClientError::Subscribe] if the message could not be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink of the connection.
+    /// * `message` - The serialized message.
+    async fn send_subscription(&self, sink: &mut (impl Sink<Message, Error=Error> + Unpin), message: &str) -> Result<(), ExchangeClientError> {
+        sink.send(Message::text(message)).await
+            .map_err(|error| ExchangeClientError::Subscribe(format!("Could not send `{}` : `{}`", message, error)))?;
+        println!("Subscribing to `{}` : `{}`", self.exchange(), message);
+        Ok(())
+    }
+
+    /// Reads the messages of a new connection until the exchange confirms the subscription of the client.
+    ///
+    /// Binary frames are decoded by [ExchangeClient::decode_binary] and application level pings are answered
+    /// with the [ExchangeClient::reply] of the client, like in [ExchangeClient::process_stream]. Every other message
+    /// is handled by `is_confirmation`, so the messages that arrive before the confirmation are not dropped.
+    /// The messages after the confirmation are left in the stream for [ExchangeClient::process_stream].
+    ///
+    /// Messages that cannot be decoded are skipped and fatal errors are returned as they are.
+    /// Returns [ExchangeClientError::Subscribe] for any other error of `is_confirmation`,
+    /// e.g. [ExchangeClientError::rejected], if the exchange closes the connection,
+    /// or if the subscription is not confirmed within [SUBSCRIPTION_TIMEOUT].
+    ///
+    /// # Arguments
+    ///
+    /// * `sink` - The sink of the connection, where the replies are sent.
+    /// * `stream` - The stream of the connection.
+    /// * `is_confirmation` - Handles a message of the exchange and returns whether it confirms the subscription.
+    async fn await_confirmation(
+        &self,
+        sink: &mut (impl Sink<Message, Error=Error> + Unpin),
+        stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+        mut is_confirmation: impl FnMut(&str) -> Result<bool, ExchangeClientError>,
+    ) -> Result<(), ExchangeClientError> {
+        let confirmation = async {
+            loop {
+                let message = match stream.next().await {
+                    None => return Err(ExchangeClientError::Subscribe("The connection closed before the subscription was confirmed".to_string())),
+                    Some(Ok(message)) if message.is_close() => return Err(ExchangeClientError::Subscribe(format!("The connection closed before the subscription was confirmed : `{}`", message))),
+                    Some(Ok(Message::Binary(data))) => self.decode_binary(&data),
+                    Some(Ok(Message::Text(message))) => Ok(message),
+                    Some(Ok(_)) => continue,
+                    Some(Err(error)) => return Err(ExchangeClientError::Subscribe(format!("Could not read the confirmation : `{}`", error))),
+                };
+
+                if let Some(reply) = message.as_deref().ok().and_then(|message| self.reply(message)) {
+                    sink.send(reply).await
+                        .map_err(|error| ExchangeClientError::Subscribe(format!("Could not answer the ping : `{}`", error)))?;
+                    continue;
+                }
+
+                match message.and_then(|message| is_confirmation(&message)) {
+                    Ok(true) => return Ok(()),
+                    Ok(false) => continue,
+                    Err(error @ ExchangeClientError::Decode(_)) => self.on_deserialization_error(&error),
+                    Err(error @ ExchangeClientError::Subscribe(_)) => return Err(error),
+                    Err(error) if error.is_fatal() => return Err(error),
+                    Err(error) => return Err(ExchangeClientError::Subscribe(error.to_string())),
+                }
+            }
+        };
+
+        match tokio::time::timeout(SUBSCRIPTION_TIMEOUT, confirmation).await {
+            Ok(result) => result,
+            Err(_) => Err(ExchangeClientError::Subscribe(format!("The subscription was not confirmed within {:?}", SUBSCRIPTION_TIMEOUT))),
+        }
+    }
 
     /// The implementation will call this method after the client has connected to
     /// the exchange and is ready to receive orderbook updates.
-    async fn process_stream(&self, sink: impl Sink<Message>, mut steam: impl Stream<Item=Result<Message, Error>> + Unpin) {
-        while let Some(message) = steam.next().await {
-            _ = match message {
-                Ok(message) => {
-                    if message.is_text() {
-                        match message.into_text() {
-                            Ok(message_str) => self.deserialize(&message_str),
-                            Err(err) => panic!("Unexpected error {}", err)
-                        }
-                        continue;
-                    }
-
-                    if message.is_ping() {
-                        self.on_ping(&message);
-                        continue;
-                    }
+    ///
+    /// If the client has a [HeartbeatPolicy], see [ExchangeClient::heartbeat], pings are sent through the sink and
+    /// the round trip time of their pongs, see [ExchangeClient::pong], is recorded in [ExchangeClientConfig::round_trip_time].
+    ///
+    /// Returns [Ok] when the stream ends or the exchange closes the connection.
+    /// Binary frames are decoded by [ExchangeClient::decode_binary] and application level pings are answered
+    /// with the [ExchangeClient::reply] of the client.
+    /// Messages that cannot be decoded are skipped, any other error ends the stream.
+    /// If the exchange asks for a reconnect or the local orderbook is out of sync, the connection is closed and
+    /// [ExchangeClientError::ReconnectRequested] or [ExchangeClientError::OutOfSync] is returned,
+    /// so that [ExchangeClient::start] reconnects and resubscribes.
+    /// Returns [ExchangeClientError::HeartbeatTimeout] if the exchange stays silent for longer than the
+    /// heartbeat timeout, so that [ExchangeClient::start] replaces the dead connection.
+    /// The heartbeat is disabled while the client replays a capture.
+    async fn process_stream(&self, mut sink: impl Sink<Message, Error=Error> + Unpin, mut steam: impl Stream<Item=Result<Message, Error>> + Unpin)
+                            -> Result<(), ExchangeClientError> {
+        let policy = self.heartbeat().filter(|_| self.config().replay.is_none());
+        let mut heartbeat = policy.as_ref().map(Heartbeat::new);
 
-                    if message.is_pong() {
-                        self.on_pong(&message);
-                        continue;
-                    }
+        loop {
+            let deadline = heartbeat.as_ref().map(Heartbeat::deadline);
+            let message = tokio::select! {
+                message = steam.next() => match message {
+                    Some(message) => message.map_err(ExchangeClientError::transport)?,
+                    None => break,
+                },
+                ping = heartbeat::next_ping(&mut heartbeat) => {
+                    sink.send(self.ping(ping)).await.map_err(ExchangeClientError::transport)?;
+                    continue;
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    let timeout = policy.as_ref().map(|policy| policy.timeout).unwrap_or_default();
+                    return Err(ExchangeClientError::HeartbeatTimeout(timeout));
+                }
+            };
 
-                    if message.is_close() {
-                        self.on_close(&message);
-                        break;
-                    }
+            if let Some(round_trip_time) = heartbeat.as_mut().and_then(|heartbeat| heartbeat.on_message(&message)) {
+                self.config().round_trip_time.record(round_trip_time);
+            }
 
-                    if message.is_empty() {
-                        continue;
+            if message.is_text() || message.is_binary() {
+                let message_str = match message {
+                    Message::Binary(data) => self.decode_binary(&data),
+                    message => message.into_text().map_err(ExchangeClientError::decode),
+                };
+                if let (Some(heartbeat), Ok(message_str)) = (heartbeat.as_mut(), message_str.as_deref()) {
+                    if let Some(round_trip_time) = self.pong(message_str).and_then(|sequence| heartbeat.on_pong(sequence)) {
+                        self.config().round_trip_time.record(round_trip_time);
                     }
-
-                    if message.is_binary() {
-                        panic!("Unexpected binary message {}", message);
+                }
+                if let Some(reply) = message_str.as_deref().ok().and_then(|message_str| self.reply(message_str)) {
+                    sink.send(reply).await.map_err(ExchangeClientError::transport)?;
+                    continue;
+                }
+                let result = message_str.and_then(|message_str| self.deserialize(&message_str));
+                match result {
+                    Err(error @ ExchangeClientError::Decode(_)) => self.on_deserialization_error(&error),
+                    Err(error @ (ExchangeClientError::ReconnectRequested | ExchangeClientError::OutOfSync(_))) => {
+                        let _ = sink.close().await;
+                        return Err(error);
                     }
+                    result => result?,
                 }
-                Err(err) => panic!("Unexpected error {}", err)
+                continue;
+            }
+
+            if message.is_ping() {
+                self.on_ping(&message);
+                continue;
+            }
+
+            if message.is_pong() {
+                self.on_pong(&message);
+                continue;
+            }
+
+            if message.is_close() {
+                self.on_close(&message);
+                break;
+            }
+
+            if message.is_empty() {
+                continue;
             }
         }
+
+        Ok(())
     }
 
     fn on_ping(&self, message: &Message);
@@ -86,161 +389,598 @@ pub trait ExchangeClient: Sized {
 
     fn on_close(&self, message: &Message);
 
-    /// Deserializes an exchange message.
-    fn deserialize(&self, message: &String);
+    /// Deserializes an exchange message and publishes the orderbook snapshot it contains.
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError>;
+
+    /// Decodes a binary frame into the text of an exchange message, which is then handled like a text frame.
+    /// The exchanges that compress their messages, e.g. Huobi, implement the decompression.
+    ///
+    /// Returns [ExchangeClientError::Protocol] by default, which ends the stream, since the other exchanges
+    /// only send text frames. A [ExchangeClientError::Decode] error skips the frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The payload of the binary frame.
+    fn decode_binary(&self, data: &[u8]) -> Result<String, ExchangeClientError> {
+        Err(ExchangeClientError::Protocol(format!("Unexpected binary message of {} bytes", data.len())))
+    }
+
+    /// Returns the [HeartbeatPolicy] of the current connection, the [ExchangeClientConfig::heartbeat] by default.
+    /// The exchanges that specify the ping interval of a connection, e.g. KuCoin, return their policy instead.
+    fn heartbeat(&self) -> Option<HeartbeatPolicy> {
+        self.config().heartbeat.clone()
+    }
+
+    /// Returns the message the heartbeat sends to ping the exchange, the given ping frame by default.
+    /// The exchanges that expect application level pings, e.g. KuCoin, replace it with their ping message.
+    ///
+    /// # Arguments
+    ///
+    /// * `ping` - The ping frame of the heartbeat, its payload is the sequence number of the ping.
+    fn ping(&self, ping: Message) -> Message {
+        ping
+    }
+
+    /// Returns the sequence number of the ping an application level pong of the exchange answers,
+    /// e.g. the id of the `{"id": <n>, "type": "pong"}` of KuCoin, so that its round trip time is recorded.
+    /// Returns [None] by default, the pong frames of the WebSocket protocol are matched by the heartbeat.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of an exchange message.
+    fn pong(&self, _message: &str) -> Option<u64> {
+        None
+    }
+
+    /// Returns the reply to an application level ping of the exchange, which is sent instead of deserializing
+    /// the message, e.g. the `{"pong": <ts>}` that Huobi expects for its `{"ping": <ts>}`.
+    /// Returns [None] by default, the other exchanges use the ping frames of the WebSocket protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of an exchange message.
+    fn reply(&self, _message: &str) -> Option<Message> {
+        None
+    }
 
     /// Action to perform when the message with the orderbook snapshot
     /// has been deserialized successfully.
-    fn on_deserialized(&self, sender: &UnboundedSender<OrderbookSnapshot>,
-                       exchange_orderbook: OrderbookSnapshot) {
-        match sender.send(exchange_orderbook) {
-            Ok(_) => (),
-            Err(err) => Self::on_send_error(err),
-        }
+    /// Returns [ExchangeClientError::ChannelClosed] if the snapshot could not be published.
+    fn on_deserialized(&self, sender: &MailboxSender,
+                       exchange_orderbook: OrderbookSnapshot) -> Result<(), ExchangeClientError> {
+        sender.send(exchange_orderbook)?;
+        Ok(())
+    }
+
+    /// Publishes a [ConnectionEvent] with the new [ConnectionState] of the client.
+    /// Returns [ExchangeClientError::ChannelClosed] if the event could not be published.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The new [ConnectionState] of the client.
+    /// * `reason` - Why the state changed, if known.
+    fn on_connection_state(&self, state: ConnectionState, reason: Option<String>) -> Result<(), ExchangeClientError> {
+        let event = ConnectionEvent::new(self.exchange(), self.config().instrument.symbol(), state).with_reason(reason);
+        self.sender().send_event(event).map_err(|_| ExchangeClientError::ChannelClosed)
     }
 
     /// Action to perform when the exchange
     /// message could not be deserialized.
-    fn on_deserialization_error(&self, error: serde_json::Error) {
+    fn on_deserialization_error(&self, error: &ExchangeClientError) {
         println!("{}", error)
     }
+}
 
-    fn on_send_error(error: SendError<OrderbookSnapshot>) {
-        println!("{}", error)
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use futures_util::{sink, stream, SinkExt, Stream, StreamExt};
+    use rust_decimal_macros::dec;
+    use url::Url;
+
+    use data_models::connection_state::ConnectionState;
+    use data_models::exchange::Exchange;
+    use data_models::exchange_event::ExchangeEvent;
+    use data_models::exchange_orderbook::OrderbookSnapshot;
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+    use test_support::{binance, bitfinex, bitstamp, coinbase, huobi, kraken, kucoin};
+    use test_support::mock_exchange::{MockExchange, Step};
+
+    use crate::api::capture::CaptureWriter;
+    use crate::api::configuration::ExchangeClientConfig;
+    use crate::api::error::ExchangeClientError;
+    use crate::api::http_fetcher::{FetchFuture, HttpFetcher};
+    use crate::api::mailbox::{MailboxReceiver, MailboxSender};
+    use crate::api::reconnect_policy::ReconnectPolicy;
+    use crate::api::replay::{ReplaySource, ReplaySpeed};
+    use crate::client_re_exports::{Error, Message};
+    use crate::exchange_client::ExchangeClient;
+
+    /// Retries after 10ms, doubling the interval, without jitter.
+    fn reconnect_policy() -> ReconnectPolicy {
+        ReconnectPolicy::new(Duration::from_millis(10), Duration::from_secs(1), 2.0, 0.0, 10, Duration::from_secs(1))
+    }
+
+    fn config(base_url: String) -> ExchangeClientConfig {
+        ExchangeClientConfig::new(base_url, 1, InstrumentRegistry::default().get("ethbtc").unwrap().clone())
+            .with_reconnect_policy(reconnect_policy())
+    }
+
+    /// Receives the events of the client until a snapshot matches `until`.
+    /// Returns the connection states received on the way and the matching snapshot.
+    async fn receive_until(receiver: &mut MailboxReceiver, until: impl Fn(&OrderbookSnapshot) -> bool)
+                           -> (Vec<ConnectionState>, OrderbookSnapshot) {
+        let mut states = vec![];
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await
+                .expect("The client did not publish the expected snapshot")
+                .unwrap();
+            match event {
+                ExchangeEvent::Connection(event) => states.push(event.state),
+                ExchangeEvent::Snapshot(snapshot) if until(&snapshot) => return (states, snapshot),
+                ExchangeEvent::Snapshot(_) => continue,
+            }
+        }
+    }
+
+    /// A client whose binary frames are UTF-8 text and which answers the `ping` messages with `pong`.
+    struct TestClient {
+        config: ExchangeClientConfig,
+        sender: MailboxSender,
+    }
+
+    impl ExchangeClient for TestClient {
+        fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+            TestClient { config, sender }
+        }
+
+        fn config(&self) -> &ExchangeClientConfig {
+            &self.config
+        }
+
+        fn exchange(&self) -> Exchange {
+            Exchange::Binance
+        }
+
+        fn sender(&self) -> &MailboxSender {
+            &self.sender
+        }
+
+        fn build_url(&self) -> Url {
+            self.config.base_url.clone()
+        }
+
+        async fn connect(&self, url: &Url) -> Result<(impl futures_util::Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+            self.connect_websocket(url).await
+        }
+
+        fn on_ping(&self, _message: &Message) {}
+
+        fn on_pong(&self, _message: &Message) {}
+
+        fn on_close(&self, _message: &Message) {}
+
+        fn deserialize(&self, _message: &str) -> Result<(), ExchangeClientError> {
+            Ok(())
+        }
+
+        fn decode_binary(&self, data: &[u8]) -> Result<String, ExchangeClientError> {
+            String::from_utf8(data.to_vec()).map_err(ExchangeClientError::decode)
+        }
+
+        fn reply(&self, message: &str) -> Option<Message> {
+            (message == "ping").then(|| Message::text("pong"))
+        }
+    }
+
+    fn test_client() -> TestClient {
+        let (sender, _receiver) = crate::api::mailbox::channel(1);
+        TestClient::new(config("wss://localhost".to_string()), sender)
+    }
+
+    /// Waits for the confirmation of the [TestClient], confirmed by a `subscribed` message and rejected by an `error` message.
+    async fn await_confirmation(messages: Vec<Result<Message, Error>>) -> (Result<(), ExchangeClientError>, Vec<String>) {
+        let mut handled = vec![];
+        let result = test_client().await_confirmation(&mut sink::drain().sink_map_err(|_| Error::ConnectionClosed), &mut stream::iter(messages), |message| {
+            handled.push(message.to_string());
+            match message {
+                "subscribed" => Ok(true),
+                "error" => Err(ExchangeClientError::rejected("Invalid symbol")),
+                "closed" => Err(ExchangeClientError::ChannelClosed),
+                "out of sync" => Err(ExchangeClientError::OutOfSync("An update was missed".to_string())),
+                "malformed" => Err(ExchangeClientError::from(serde_json::from_str::<serde_json::Value>("{").unwrap_err())),
+                _ => Ok(false),
+            }
+        }).await;
+        (result, handled)
+    }
+
+    #[tokio::test]
+    async fn await_confirmation_test() {
+        let mut sent = Vec::<Message>::new();
+        let mut messages = stream::iter(vec![
+            Ok(Message::binary("ping")), Ok(Message::text("update")), Ok(Message::binary("subscribed")), Ok(Message::text("update")),
+        ]);
+        let mut handled = vec![];
+
+        let result = test_client().await_confirmation(&mut (&mut sent).sink_map_err(|_| Error::ConnectionClosed), &mut messages, |message| {
+            handled.push(message.to_string());
+            Ok(message == "subscribed")
+        }).await;
+
+        // The ping is answered, the messages before the confirmation are handled and the rest is left in the stream
+        assert!(result.is_ok());
+        assert_eq!(sent, vec![Message::text("pong")]);
+        assert_eq!(handled, vec!["update".to_string(), "subscribed".to_string()]);
+        assert_eq!(messages.next().await.unwrap().unwrap(), Message::text("update"));
+    }
+
+    #[tokio::test]
+    async fn await_confirmation_skips_malformed_messages_test() {
+        let (result, handled) = await_confirmation(vec![
+            Ok(Message::binary(vec![0xff])), Ok(Message::text("malformed")), Ok(Message::text("subscribed")),
+        ]).await;
+
+        assert!(result.is_ok());
+        assert_eq!(handled, vec!["malformed".to_string(), "subscribed".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn await_confirmation_errors_test() {
+        let (result, _) = await_confirmation(vec![Ok(Message::text("error"))]).await;
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(reason)) if reason == "Subscription rejected : `Invalid symbol`"));
+
+        let (result, _) = await_confirmation(vec![Ok(Message::text("out of sync"))]).await;
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(reason)) if reason.contains("An update was missed")));
+
+        let (result, _) = await_confirmation(vec![Ok(Message::text("closed"))]).await;
+        assert!(matches!(result, Err(ExchangeClientError::ChannelClosed)));
+
+        let (result, handled) = await_confirmation(vec![Ok(Message::Close(None)), Ok(Message::text("subscribed"))]).await;
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(_))));
+        assert!(handled.is_empty());
+
+        let (result, _) = await_confirmation(vec![Ok(Message::text("update"))]).await;
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(reason)) if reason.contains("closed")));
+
+        let (result, _) = await_confirmation(vec![Err(Error::ConnectionClosed)]).await;
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(reason)) if reason.contains("Could not read")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn await_confirmation_timeout_test() {
+        let result = test_client().await_confirmation(&mut sink::drain().sink_map_err(|_| Error::ConnectionClosed), &mut stream::pending(), |_| Ok(true)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::Subscribe(reason)) if reason.contains("not confirmed")));
+    }
+
+    #[tokio::test]
+    async fn start_skips_malformed_messages_test() {
+        let mock_exchange = MockExchange::start(Exchange::Binance, vec![vec![
+            Step::Send(binance::partial_depth(1, &[("0.0687", "1.0")], &[("0.0688", "2.0")])),
+            Step::SendMalformed,
+            Step::Send(binance::partial_depth(2, &[("0.0686", "3.0")], &[("0.0689", "4.0")])),
+        ]]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Binance, config(format!("{}/ws", mock_exchange.url())), sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(2)).await;
+        client.abort();
+
+        assert_eq!(states, vec![ConnectionState::Connecting, ConnectionState::Subscribed]);
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.0686), dec!(3.0))], vec![Level::new(dec!(0.0689), dec!(4.0))]));
+        assert_eq!(mock_exchange.paths(), vec!["/ws/ethbtc@depth1@100ms".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn start_reconnects_test() {
+        let mock_exchange = MockExchange::start(Exchange::Bitstamp, vec![
+            vec![
+                Step::Send(bitstamp::order_book("ethbtc", 1, &[("0.0687", "1.0")], &[("0.0688", "1.0")])),
+                Step::Ping(vec![7]),
+                Step::Delay(Duration::from_millis(50)),
+                Step::Disconnect,
+            ],
+            vec![Step::Send(bitstamp::request_reconnect())],
+            vec![Step::Send(bitstamp::order_book("ethbtc", 3, &[("0.0686", "1.0")], &[("0.0689", "1.0")]))],
+        ]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Bitstamp, config(mock_exchange.url()), sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.exchange_timestamp == Some(3)).await;
+        client.abort();
+
+        // The client reconnects after the connection dropped and after the exchange requested a reconnect
+        assert_eq!(states, vec![
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed,
+        ]);
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.0686), dec!(1.0))]);
+        assert_eq!(mock_exchange.connections(), 3);
+        assert_eq!(mock_exchange.pongs(), vec![vec![7]]);
+    }
+
+    #[tokio::test]
+    async fn start_backs_off_dropped_connections_test() {
+        // The exchange drops every connection right after the subscription
+        let mock_exchange = MockExchange::start(Exchange::Bitstamp, vec![
+            vec![Step::Disconnect],
+            vec![Step::Disconnect],
+            vec![Step::Disconnect],
+            vec![Step::Send(bitstamp::order_book("ethbtc", 1, &[("0.0687", "1.0")], &[("0.0688", "1.0")]))],
+        ]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Bitstamp, config(mock_exchange.url()), sender);
+        let mut delays = vec![];
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await
+                .expect("The client did not publish the expected snapshot")
+                .unwrap();
+            match event {
+                ExchangeEvent::Connection(event) if event.state == ConnectionState::Reconnecting => delays.push(event.reason.unwrap()),
+                ExchangeEvent::Connection(_) => continue,
+                ExchangeEvent::Snapshot(_) => break,
+            }
+        }
+        client.abort();
+
+        assert_eq!(delays, vec!["Retrying in 10ms", "Retrying in 20ms", "Retrying in 40ms"]);
+        assert_eq!(mock_exchange.connections(), 4);
+    }
+
+    #[tokio::test]
+    async fn start_resubscribes_on_checksum_mismatch_test() {
+        let mock_exchange = MockExchange::start(Exchange::Kraken, vec![
+            vec![
+                // zlib.crc32(b"53121000000000" + b"5310125000000")
+                Step::Send(kraken::book_snapshot("ETH/BTC", &[("0.0531", "1.25")], &[("0.05312", "10.0")], 929544495)),
+                Step::Send(kraken::book_update("ETH/BTC", &[("0.05311", "2.0")], &[], 1)),
+            ],
+            // zlib.crc32(b"53121000000000" + b"5311200000000")
+            vec![Step::Send(kraken::book_snapshot("ETH/BTC", &[("0.05311", "2.0")], &[("0.05312", "10.0")], 4046833166))],
+        ]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Kraken, config(mock_exchange.url()), sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.levels.bids[0].price == dec!(0.05311)).await;
+        client.abort();
+
+        // The update that does not match its checksum is not published, the client resubscribes instead
+        assert_eq!(states, vec![
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed,
+        ]);
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.05311), dec!(2))], vec![Level::new(dec!(0.05312), dec!(10))]));
+        assert_eq!(mock_exchange.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn start_resubscribes_on_bitfinex_checksum_mismatch_test() {
+        let mock_exchange = MockExchange::start(Exchange::Bitfinex, vec![
+            vec![
+                Step::Send(bitfinex::book_snapshot(&[("0.0531", 2, "1.25"), ("0.05312", 3, "-10.5")])),
+                Step::Send(bitfinex::book_update(("0.05311", 1, "2"))),
+                // The checksum does not include the update, as if the book of Bitfinex had diverged
+                Step::Send(bitfinex::book_checksum(bitfinex::checksum(&[("0.0531", "1.25")], &[("0.05312", "-10.5")]))),
+            ],
+            vec![Step::Send(bitfinex::book_snapshot(&[("0.05308", 1, "4"), ("0.05312", 3, "-10.5")]))],
+        ]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Bitfinex, config(mock_exchange.url()), sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.levels.bids[0].price == dec!(0.05308)).await;
+        client.abort();
+
+        assert_eq!(states, vec![
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed,
+        ]);
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.05308), dec!(4))], vec![Level::new(dec!(0.05312), dec!(10.5))]));
+        // The configuration and the subscription of both connections
+        assert_eq!(mock_exchange.received().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn start_resubscribes_on_missing_heartbeats_test() {
+        let mock_exchange = MockExchange::start(Exchange::Coinbase, vec![
+            vec![
+                Step::Send(coinbase::snapshot("ETH-BTC", &[("0.05310", "1.25")], &[("0.05312", "10.0")])),
+                Step::Send(coinbase::heartbeat("ETH-BTC", 100, "2023-06-14T14:05:40.000000Z")),
+                Step::Send(coinbase::l2update("ETH-BTC", &[("buy", "0.05311", "2.0")], "2023-06-14T14:05:41.000000Z")),
+                // The heartbeats between the two updates are missing
+                Step::Send(coinbase::l2update("ETH-BTC", &[("buy", "0.05309", "3.0")], "2023-06-14T14:05:50.000000Z")),
+            ],
+            vec![Step::Send(coinbase::snapshot("ETH-BTC", &[("0.05308", "4.0")], &[("0.05312", "10.0")]))],
+        ]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Coinbase, config(mock_exchange.url()), sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.levels.bids[0].price == dec!(0.05308)).await;
+        client.abort();
+
+        // The update after the missing heartbeats is not published, the client resubscribes instead
+        assert_eq!(states, vec![
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed,
+        ]);
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.05308), dec!(4.0))], vec![Level::new(dec!(0.05312), dec!(10.0))]));
+        assert_eq!(mock_exchange.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn start_decompresses_frames_and_answers_pings_test() {
+        let mock_exchange = MockExchange::start(Exchange::Huobi, vec![vec![
+            Step::SendBinary(huobi::ping(1630983549450)),
+            Step::Delay(Duration::from_millis(50)),
+            Step::SendBinary(huobi::depth("ethbtc", 1, &[("0.0687", "1.0")], &[("0.0688", "2.0")])),
+        ]]).await;
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client = crate::api::provider::start(Exchange::Huobi, config(mock_exchange.url()), sender);
+        let (_, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(1)).await;
+        client.abort();
+
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.0687), dec!(1.0))], vec![Level::new(dec!(0.0688), dec!(2.0))]));
+        assert_eq!(mock_exchange.received(), vec![
+            r#"{"sub":"market.ethbtc.depth.step0","id":"market.ethbtc.depth.step0"}"#.to_string(),
+            r#"{"pong":1630983549450}"#.to_string(),
+        ]);
+    }
+
+    /// An [HttpFetcher] that imitates the REST api of KuCoin, handing out connection tokens for the endpoint
+    /// of a [MockExchange] and serving the same orderbook snapshot on every request.
+    struct KucoinFetcher {
+        endpoint: String,
+        requests: Mutex<Vec<String>>,
+    }
+
+    impl HttpFetcher for KucoinFetcher {
+        fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.requests.lock().unwrap().push(format!("GET {}", url.path()));
+            Box::pin(async {
+                Ok(r#"{"code":"200000","data":{"sequence":"10","bids":[["0.05310","1.25"]],"asks":[["0.05312","10.5"]]}}"#.to_string())
+            })
+        }
+
+        fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.requests.lock().unwrap().push(format!("POST {}", url.path()));
+            let token = format!(r#"{{"code":"200000","data":{{"token":"token","instanceServers":[{{"endpoint":"{}/endpoint","pingInterval":18000,"pingTimeout":10000}}]}}}}"#, self.endpoint);
+            Box::pin(async move { Ok(token) })
+        }
+    }
+
+    #[tokio::test]
+    async fn start_bootstraps_every_connection_test() {
+        let mock_exchange = MockExchange::start(Exchange::Kucoin, vec![
+            vec![
+                Step::Send(kucoin::l2update("ETH-BTC", 11, 11, &[("0.05311", "2", "11")], &[])),
+                // The update of sequence 12 is missing
+                Step::Send(kucoin::l2update("ETH-BTC", 13, 13, &[("0.05311", "0", "13")], &[])),
+            ],
+            vec![Step::Send(kucoin::l2update("ETH-BTC", 11, 12, &[("0.05309", "3", "11"), ("0.05311", "4", "12")], &[]))],
+        ]).await;
+        let fetcher = Arc::new(KucoinFetcher { endpoint: mock_exchange.url(), requests: Mutex::new(vec![]) });
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let client_config = config("wss://ws-api-spot.kucoin.com".to_string())
+            .with_rest_url("http://localhost:8080".to_string())
+            .with_http_fetcher(fetcher.clone());
+        let client = crate::api::provider::start(Exchange::Kucoin, client_config, sender);
+        let (states, snapshot) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(12)).await;
+        client.abort();
+
+        // The client requests a new token and a new snapshot for the connection after the missing update
+        assert_eq!(states, vec![
+            ConnectionState::Connecting, ConnectionState::Subscribed, ConnectionState::Disconnected, ConnectionState::Reconnecting,
+            ConnectionState::Connecting, ConnectionState::Subscribed,
+        ]);
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.05311), dec!(4))], vec![Level::new(dec!(0.05312), dec!(10.5))]));
+        assert_eq!(fetcher.requests.lock().unwrap().clone(), vec![
+            "POST /api/v1/bullet-public", "GET /api/v1/market/orderbook/level2_100",
+            "POST /api/v1/bullet-public", "GET /api/v1/market/orderbook/level2_100",
+        ]);
+        assert_eq!(mock_exchange.paths(), vec!["/endpoint".to_string(), "/endpoint".to_string()]);
+        assert_eq!(mock_exchange.received()[0],
+                   r#"{"id":"/market/level2:ETH-BTC","type":"subscribe","topic":"/market/level2:ETH-BTC","privateChannel":false,"response":true}"#);
+    }
+
+    #[tokio::test]
+    async fn capture_and_replay_test() {
+        let mock_exchange = MockExchange::start(Exchange::Binance, vec![vec![
+            Step::Send(binance::partial_depth(1, &[("0.0687", "1.0")], &[("0.0688", "2.0")])),
+            Step::Send(binance::partial_depth(2, &[("0.0686", "3.0")], &[("0.0689", "4.0")])),
+        ]]).await;
+        let path = std::env::temp_dir().join(format!("capture_and_replay_test_{}.jsonl", std::process::id()));
+        let capture = Arc::new(CaptureWriter::create(&path).unwrap());
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let capture_config = config(format!("{}/ws", mock_exchange.url())).with_capture(Some(capture));
+        let client = crate::api::provider::start(Exchange::Binance, capture_config, sender);
+        let (_, captured) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(2)).await;
+        client.abort();
+
+        let source = ReplaySource::open(&path, Exchange::Binance, "ethbtc", ReplaySpeed::AsFastAsPossible).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+        let result = crate::api::provider::replay(Exchange::Binance, config(mock_exchange.url()), sender, source).await.unwrap();
+
+        // The replay publishes the same orderbooks without connecting to the exchange
+        assert!(result.is_ok());
+        let (states, replayed) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(2)).await;
+        assert!(states.is_empty());
+        assert_eq!(replayed.levels, captured.levels);
+        assert_eq!(mock_exchange.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn capture_and_replay_bitfinex_test() {
+        let mock_exchange = MockExchange::start(Exchange::Bitfinex, vec![vec![
+            Step::Send(bitfinex::book_snapshot(&[("0.0531", 2, "1.25"), ("0.05312", 3, "-10.5")])),
+            Step::Send(bitfinex::book_update(("0.05311", 1, "2"))),
+        ]]).await;
+        let path = std::env::temp_dir().join(format!("capture_and_replay_bitfinex_test_{}.jsonl", std::process::id()));
+        let capture = Arc::new(CaptureWriter::create(&path).unwrap());
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let capture_config = config(mock_exchange.url()).with_capture(Some(capture));
+        let client = crate::api::provider::start(Exchange::Bitfinex, capture_config, sender);
+        let (_, captured) = receive_until(&mut receiver, |snapshot| snapshot.levels.bids[0].price == dec!(0.05311)).await;
+        client.abort();
+
+        let source = ReplaySource::open(&path, Exchange::Bitfinex, "ethbtc", ReplaySpeed::AsFastAsPossible).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+        let result = crate::api::provider::replay(Exchange::Bitfinex, config(mock_exchange.url()), sender, source).await.unwrap();
+
+        // The handshake is captured, so the replay subscribes to the captured channel again
+        assert!(result.is_ok());
+        let (states, replayed) = receive_until(&mut receiver, |snapshot| snapshot.levels.bids[0].price == dec!(0.05311)).await;
+        assert!(states.is_empty());
+        assert_eq!(replayed.levels, captured.levels);
+        assert_eq!(mock_exchange.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn capture_and_replay_kucoin_test() {
+        let mock_exchange = MockExchange::start(Exchange::Kucoin, vec![
+            vec![
+                Step::Send(kucoin::l2update("ETH-BTC", 11, 11, &[("0.05311", "2", "11")], &[])),
+                // The update of sequence 12 is missing
+                Step::Send(kucoin::l2update("ETH-BTC", 13, 13, &[("0.05311", "0", "13")], &[])),
+            ],
+            vec![Step::Send(kucoin::l2update("ETH-BTC", 11, 12, &[("0.05309", "3", "11"), ("0.05311", "4", "12")], &[]))],
+        ]).await;
+        let fetcher = Arc::new(KucoinFetcher { endpoint: mock_exchange.url(), requests: Mutex::new(vec![]) });
+        let path = std::env::temp_dir().join(format!("capture_and_replay_kucoin_test_{}.jsonl", std::process::id()));
+        let capture = Arc::new(CaptureWriter::create(&path).unwrap());
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+
+        let capture_config = config("wss://ws-api-spot.kucoin.com".to_string())
+            .with_rest_url("http://localhost:8080".to_string())
+            .with_http_fetcher(fetcher.clone())
+            .with_capture(Some(capture));
+        let client = crate::api::provider::start(Exchange::Kucoin, capture_config, sender);
+        let (_, captured) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(12)).await;
+        client.abort();
+
+        let source = ReplaySource::open(&path, Exchange::Kucoin, "ethbtc", ReplaySpeed::AsFastAsPossible).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let (sender, mut receiver) = crate::api::mailbox::channel(1);
+        let replay_config = config("wss://ws-api-spot.kucoin.com".to_string()).with_rest_url("http://localhost:8080".to_string());
+        let result = crate::api::provider::replay(Exchange::Kucoin, replay_config, sender, source).await.unwrap();
+
+        // Both captured connections are subscribed and synchronized again from the captured
+        // tokens and snapshots, without sending a request or connecting to the exchange
+        assert!(result.is_ok());
+        let (states, replayed) = receive_until(&mut receiver, |snapshot| snapshot.sequence == Some(12)).await;
+        assert!(states.is_empty());
+        assert_eq!(replayed.levels, captured.levels);
+        assert_eq!(fetcher.requests.lock().unwrap().len(), 4);
+        assert_eq!(mock_exchange.connections(), 2);
     }
 }
-//
-// //#[rustfmt::skip]
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-//     use crate::deserialization::levels;
-//     use data_models::levels::Level;
-//     use futures_util::stream;
-//     use rstest::rstest;
-//     use serde_json::json;
-//
-//     struct ExchangeClientFixture {
-//         depth: usize,
-//         sender: UnboundedSender<Levels>,
-//         stream_messages: Vec<String>,
-//     }
-//
-//     #[async_trait]
-//     impl ExchangeClient for ExchangeClientFixture {
-//         type T = Levels;
-//
-//         fn new(_: ExchangeClientConfig, _: UnboundedSender<Levels>) -> Self {
-//             panic!("Not implemented")
-//         }
-//
-//         fn build_url(&self) -> Url {
-//             Url::parse("http://www.somebaseurl.com").unwrap()
-//         }
-//
-//         async fn connect(&self, _: &Url) -> Box<dyn Stream<Item = String> + Send> {
-//             Box::new(stream::iter(self.stream_messages.clone()))
-//         }
-//
-//         fn deserialize(&self, message: &String) -> LevelDeserializationResult {
-//             levels::deserialize(
-//                 self.depth,
-//                 message,
-//                 |value| -> &serde_json::Value { &value["bids"] },
-//                 |value| -> &serde_json::Value { &value["asks"] },
-//             )
-//         }
-//
-//         fn on_deserialized(&self, levels: Levels) {
-//             self.sender.send(Levels::new(levels.bids, levels.asks));
-//         }
-//
-//         fn on_deserialization_error(&self, message: &String, error: serde_json::Error) {
-//             // Currently this method does cause any side-effects in the exchange client implementations.
-//             // This side effect (sending empty Levels) is for testing only.
-//             self.sender
-//                 .send(Levels::new(Vec::<Level>::new(), Vec::<Level>::new()));
-//         }
-//     }
-//
-//     #[rstest]
-//     async fn deseriaze_messages() {
-//         let stream_messages = vec![
-//             json!( { "bids": [["1.0", "99"]], "asks": [["2.0", "101"]] } ).to_string(),
-//             json!( { "bids": [["0.9", "98"]], "asks": [["2.1", "102"]] } ).to_string(),
-//         ];
-//
-//         let (tx_exchange, mut rx_exchange) = tokio::sync::mpsc::unbounded_channel::<Levels>();
-//
-//         let exchange_client = ExchangeClientFixture {
-//             depth: 1,
-//             sender: tx_exchange.clone(),
-//             stream_messages,
-//         };
-//
-//         exchange_client.start().await;
-//
-//         let first_level_published = rx_exchange.blocking_recv();
-//         assert_eq!(
-//             first_level_published.as_ref().unwrap().bids,
-//             vec![Level::new(1.0, 99.0)]
-//         );
-//         assert_eq!(
-//             first_level_published.as_ref().unwrap().asks,
-//             vec![Level::new(2.0, 101.0)]
-//         );
-//
-//         let second_level_published = rx_exchange.blocking_recv();
-//         assert_eq!(
-//             second_level_published.as_ref().unwrap().bids,
-//             vec![Level::new(0.9, 98.0)]
-//         );
-//         assert_eq!(
-//             second_level_published.as_ref().unwrap().asks,
-//             vec![Level::new(2.1, 102.0)]
-//         );
-//     }
-//
-//     #[rstest]
-//     async fn deserialization_error() {
-//         let stream_messages = vec![
-//             json!( { "bids": [["1.0", "99"]], "asks": [["2.0", "101"]] } ).to_string(),
-//             json!( { "bids": [["99"]], "asks": [["2.0", "101"]] } ).to_string(), //will cause deserialization error
-//             json!( { "bids": [["0.9", "98"]], "asks": [["2.1", "102"]] } ).to_string(),
-//         ];
-//
-//         let (tx_exchange, mut rx_exchange) = tokio::sync::mpsc::unbounded_channel::<Levels>();
-//
-//         let exchange_client = ExchangeClientFixture {
-//             depth: 1,
-//             sender: tx_exchange.clone(),
-//             stream_messages,
-//         };
-//
-//         exchange_client.start().await;
-//
-//         let first_level_published = rx_exchange.blocking_recv();
-//         assert_eq!(
-//             first_level_published.as_ref().unwrap().bids,
-//             vec![Level::new(1.0, 99.0)]
-//         );
-//         assert_eq!(
-//             first_level_published.as_ref().unwrap().asks,
-//             vec![Level::new(2.0, 101.0)]
-//         );
-//
-//         let second_level_published = rx_exchange.blocking_recv();
-//         assert_eq!(second_level_published.as_ref().unwrap().bids, vec![]);
-//         assert_eq!(second_level_published.as_ref().unwrap().asks, vec![]);
-//
-//         let third_level_published = rx_exchange.blocking_recv();
-//         assert_eq!(
-//             third_level_published.as_ref().unwrap().bids,
-//             vec![Level::new(0.9, 98.0)]
-//         );
-//         assert_eq!(
-//             third_level_published.as_ref().unwrap().asks,
-//             vec![Level::new(2.1, 102.0)]
-//         );
-//     }
-// }
@@ -1,49 +1,74 @@
 //! A client implementation for the Binance exchange.
 //! The client is instantiated by [crate::api::provider].
 
+use std::sync::{Mutex, PoisonError};
+
 use futures_util::Sink;
+
+use crate::api::configuration::DepthStream;
 use crate::client_re_exports::*;
 
+use super::diff_depth::{DepthUpdate, DiffDepthBook};
+
+/// The base Url of the REST api that serves the orderbook snapshots of the diff depth stream.
+const REST_URL: &str = "https://api.binance.com";
+/// The number of levels per side of the REST orderbook snapshot, the maximum Binance serves.
+const SNAPSHOT_LIMIT: usize = 5000;
+
 pub struct Binance {
     pub config: ExchangeClientConfig,
-    sender: UnboundedSender<OrderbookSnapshot>,
+    sender: MailboxSender,
     exchange: Exchange,
+    /// The local orderbook of the [DepthStream::Diff] stream, synchronized on every connect.
+    diff_depth_book: Mutex<Option<DiffDepthBook>>,
 }
 
 impl ExchangeClient for Binance {
 
-    fn new(config: ExchangeClientConfig, sender: UnboundedSender<OrderbookSnapshot>) -> Self {
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
         Binance {
             config,
             sender,
             exchange: Exchange::Binance,
+            diff_depth_book: Mutex::new(None),
         }
     }
 
     fn build_url(&self) -> Url {
+        let stream = match self.config.depth_stream {
+            DepthStream::Partial => format!("depth{}", self.config.depth),
+            DepthStream::Diff => "depth".to_string(),
+        };
         let url_str: String = format!(
-            "{}/{}@depth{}@100ms",
+            "{}/{}@{}@100ms",
             self.config.base_url,
-            self.config.symbol.to_string(),
-            self.config.depth.to_string()
+            self.config.instrument.exchange_symbol(self.exchange),
+            stream
         );
 
         Url::parse(&url_str).unwrap()
     }
 
-    async fn connect(&self, url: &Url) -> (impl Sink<Message>, impl Stream<Item=Result<Message, Error>> + Unpin) {
-        println!("Connecting to `{}` : `{}`", self.exchange, url.as_str());
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
 
-        let ws_stream = match connect_async(url.as_str()).await  {
-            Ok((ws_stream, _)) => {
-                println!("Connected to `{}` : `{}`", self.exchange, url.as_str());
-                ws_stream
-            }
-            Err(error) => {
-                panic!("Error connecting to `{}` : `{}`", self.exchange, error);
-            }
-        };
-        ws_stream.split()
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (ws_write_stream, ws_read_stream) = self.connect_websocket(url).await?;
+
+        // The updates are buffered by the unread stream until the local orderbook is synchronized
+        if self.config.depth_stream == DepthStream::Diff {
+            self.synchronize().await?;
+        }
+        Ok((ws_write_stream, ws_read_stream))
     }
 
     fn on_ping(&self, message: &Message) {
@@ -58,21 +83,310 @@ impl ExchangeClient for Binance {
         println!("{}", message)
     }
 
-    fn deserialize(&self, message: &String) {
-        _ = match levels::deserialize(
-            self.config.depth,
-            message,
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        let snapshot = match self.config.depth_stream {
+            DepthStream::Partial => self.deserialize_snapshot(message)?,
+            DepthStream::Diff => match self.deserialize_update(message)? {
+                Some(snapshot) => snapshot,
+                None => return Ok(()),
+            },
+        };
+        self.on_deserialized(&self.sender, snapshot)
+    }
+}
+
+impl Binance {
+    /// Fetches a REST orderbook snapshot and replaces the local orderbook of the diff depth stream with it.
+    /// The updates of the stream are applied to the snapshot by [Binance::deserialize_update].
+    async fn synchronize(&self) -> Result<(), ExchangeClientError> {
+        let mut url = match &self.config.rest_url {
+            Some(rest_url) => rest_url.clone(),
+            None => Url::parse(REST_URL).unwrap(),
+        };
+        url.set_path("/api/v3/depth");
+        url.query_pairs_mut()
+            .append_pair("symbol", &self.config.instrument.exchange_symbol(self.exchange).to_uppercase())
+            .append_pair("limit", &SNAPSHOT_LIMIT.to_string());
+
+        println!("Fetching the orderbook snapshot of `{}` : `{}`", self.exchange, url.as_str());
+        let body = self.http_get(&url).await?;
+        let json_value: serde_json::Value = serde_json::from_str(&body)?;
+
+        let last_update_id = metadata::as_u64(&json_value["lastUpdateId"])
+            .ok_or_else(|| ExchangeClientError::Fetch(format!("The orderbook snapshot of `{}` has no `lastUpdateId`", url)))?;
+        let levels = levels::deserialize_all(
+            &json_value,
             |value| -> &serde_json::Value { &value["bids"] },
             |value| -> &serde_json::Value { &value["asks"] },
-        ) {
-            Ok(levels) => {
-                let snapshot = OrderbookSnapshot::new(
-                    self.exchange,
-                    self.config.symbol.clone(),
-                    levels);
-                self.on_deserialized(&self.sender, snapshot);
+        )?;
+
+        *self.diff_depth_book.lock().unwrap_or_else(PoisonError::into_inner) = Some(DiffDepthBook::new(last_update_id, &levels));
+        Ok(())
+    }
+
+    /// Applies a diff depth update to the local orderbook and returns its top levels as an [OrderbookSnapshot].
+    /// The final update id of the update is used as the sequence of the snapshot.
+    ///
+    /// Returns [None] if the update is older than the local orderbook.
+    /// Returns [ExchangeClientError::OutOfSync] if an update was missed, so that the client reconnects
+    /// and synchronizes the local orderbook from a new REST snapshot.
+    fn deserialize_update(&self, message: &str) -> Result<Option<OrderbookSnapshot>, ExchangeClientError> {
+        let received_timestamp = timestamp::now_micros();
+        let json_value: serde_json::Value = serde_json::from_str(message)?;
+        let update = DepthUpdate::deserialize(&json_value)?;
+
+        let mut diff_depth_book = self.diff_depth_book.lock().unwrap_or_else(PoisonError::into_inner);
+        let book = diff_depth_book.as_mut()
+            .ok_or_else(|| ExchangeClientError::OutOfSync("The orderbook snapshot has not been fetched".to_string()))?;
+
+        match book.apply(&update) {
+            Ok(true) => Ok(Some(OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), book.levels(self.config.depth))
+                .with_sequence(Some(update.final_update_id))
+                .with_exchange_timestamp(update.event_time)
+                .with_received_timestamp(received_timestamp))),
+            Ok(false) => Ok(None),
+            Err(error) => {
+                *diff_depth_book = None;
+                Err(error)
             }
-            Err(error) => self.on_deserialization_error(error)
         }
     }
+
+    /// Deserializes a partial book depth message into an [OrderbookSnapshot].
+    /// The `lastUpdateId` of the message is used as the sequence of the snapshot.
+    /// The partial book depth stream does not publish an event time.
+    fn deserialize_snapshot(&self, message: &str) -> Result<OrderbookSnapshot, serde_json::Error> {
+        let received_timestamp = timestamp::now_micros();
+        let json_value: serde_json::Value = serde_json::from_str(message)?;
+
+        let levels = levels::deserialize(
+            self.config.depth,
+            &json_value,
+            |value| -> &serde_json::Value { &value["bids"] },
+            |value| -> &serde_json::Value { &value["asks"] },
+        )?;
+
+        Ok(OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), levels)
+            .with_sequence(metadata::as_u64(&json_value["lastUpdateId"]))
+            .with_received_timestamp(received_timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::{sink, stream, SinkExt};
+
+    use crate::api::heartbeat::HeartbeatPolicy;
+    use crate::api::http_fetcher::{FetchFuture, HttpFetcher};
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::Level;
+
+    use super::*;
+
+    fn client(sender: MailboxSender) -> Binance {
+        Binance::new(ExchangeClientConfig::new("wss://localhost".to_string(), 1, InstrumentRegistry::default().get("ethbtc").unwrap().clone()), sender)
+    }
+
+    /// An [HttpFetcher] that serves a recorded response and records the requested urls.
+    struct StubFetcher {
+        body: String,
+        urls: Mutex<Vec<Url>>,
+    }
+
+    impl HttpFetcher for StubFetcher {
+        fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.urls.lock().unwrap().push(url.clone());
+            Box::pin(async move { Ok(self.body.clone()) })
+        }
+
+        fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.get(url)
+        }
+    }
+
+    fn diff_client(sender: MailboxSender, fetcher: Arc<StubFetcher>) -> Binance {
+        Binance::new(ExchangeClientConfig::new("wss://localhost/ws".to_string(), 2, InstrumentRegistry::default().get("ethbtc").unwrap().clone())
+                         .with_depth_stream(DepthStream::Diff)
+                         .with_rest_url("http://localhost:8080".to_string())
+                         .with_http_fetcher(fetcher), sender)
+    }
+
+    fn depth_update_message(first_update_id: u64, final_update_id: u64, bids: serde_json::Value) -> Message {
+        Message::text(json!({
+            "e": "depthUpdate",
+            "E": 1686751543123u64,
+            "s": "ETHBTC",
+            "U": first_update_id,
+            "u": final_update_id,
+            "b": bids,
+            "a": []
+        }).to_string())
+    }
+
+    impl Binance {
+        fn with_heartbeat_policy(mut self, heartbeat: HeartbeatPolicy) -> Self {
+            self.config = self.config.with_heartbeat(Some(heartbeat));
+            self
+        }
+    }
+
+    fn snapshot_message(last_update_id: u64) -> Message {
+        Message::text(json!({
+            "lastUpdateId": last_update_id,
+            "bids": [["0.06870000", "12.30450000"]],
+            "asks": [["0.06871000", "1.00000000"]]
+        }).to_string())
+    }
+
+    #[test]
+    fn deserialize_snapshot_test() {
+        let (sender, _) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+        let message = json!({
+            "lastUpdateId": 160,
+            "bids": [["0.06870000", "12.30450000"]],
+            "asks": [["0.06871000", "1.00000000"]]
+        }).to_string();
+
+        let snapshot = client.deserialize_snapshot(&message).unwrap();
+
+        assert_eq!(snapshot.exchange, Exchange::Binance);
+        assert_eq!(snapshot.symbol, "ethbtc");
+        assert_eq!(snapshot.sequence, Some(160));
+        assert_eq!(snapshot.exchange_timestamp, None);
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.06870000), dec!(12.30450000))]);
+        assert_eq!(snapshot.levels.asks, vec![Level::new(dec!(0.06871000), dec!(1.00000000))]);
+    }
+
+    #[tokio::test]
+    async fn process_stream_skips_undecodable_messages_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let messages = vec![Ok(snapshot_message(1)), Ok(Message::text("{")), Ok(snapshot_message(2))];
+
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(result.is_ok());
+        // The mailbox keeps the latest snapshot, the first one was published and replaced
+        assert_eq!(receiver.recv_snapshot().await.unwrap().sequence, Some(2));
+        assert_eq!(receiver.dropped(Exchange::Binance, "ethbtc"), 1);
+    }
+
+    #[tokio::test]
+    async fn process_stream_errors_test() {
+        let (sender, receiver) = crate::api::mailbox::channel(16);
+
+        let messages = vec![Ok(Message::binary(vec![1, 2, 3])), Ok(snapshot_message(1))];
+        let result = client(sender.clone()).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(matches!(result, Err(ExchangeClientError::Protocol(_))));
+
+        let messages = vec![Err(Error::ConnectionClosed)];
+        let result = client(sender.clone()).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(matches!(result, Err(ExchangeClientError::Transport(error)) if matches!(*error, Error::ConnectionClosed)));
+
+        drop(receiver);
+        let messages = vec![Ok(snapshot_message(1))];
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_err_and(|error| error.is_fatal()));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn process_stream_heartbeat_timeout_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender)
+            .with_heartbeat_policy(HeartbeatPolicy::new(Duration::from_secs(10), Duration::from_secs(25)));
+        let mut pings = Vec::<Message>::new();
+
+        let result = client.process_stream((&mut pings).sink_map_err(|_| Error::ConnectionClosed), stream::pending()).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::HeartbeatTimeout(timeout)) if timeout == Duration::from_secs(25)));
+        assert_eq!(pings, vec![Message::Ping(1u64.to_be_bytes().to_vec()), Message::Ping(2u64.to_be_bytes().to_vec())]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn process_stream_records_round_trip_time_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender)
+            .with_heartbeat_policy(HeartbeatPolicy::new(Duration::from_secs(10), Duration::from_secs(25)));
+        let round_trip_time = client.config.round_trip_time.clone();
+        // The pong arrives 15ms after the first ping, then the exchange closes the connection
+        let messages = stream::once(async {
+            tokio::time::sleep(Duration::from_millis(10_015)).await;
+            Ok(Message::Pong(1u64.to_be_bytes().to_vec()))
+        }).chain(stream::iter(vec![Ok(Message::Close(None))]));
+
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), Box::pin(messages)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(round_trip_time.last(), Some(Duration::from_millis(15)));
+    }
+
+    #[tokio::test]
+    async fn diff_depth_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let fetcher = Arc::new(StubFetcher {
+            body: json!({
+                "lastUpdateId": 100,
+                "bids": [["0.06870000", "1.00000000"], ["0.06860000", "2.00000000"], ["0.06850000", "3.00000000"]],
+                "asks": [["0.06871000", "4.00000000"]]
+            }).to_string(),
+            urls: Mutex::new(vec![]),
+        });
+        let client = diff_client(sender, fetcher.clone());
+        assert_eq!(client.build_url().as_str(), "wss://localhost/ws/ethbtc@depth@100ms");
+
+        client.synchronize().await.unwrap();
+        assert_eq!(fetcher.urls.lock().unwrap()[0].as_str(), "http://localhost:8080/api/v3/depth?symbol=ETHBTC&limit=5000");
+
+        let messages = vec![
+            // Buffered before the snapshot, skipped
+            Ok(depth_update_message(95, 100, json!([["0.06870000", "9.00000000"]]))),
+            Ok(depth_update_message(99, 102, json!([["0.06870000", "0.00000000"]]))),
+        ];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.sequence, Some(102));
+        assert_eq!(snapshot.exchange_timestamp, Some(1686751543123000));
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.06860000), dec!(2.00000000)), Level::new(dec!(0.06850000), dec!(3.00000000))]);
+        assert_eq!(snapshot.levels.asks, vec![Level::new(dec!(0.06871000), dec!(4.00000000))]);
+
+        let messages = vec![Ok(depth_update_message(103, 103, json!([["0.06880000", "5.00000000"]])))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.sequence, Some(103));
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.06880000), dec!(5.00000000)), Level::new(dec!(0.06860000), dec!(2.00000000))]);
+    }
+
+    #[tokio::test]
+    async fn diff_depth_stream_gap_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let fetcher = Arc::new(StubFetcher {
+            body: json!({"lastUpdateId": 100, "bids": [], "asks": []}).to_string(),
+            urls: Mutex::new(vec![]),
+        });
+        let client = diff_client(sender, fetcher);
+        client.synchronize().await.unwrap();
+
+        let messages = vec![
+            Ok(depth_update_message(101, 102, json!([["0.06870000", "1.00000000"]]))),
+            Ok(depth_update_message(104, 105, json!([["0.06870000", "2.00000000"]]))),
+            Ok(depth_update_message(106, 107, json!([["0.06870000", "3.00000000"]]))),
+        ];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().sequence, Some(102));
+        assert!(receiver.try_recv().is_none());
+        // The local orderbook is dropped until the client reconnects and synchronizes it
+        assert!(client.diff_depth_book.lock().unwrap().is_none());
+    }
 }
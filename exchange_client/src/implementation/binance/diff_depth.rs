@@ -0,0 +1,179 @@
+//! Synchronizes a local orderbook from a REST snapshot and the updates of the Binance diff depth stream,
+//! as documented in <https://developers.binance.com/docs/binance-spot-api-docs/web-socket-streams#how-to-manage-a-local-order-book-correctly>.
+
+use serde_json::Value;
+
+use data_models::levels::Levels;
+
+use crate::api::error::ExchangeClientError;
+use crate::deserialization::{levels, metadata};
+use crate::local_book::LocalBook;
+
+/// An update of the diff depth stream.
+pub struct DepthUpdate {
+    /// The first update id of the update, `U`.
+    pub first_update_id: u64,
+    /// The final update id of the update, `u`.
+    pub final_update_id: u64,
+    /// The event time in microseconds since the unix epoch, `E`.
+    pub event_time: Option<u64>,
+    pub levels: Levels,
+}
+
+impl DepthUpdate {
+    /// Deserializes a `depthUpdate` event of the diff depth stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `json_value` - The root [Value] of the event.
+    pub fn deserialize(json_value: &Value) -> Result<DepthUpdate, serde_json::Error> {
+        let update_id = |field: &'static str| {
+            metadata::as_u64(&json_value[field]).ok_or_else(|| serde::de::Error::missing_field(field))
+        };
+
+        Ok(DepthUpdate {
+            first_update_id: update_id("U")?,
+            final_update_id: update_id("u")?,
+            event_time: metadata::as_u64(&json_value["E"]).map(|millis| millis * 1_000),
+            levels: levels::deserialize_all(
+                json_value,
+                |value| -> &Value { &value["b"] },
+                |value| -> &Value { &value["a"] },
+            )?,
+        })
+    }
+}
+
+/// The local orderbook of the diff depth stream.
+pub struct DiffDepthBook {
+    book: LocalBook,
+    last_update_id: u64,
+    synchronized: bool,
+}
+
+impl DiffDepthBook {
+    /// Constructs a new [DiffDepthBook] from a REST orderbook snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `last_update_id` - The `lastUpdateId` of the snapshot.
+    /// * `levels` - The bids and asks of the snapshot.
+    pub fn new(last_update_id: u64, levels: &Levels) -> Self {
+        let mut book = LocalBook::default();
+        book.replace(levels);
+
+        DiffDepthBook {
+            book,
+            last_update_id,
+            synchronized: false,
+        }
+    }
+
+    /// Applies an update to the local orderbook.
+    ///
+    /// Returns false if the update is older than the local orderbook and was skipped.
+    /// Returns [ExchangeClientError::OutOfSync] if the update does not follow on from the local orderbook:
+    /// the first applied update has to contain the update after the snapshot and every
+    /// following update has to start right after the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The [DepthUpdate] of the diff depth stream.
+    pub fn apply(&mut self, update: &DepthUpdate) -> Result<bool, ExchangeClientError> {
+        if update.final_update_id <= self.last_update_id {
+            return Ok(false);
+        }
+
+        let next_update_id = self.last_update_id + 1;
+        let follows_on = match self.synchronized {
+            true => update.first_update_id == next_update_id,
+            false => update.first_update_id <= next_update_id,
+        };
+        if !follows_on {
+            return Err(ExchangeClientError::OutOfSync(format!(
+                "Expected update {} but received updates {} to {}",
+                next_update_id, update.first_update_id, update.final_update_id
+            )));
+        }
+
+        self.book.update(&update.levels);
+        self.last_update_id = update.final_update_id;
+        self.synchronized = true;
+        Ok(true)
+    }
+
+    /// Returns up to `depth` of the best bids and asks of the local orderbook.
+    pub fn levels(&self, depth: usize) -> Levels {
+        self.book.levels(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    use data_models::level::Decimal;
+    use data_models::levels::{Level, Levels};
+
+    use crate::api::error::ExchangeClientError;
+    use crate::implementation::binance::diff_depth::{DepthUpdate, DiffDepthBook};
+
+    fn update(first_update_id: u64, final_update_id: u64) -> DepthUpdate {
+        DepthUpdate {
+            first_update_id,
+            final_update_id,
+            event_time: None,
+            levels: Levels::new(vec![Level::new(dec!(1.0), Decimal::from(final_update_id))], vec![]),
+        }
+    }
+
+    #[test]
+    fn deserialize_test() {
+        let update = DepthUpdate::deserialize(&json!({
+            "e": "depthUpdate",
+            "E": 1686751543123u64,
+            "s": "ETHBTC",
+            "U": 157,
+            "u": 160,
+            "b": [["0.06870000", "12.30450000"]],
+            "a": [["0.06871000", "0.00000000"]]
+        })).unwrap();
+
+        assert_eq!(update.first_update_id, 157);
+        assert_eq!(update.final_update_id, 160);
+        assert_eq!(update.event_time, Some(1686751543123000));
+        assert_eq!(update.levels, Levels::new(
+            vec![Level::new(dec!(0.06870000), dec!(12.30450000))],
+            vec![Level::new(dec!(0.06871000), dec!(0))],
+        ));
+
+        assert!(DepthUpdate::deserialize(&json!({"u": 160, "b": [], "a": []})).is_err());
+    }
+
+    #[rstest]
+    // Updates up to the snapshot are skipped
+    #[case(vec![update(90, 100)], Ok(vec![false]))]
+    // The first update has to contain the update after the snapshot
+    #[case(vec![update(95, 105), update(106, 110)], Ok(vec![true, true]))]
+    #[case(vec![update(101, 101), update(102, 103)], Ok(vec![true, true]))]
+    #[case(vec![update(90, 100), update(101, 102)], Ok(vec![false, true]))]
+    #[case(vec![update(102, 105)], Err(()))]
+    // Every following update has to start right after the previous one
+    #[case(vec![update(95, 105), update(107, 110)], Err(()))]
+    fn apply_test(#[case] updates: Vec<DepthUpdate>, #[case] expected: Result<Vec<bool>, ()>) {
+        let mut book = DiffDepthBook::new(100, &Levels::new(vec![Level::new(dec!(1.0), dec!(100))], vec![]));
+
+        let result: Result<Vec<bool>, ExchangeClientError> = updates.iter().map(|update| book.apply(update)).collect();
+
+        match expected {
+            Ok(expected) => {
+                assert_eq!(result.unwrap(), expected);
+                let last_applied = updates.iter().map(|update| update.final_update_id).rfind(|id| *id > 100).unwrap_or(100);
+                assert_eq!(book.levels(1).bids, vec![Level::new(dec!(1.0), Decimal::from(last_applied))]);
+            }
+            Err(()) => assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_)))),
+        }
+    }
+}
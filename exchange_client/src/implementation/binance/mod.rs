@@ -1 +1,2 @@
 pub mod client;
+mod diff_depth;
\ No newline at end of file
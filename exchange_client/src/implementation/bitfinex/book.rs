@@ -0,0 +1,139 @@
+//! Maintains a local orderbook from the snapshot and the updates of the Bitfinex book channel, and verifies it
+//! against the checksums Bitfinex publishes, as documented in <https://docs.bitfinex.com/docs/ws-websocket-checksum>.
+
+use data_models::levels::Levels;
+
+use crate::api::error::ExchangeClientError;
+use crate::local_book::LocalBook;
+
+/// The number of levels per side the checksum is computed over.
+const CHECKSUM_DEPTH: usize = 25;
+
+/// The local orderbook of the book channel.
+pub struct BitfinexBook {
+    book: LocalBook,
+    /// The depth of the subscription. The levels beyond it are truncated, as Bitfinex stops updating them.
+    depth: usize,
+}
+
+impl BitfinexBook {
+    /// Constructs a new [BitfinexBook] from the snapshot of a subscription.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The depth of the subscription.
+    /// * `levels` - The bids and asks of the snapshot.
+    pub fn new(depth: usize, levels: &Levels) -> Self {
+        let mut book = LocalBook::default();
+        book.replace(levels);
+        book.truncate(depth);
+
+        BitfinexBook {
+            book,
+            depth,
+        }
+    }
+
+    /// Applies an update to the local orderbook.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - The levels that changed. A zero amount removes the level.
+    pub fn apply(&mut self, levels: &Levels) {
+        self.book.update(levels);
+        self.book.truncate(self.depth);
+    }
+
+    /// Verifies the local orderbook against a checksum of the book channel.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if the orderbook does not match the checksum,
+    /// e.g. because an update was missed.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The Bitfinex symbol of the book, e.g. `tETHBTC`.
+    /// * `expected` - The checksum Bitfinex published.
+    pub fn verify(&self, symbol: &str, expected: i32) -> Result<(), ExchangeClientError> {
+        let checksum = checksum(&self.book.levels(CHECKSUM_DEPTH));
+        if checksum != expected {
+            return Err(ExchangeClientError::OutOfSync(format!(
+                "The checksum {} of the local orderbook does not match the checksum {} of `{}`",
+                checksum, expected, symbol
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns up to `depth` of the best bids and asks of the local orderbook.
+    pub fn levels(&self, depth: usize) -> Levels {
+        self.book.levels(depth)
+    }
+}
+
+/// Computes the signed CRC32 checksum of the top levels of an orderbook: the bid and the ask of each rank,
+/// from the best, as `price:amount` joined by `:`, with the amounts of the asks negative.
+///
+/// The prices and amounts are formatted with the representation Bitfinex published them with,
+/// which is kept by the local orderbook.
+///
+/// # Arguments
+///
+/// * `levels` - The top 25 levels per side of the orderbook.
+pub fn checksum(levels: &Levels) -> i32 {
+    let mut values = Vec::with_capacity(4 * CHECKSUM_DEPTH);
+    for rank in 0..levels.bids.len().max(levels.asks.len()) {
+        if let Some(bid) = levels.bids.get(rank) {
+            values.push(format!("{}:{}", bid.price, bid.amount));
+        }
+        if let Some(ask) = levels.asks.get(rank) {
+            values.push(format!("{}:-{}", ask.price, ask.amount));
+        }
+    }
+    crc32fast::hash(values.join(":").as_bytes()) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::api::error::ExchangeClientError;
+    use crate::implementation::bitfinex::book::{checksum, BitfinexBook};
+
+    /// zlib.crc32(b"0.0531:1.25:0.05312:-10.5:0.05309:0.004") as a signed 32 bit integer
+    const CHECKSUM: i32 = 4046777;
+
+    #[test]
+    fn checksum_test() {
+        let levels = Levels::new(
+            vec![Level::new(dec!(0.0531), dec!(1.25)), Level::new(dec!(0.05309), dec!(0.004))],
+            vec![Level::new(dec!(0.05312), dec!(10.5))],
+        );
+
+        assert_eq!(checksum(&levels), CHECKSUM);
+        assert_eq!(checksum(&Levels::new(vec![], vec![])), 0);
+    }
+
+    #[test]
+    fn apply_test() {
+        let mut book = BitfinexBook::new(2, &Levels::new(
+            vec![Level::new(dec!(0.0531), dec!(1.25)), Level::new(dec!(0.05309), dec!(0.004))],
+            vec![Level::new(dec!(0.05312), dec!(10.5))],
+        ));
+        book.verify("tETHBTC", CHECKSUM).unwrap();
+
+        // The new best bid pushes the worst bid out of the subscribed depth
+        book.apply(&Levels::new(vec![Level::new(dec!(0.05311), dec!(2))], vec![]));
+        assert_eq!(book.levels(10), Levels::new(
+            vec![Level::new(dec!(0.05311), dec!(2)), Level::new(dec!(0.0531), dec!(1.25))],
+            vec![Level::new(dec!(0.05312), dec!(10.5))],
+        ));
+
+        book.apply(&Levels::new(vec![], vec![Level::new(dec!(0.05312), dec!(0))]));
+        assert_eq!(book.levels(10).asks, vec![]);
+
+        let result = book.verify("tETHBTC", CHECKSUM);
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+}
@@ -0,0 +1,311 @@
+//! A client implementation for the Bitfinex exchange, subscribing to the book channel of the websocket v2 api.
+//! The client is instantiated by [crate::api::provider].
+
+use std::sync::{Mutex, PoisonError};
+
+use futures_util::Sink;
+
+use data_models::symbol_format::SymbolFormat;
+
+use crate::client_re_exports::*;
+
+use super::book::BitfinexBook;
+use super::event::{BookFrame, Event, RECONNECT_CODE};
+use super::subscription::{Configuration, Subscription, CHECKSUM_FLAG, TIMESTAMP_FLAG};
+
+pub struct Bitfinex {
+    config: ExchangeClientConfig,
+    sender: MailboxSender,
+    exchange: Exchange,
+    /// The book channel of the instrument, assigned by Bitfinex on every subscription.
+    channel: Mutex<Option<Channel>>,
+}
+
+/// The channel messages are keyed by the channel id of the subscription, not by the symbol.
+struct Channel {
+    id: u64,
+    /// The local orderbook, replaced by the snapshot of the subscription.
+    book: Option<BitfinexBook>,
+}
+
+impl ExchangeClient for Bitfinex {
+
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+        Bitfinex {
+            config,
+            sender,
+            exchange: Exchange::Bitfinex,
+            channel: Mutex::new(None),
+        }
+    }
+
+    fn build_url(&self) -> Url {
+        self.config.base_url.clone()
+    }
+
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        // The channel id and the local orderbook are assigned by the new subscription
+        *self.channel.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        Ok((ws_write_stream, ws_read_stream))
+    }
+
+    fn on_ping(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_pong(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_close(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message)?)
+    }
+}
+
+impl Bitfinex {
+    /// Handles an [Event] of the Bitfinex websocket.
+    ///
+    /// Applies the snapshot and the updates of the book channel of the instrument to the local orderbook
+    /// and publishes its top levels. The messages of other channels are ignored.
+    /// Returns [ExchangeClientError::OutOfSync] if an update arrives before the snapshot, or if the local orderbook
+    /// does not match a checksum, so that the client reconnects and resubscribes to a new snapshot.
+    /// Returns [ExchangeClientError::ReconnectRequested] if Bitfinex asks the client to reconnect
+    /// and [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::Snapshot(frame) => {
+                let mut channel = self.channel.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(channel) = channel.as_mut().filter(|channel| channel.id == frame.channel_id) else {
+                    return Self::ignore(frame.channel_id);
+                };
+                let book = channel.book.insert(BitfinexBook::new(self.subscription().depth(), &frame.levels));
+                self.publish(book, &frame)
+            }
+            Event::Update(frame) => {
+                let mut channel = self.channel.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(channel) = channel.as_mut().filter(|channel| channel.id == frame.channel_id) else {
+                    return Self::ignore(frame.channel_id);
+                };
+                let book = channel.book.as_mut()
+                    .ok_or_else(|| ExchangeClientError::OutOfSync("An update was received before the snapshot".to_string()))?;
+                book.apply(&frame.levels);
+                self.publish(book, &frame)
+            }
+            Event::Checksum { channel_id, checksum } => {
+                let mut channel = self.channel.lock().unwrap_or_else(PoisonError::into_inner);
+                let Some(channel) = channel.as_mut().filter(|channel| channel.id == channel_id) else {
+                    return Self::ignore(channel_id);
+                };
+                let result = channel.book.as_ref().map_or(Ok(()), |book| book.verify(&self.symbol(), checksum));
+                if result.is_err() {
+                    channel.book = None;
+                }
+                result
+            }
+            Event::Heartbeat { .. } => Ok(()),
+            Event::Subscribed { channel_id, channel, symbol } if channel == "book" && symbol == self.symbol() => {
+                println!("Subscribed successfully to `{}` : `{}` `{}` on channel {}", self.exchange, channel, symbol, channel_id);
+                *self.channel.lock().unwrap_or_else(PoisonError::into_inner) = Some(Channel { id: channel_id, book: None });
+                Ok(())
+            }
+            Event::Subscribed { channel_id, channel, symbol } => {
+                println!("Ignoring `{}` subscription `{}` `{}` on channel {}", self.exchange, channel, symbol, channel_id);
+                Ok(())
+            }
+            Event::Info { code: Some(RECONNECT_CODE), message } => {
+                println!("`{}` requested a reconnect : `{}`", self.exchange, message);
+                Err(ExchangeClientError::ReconnectRequested)
+            }
+            Event::Info { message, .. } => {
+                println!("`{}` info : `{}`", self.exchange, message);
+                Ok(())
+            }
+            Event::Conf { flags, accepted: false } => {
+                println!("`{}` did not enable the flags {}, the checksums are not verified", self.exchange, flags);
+                Ok(())
+            }
+            Event::Conf { .. } => Ok(()),
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::Unknown(message) => {
+                println!("Ignoring `{}` message `{}`", self.exchange, message);
+                Ok(())
+            }
+        }
+    }
+
+    fn ignore(channel_id: u64) -> Result<(), ExchangeClientError> {
+        println!("Ignoring `{}` message of channel {}", Exchange::Bitfinex, channel_id);
+        Ok(())
+    }
+
+    /// Publishes the top levels of the local orderbook after the snapshot or an update.
+    /// Bitfinex does not publish an update id, so the snapshot only carries the exchange timestamp.
+    fn publish(&self, book: &BitfinexBook, frame: &BookFrame) -> Result<(), ExchangeClientError> {
+        let snapshot = OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), book.levels(self.config.depth))
+            .with_exchange_timestamp(frame.timestamp)
+            .with_received_timestamp(timestamp::now_micros());
+        self.on_deserialized(&self.sender, snapshot)
+    }
+
+    fn subscription(&self) -> Subscription {
+        Subscription::new(&self.symbol(), self.config.depth)
+    }
+
+    /// Returns the Bitfinex symbol of the instrument, e.g. `tETHBTC`. The `t` prefix denotes a trading pair,
+    /// and the assets are separated by a colon if either code is longer than 3 characters, e.g. `tDOGE:USD`.
+    fn symbol(&self) -> String {
+        let instrument = &self.config.instrument;
+        let format = self.exchange.symbol_format();
+        let format = match format.asset(&instrument.base).len().max(format.asset(&instrument.quote).len()) {
+            3 => format,
+            _ => SymbolFormat { separator: ":", ..format },
+        };
+        format!("t{}", instrument.native_symbol(&format))
+    }
+
+    /// Enables the checksums and timestamps of the book channel, subscribes to it and waits for Bitfinex
+    /// to confirm the subscription, see [ExchangeClient::await_confirmation]. Messages that arrive before
+    /// the confirmation, e.g. the info message of the connection, are handled.
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        self.send_subscription(ws_write_stream, &Configuration::new(CHECKSUM_FLAG | TIMESTAMP_FLAG).serialize()).await?;
+        self.send_subscription(ws_write_stream, &self.subscription().serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => {
+                let confirmed = matches!(&event, Event::Subscribed { channel, symbol, .. } if channel == "book" && *symbol == self.symbol());
+                self.on_event(event).map(|()| confirmed)
+            }
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{sink, stream, SinkExt};
+    use rust_decimal_macros::dec;
+
+    use data_models::instrument::Instrument;
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+
+    use super::*;
+
+    /// Messages of the book channel of `tETHBTC`. The checksums are the signed CRC32 of the interleaved levels:
+    /// zlib.crc32(b"0.0531:1.25:0.05312:-10.5:0.05309:0.004") for the snapshot and
+    /// zlib.crc32(b"0.0531:1.25:0.05312:-10.5") after the update.
+    const SUBSCRIBED: &str = r#"{"event":"subscribed","channel":"book","chanId":17082,"symbol":"tETHBTC","prec":"P0","freq":"F0","len":"25","pair":"ETHBTC"}"#;
+    const SNAPSHOT: &str = r#"[17082,[[0.0531,2,1.25],[0.05309,1,0.004],[0.05312,3,-10.5]],1696613755440]"#;
+    const SNAPSHOT_CHECKSUM: &str = r#"[17082,"cs",4046777,1696613755441]"#;
+    const UPDATE: &str = r#"[17082,[0.05309,0,1],1696613756012]"#;
+    const UPDATE_CHECKSUM: &str = r#"[17082,"cs",461737145,1696613756013]"#;
+    const HEARTBEAT: &str = r#"[17082,"hb",1696613757000]"#;
+
+    fn client(sender: MailboxSender) -> Bitfinex {
+        Bitfinex::new(ExchangeClientConfig::new("wss://localhost/ws/2".to_string(), 1, InstrumentRegistry::default().get("ethbtc").unwrap().clone()), sender)
+    }
+
+    #[tokio::test]
+    async fn process_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+        client.on_event(Event::parse(SUBSCRIBED).unwrap()).unwrap();
+
+        let messages = vec![
+            Ok(Message::text(SNAPSHOT)), Ok(Message::text(SNAPSHOT_CHECKSUM)), Ok(Message::text(HEARTBEAT)),
+            // The messages of other channels are ignored
+            Ok(Message::text(UPDATE.replace("17082", "17083"))),
+        ];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange, Exchange::Bitfinex);
+        assert_eq!(snapshot.symbol, "ethbtc");
+        assert_eq!(snapshot.sequence, None);
+        assert_eq!(snapshot.exchange_timestamp, Some(1696613755440000));
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.0531), dec!(1.25))], vec![Level::new(dec!(0.05312), dec!(10.5))]));
+        assert!(receiver.try_recv().is_none());
+
+        let messages = vec![Ok(Message::text(UPDATE)), Ok(Message::text(UPDATE_CHECKSUM))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange_timestamp, Some(1696613756012000));
+        assert_eq!(client.channel.lock().unwrap().as_ref().unwrap().book.as_ref().unwrap().levels(10).bids, vec![Level::new(dec!(0.0531), dec!(1.25))]);
+    }
+
+    #[tokio::test]
+    async fn process_stream_checksum_mismatch_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+        client.on_event(Event::parse(SUBSCRIBED).unwrap()).unwrap();
+
+        // The checksum still includes the deleted bid, as if an update had been missed
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(UPDATE)), Ok(Message::text(SNAPSHOT_CHECKSUM))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+        assert!(receiver.recv_snapshot().await.is_some());
+        // The local orderbook is dropped until the client reconnects and resubscribes
+        assert!(client.channel.lock().unwrap().as_ref().unwrap().book.is_none());
+    }
+
+    #[tokio::test]
+    async fn process_stream_reconnect_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+
+        let messages = vec![Ok(Message::text(r#"{"event":"info","code":20051,"msg":"Stop/Restart Websocket Server (please reconnect)"}"#))];
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::ReconnectRequested)));
+    }
+
+    #[tokio::test]
+    async fn process_stream_update_before_snapshot_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+        client.on_event(Event::parse(SUBSCRIBED).unwrap()).unwrap();
+
+        let messages = vec![Ok(Message::text(UPDATE))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+
+    #[test]
+    fn symbol_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let registry = InstrumentRegistry::default();
+
+        let symbol = |instrument: &Instrument| Bitfinex::new(ExchangeClientConfig::new("wss://localhost/ws/2".to_string(), 1, instrument.clone()), sender.clone()).symbol();
+
+        assert_eq!(symbol(registry.get("ethbtc").unwrap()), "tETHBTC");
+        assert_eq!(symbol(registry.get("btcusd").unwrap()), "tBTCUSD");
+        assert_eq!(symbol(registry.get("btcusdt").unwrap()), "tBTCUST");
+        assert_eq!(symbol(&Instrument::new("DOGE", "USD", dec!(0.00001), dec!(1))), "tDOGE:USD");
+    }
+}
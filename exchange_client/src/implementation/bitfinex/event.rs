@@ -0,0 +1,216 @@
+//! Decodes the messages of the Bitfinex websocket v2 api.
+//!
+//! The responses to requests are JSON objects with an `event`, e.g. `{"event":"subscribed","channel":"book","chanId":17082,...}`.
+//! The channel messages are positional arrays keyed by the channel id of the subscription:
+//! * `[17082, [[price, count, amount], ...]]` - the snapshot of the book.
+//! * `[17082, [price, count, amount]]` - an update of a single level.
+//! * `[17082, "cs", checksum]` - the checksum of the book, if the [super::subscription::CHECKSUM_FLAG] is enabled.
+//! * `[17082, "hb"]` - the heartbeat Bitfinex sends when the channel is idle.
+//!
+//! If the [super::subscription::TIMESTAMP_FLAG] is enabled, the timestamp in milliseconds is appended to every message.
+
+use serde::de::Unexpected;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use data_models::level::Decimal;
+use data_models::levels::{Level, Levels};
+
+use crate::deserialization::serde::decimal;
+
+/// The info code of the request to reconnect, e.g. ahead of a restart of the websocket server.
+pub const RECONNECT_CODE: u64 = 20051;
+
+/// The response to a request.
+#[derive(Deserialize)]
+struct Envelope {
+    event: String,
+    #[serde(flatten)]
+    fields: Value,
+}
+
+/// A snapshot or an update of the book channel.
+#[derive(Debug, PartialEq)]
+pub struct BookFrame {
+    pub channel_id: u64,
+    /// The levels of the snapshot, or the level that changed. A zero amount removes the level.
+    pub levels: Levels,
+    /// The time of the message in microseconds since the unix epoch, if the timestamp flag is enabled.
+    pub timestamp: Option<u64>,
+}
+
+/// The events of the Bitfinex websocket the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The information Bitfinex sends on connect and ahead of a maintenance, holding its code, if any, and its message.
+    Info { code: Option<u64>, message: String },
+    /// The flags of the connection were configured.
+    Conf { flags: u64, accepted: bool },
+    /// The subscription to the given channel and symbol was confirmed, and assigned the given channel id.
+    Subscribed { channel_id: u64, channel: String, symbol: String },
+    /// Bitfinex rejected a request, holding the error message.
+    Error(String),
+    Snapshot(BookFrame),
+    Update(BookFrame),
+    /// The checksum of the top 25 levels per side of the book after the latest update.
+    Checksum { channel_id: u64, checksum: i32 },
+    Heartbeat { channel_id: u64 },
+    /// Any other message, holding its event.
+    Unknown(String),
+}
+
+impl Event {
+    /// Parses a message of the Bitfinex websocket v2 api.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse(message: &str) -> Result<Event, serde_json::Error> {
+        if message.trim_start().starts_with('[') {
+            return Self::parse_channel_message(message);
+        }
+
+        let envelope: Envelope = serde_json::from_str(message)?;
+        let fields = &envelope.fields;
+        let message_text = || fields["msg"].as_str().map_or_else(|| fields.to_string(), str::to_string);
+
+        Ok(match envelope.event.as_str() {
+            "info" => Event::Info { code: fields["code"].as_u64(), message: message_text() },
+            "conf" => Event::Conf {
+                flags: fields["flags"].as_u64().unwrap_or_default(),
+                accepted: fields["status"] == "OK",
+            },
+            "subscribed" => Event::Subscribed {
+                channel_id: fields["chanId"].as_u64().ok_or_else(|| serde::de::Error::missing_field("chanId"))?,
+                channel: fields["channel"].as_str().unwrap_or_default().to_string(),
+                symbol: fields["symbol"].as_str().unwrap_or_default().to_string(),
+            },
+            "error" => Event::Error(match fields["code"].as_u64() {
+                Some(code) => format!("{} ({})", message_text(), code),
+                None => message_text(),
+            }),
+            _ => Event::Unknown(envelope.event),
+        })
+    }
+
+    fn parse_channel_message(message: &str) -> Result<Event, serde_json::Error> {
+        let elements: Vec<&RawValue> = serde_json::from_str(message)?;
+        let Some((channel_id, payload)) = elements.first().zip(elements.get(1)) else {
+            return Err(serde::de::Error::invalid_length(elements.len(), &"a channel message [<channel id>, <payload>, ...]"));
+        };
+        let channel_id: u64 = serde_json::from_str(channel_id.get())?;
+
+        if payload.get().starts_with('"') {
+            let message_type: String = serde_json::from_str(payload.get())?;
+            return Ok(match message_type.as_str() {
+                "hb" => Event::Heartbeat { channel_id },
+                "cs" => Event::Checksum {
+                    channel_id,
+                    checksum: serde_json::from_str(elements.get(2).ok_or_else(|| serde::de::Error::missing_field("checksum"))?.get())?,
+                },
+                _ => Event::Unknown(message_type),
+            });
+        }
+
+        // The timestamp follows the payload if the timestamp flag is enabled
+        let timestamp = match elements.get(2) {
+            Some(timestamp) => Some(serde_json::from_str::<u64>(timestamp.get())? * 1000),
+            None => None,
+        };
+        let entries: Vec<&RawValue> = serde_json::from_str(payload.get())?;
+
+        // The snapshot is an array of entries, an update is a single entry
+        if entries.first().is_none_or(|entry| entry.get().starts_with('[')) {
+            let mut levels = Levels::new(vec![], vec![]);
+            for entry in entries {
+                add_entry(&mut levels, &serde_json::from_str::<Vec<&RawValue>>(entry.get())?)?;
+            }
+            Ok(Event::Snapshot(BookFrame { channel_id, levels, timestamp }))
+        } else {
+            let mut levels = Levels::new(vec![], vec![]);
+            add_entry(&mut levels, &entries)?;
+            Ok(Event::Update(BookFrame { channel_id, levels, timestamp }))
+        }
+    }
+}
+
+/// Adds an entry of the book channel, `[price, count, amount]`, to the bids or asks of the given [Levels].
+///
+/// A positive amount is a bid and a negative amount an ask. A count of 0 deletes the level, in which case
+/// the amount is 1 for a bid and -1 for an ask, and the level is added with a zero amount.
+fn add_entry(levels: &mut Levels, entry: &[&RawValue]) -> Result<(), serde_json::Error> {
+    let [price, count, amount] = entry else {
+        return Err(serde::de::Error::invalid_length(entry.len(), &"an entry [<price>, <count>, <amount>]"));
+    };
+    let price = decimal(price)?;
+    let count: u64 = serde_json::from_str(count.get())?;
+    let amount = decimal(amount)?;
+
+    let level = |amount: Decimal| Level::new(price, if count == 0 { Decimal::ZERO } else { amount });
+    if amount.is_sign_positive() && !amount.is_zero() {
+        levels.bids.push(level(amount));
+    } else if amount.is_sign_negative() && !amount.is_zero() {
+        levels.asks.push(level(-amount));
+    } else {
+        return Err(serde::de::Error::invalid_value(Unexpected::Other(&amount.to_string()), &"a non zero amount"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::implementation::bitfinex::event::{BookFrame, Event};
+
+    #[rstest]
+    #[case(r#"{"event":"info","version":2,"serverId":"9e2e5e3a-2c1c-4a4d-a15f-8e0f8a7d1e3b","platform":{"status":1}}"#,
+           Event::Info { code: None, message: r#"{"platform":{"status":1},"serverId":"9e2e5e3a-2c1c-4a4d-a15f-8e0f8a7d1e3b","version":2}"#.to_string() })]
+    #[case(r#"{"event":"info","code":20051,"msg":"Stop/Restart Websocket Server (please reconnect)"}"#,
+           Event::Info { code: Some(20051), message: "Stop/Restart Websocket Server (please reconnect)".to_string() })]
+    #[case(r#"{"event":"conf","status":"OK","flags":163840}"#, Event::Conf { flags: 163840, accepted: true })]
+    #[case(r#"{"event":"subscribed","channel":"book","chanId":17082,"symbol":"tETHBTC","prec":"P0","freq":"F0","len":"25","pair":"ETHBTC"}"#,
+           Event::Subscribed { channel_id: 17082, channel: "book".to_string(), symbol: "tETHBTC".to_string() })]
+    #[case(r#"{"event":"error","msg":"symbol: invalid","code":10300}"#, Event::Error("symbol: invalid (10300)".to_string()))]
+    #[case(r#"{"event":"pong","ts":1511545528111}"#, Event::Unknown("pong".to_string()))]
+    #[case(r#"[17082,"hb"]"#, Event::Heartbeat { channel_id: 17082 })]
+    #[case(r#"[17082,"hb",1696613755440]"#, Event::Heartbeat { channel_id: 17082 })]
+    #[case(r#"[17082,"cs",-1618423339,1696613755440]"#, Event::Checksum { channel_id: 17082, checksum: -1618423339 })]
+    #[case(r#"[17082,[0.05310,2,1.25],1696613755440]"#, Event::Update(BookFrame {
+        channel_id: 17082,
+        levels: Levels::new(vec![Level::new(dec!(0.05310), dec!(1.25))], vec![]),
+        timestamp: Some(1696613755440000),
+    }))]
+    // A count of 0 deletes the level, the sign of the amount tells its side
+    #[case(r#"[17082,[0.05312,0,-1]]"#, Event::Update(BookFrame {
+        channel_id: 17082,
+        levels: Levels::new(vec![], vec![Level::new(dec!(0.05312), dec!(0))]),
+        timestamp: None,
+    }))]
+    #[case(r#"[17082,[[0.05310,2,1.25],[0.05309,1,0.004],[0.05312,3,-10.5]]]"#, Event::Snapshot(BookFrame {
+        channel_id: 17082,
+        levels: Levels::new(vec![Level::new(dec!(0.05310), dec!(1.25)), Level::new(dec!(0.05309), dec!(0.004))], vec![Level::new(dec!(0.05312), dec!(10.5))]),
+        timestamp: None,
+    }))]
+    #[case(r#"[17082,[]]"#, Event::Snapshot(BookFrame { channel_id: 17082, levels: Levels::new(vec![], vec![]), timestamp: None }))]
+    fn parse_test(#[case] message: &str, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(message).unwrap(), expected_event);
+    }
+
+    #[rstest]
+    #[case(r#"{"channel":"book"}"#)]
+    #[case(r#"{"event":"subscribed","channel":"book"}"#)]
+    #[case(r#"[17082]"#)]
+    #[case(r#"["17082","hb"]"#)]
+    #[case(r#"[17082,"cs"]"#)]
+    #[case(r#"[17082,[0.05310,2]]"#)]
+    #[case(r#"[17082,[0.05310,2,0]]"#)]
+    #[case(r#"[17082,[["a",2,1.25]]]"#)]
+    fn parse_fails_test(#[case] message: &str) {
+        assert!(Event::parse(message).is_err());
+    }
+}
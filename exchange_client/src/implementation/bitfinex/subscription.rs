@@ -0,0 +1,66 @@
+use serde::Serialize;
+
+/// The depths of the book channel Bitfinex publishes. The checksum covers the top 25 levels per side,
+/// so the book is subscribed with at least 25 levels.
+const DEPTHS: [usize; 3] = [25, 100, 250];
+
+/// The flag of the `conf` request that enables the checksum of the book channel.
+pub const CHECKSUM_FLAG: u64 = 131072;
+/// The flag of the `conf` request that appends the timestamp in milliseconds to every channel message.
+pub const TIMESTAMP_FLAG: u64 = 32768;
+
+/// Configures the optional flags of the connection.
+#[derive(Serialize)]
+pub struct Configuration {
+    event: String,
+    flags: u64,
+}
+
+impl Configuration {
+    pub fn new(flags: u64) -> Self {
+        Configuration {
+            event: "conf".into(),
+            flags,
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Serialize)]
+pub struct Subscription {
+    event: String,
+    channel: String,
+    symbol: String,
+    /// The price aggregation, `P0` publishes the prices unaggregated.
+    prec: String,
+    /// The frequency of the updates, `F0` publishes them in realtime.
+    freq: String,
+    len: String,
+}
+
+impl Subscription {
+    /// Subscribes to the realtime, unaggregated book channel of the given symbol, with the smallest depth
+    /// Bitfinex publishes that covers the given depth.
+    pub fn new(symbol: &str, depth: usize) -> Self {
+        Subscription {
+            event: "subscribe".into(),
+            channel: "book".into(),
+            symbol: symbol.to_string(),
+            prec: "P0".into(),
+            freq: "F0".into(),
+            len: DEPTHS.into_iter().find(|published| *published >= depth).unwrap_or(DEPTHS[DEPTHS.len() - 1]).to_string(),
+        }
+    }
+
+    /// Returns the number of levels per side of the subscribed book.
+    pub fn depth(&self) -> usize {
+        self.len.parse().unwrap()
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
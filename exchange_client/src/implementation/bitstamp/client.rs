@@ -7,15 +7,18 @@ use data_models::exchange_orderbook::OrderbookSnapshot;
 
 use crate::client_re_exports::*;
 
+use super::event::Event;
+use super::subscription::Subscription;
+
 pub struct Bitstamp {
     config: ExchangeClientConfig,
-    sender: UnboundedSender<OrderbookSnapshot>,
+    sender: MailboxSender,
     exchange: Exchange,
 }
 
 impl ExchangeClient for Bitstamp {
 
-    fn new(config: ExchangeClientConfig, sender: UnboundedSender<OrderbookSnapshot>) -> Self {
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
         Bitstamp {
             config,
             sender,
@@ -27,22 +30,22 @@ impl ExchangeClient for Bitstamp {
         self.config.base_url.clone()
     }
 
-    async fn connect(&self, url: &Url) -> (impl Sink<Message>, impl Stream<Item=Result<Message, Error>> + Unpin) {
-        println!("Connecting to `{}` : `{}`", self.exchange, url.as_str());
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
 
-        let ws_stream = match connect_async(url.as_str()).await {
-            Ok((ws_stream, _)) => {
-                println!("Connected to `{}` : `{}`", self.exchange, url.as_str());
-                ws_stream
-            }
-            Err(error) => {
-                panic!("Error connecting to `{}` : `{}`", self.exchange, error);
-            }
-        };
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
 
-        let (mut ws_write_stream, mut ws_read_stream) = ws_stream.split();
-        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await;
-        (ws_write_stream, ws_read_stream)
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        Ok((ws_write_stream, ws_read_stream))
     }
 
     fn on_ping(&self, message: &Message) {
@@ -57,61 +60,165 @@ impl ExchangeClient for Bitstamp {
         println!("{}", message)
     }
 
-    fn deserialize(&self, message: &String) {
-        _ = match levels::deserialize(
-            self.config.depth,
-            message,
-            |value| -> &serde_json::Value { &value["data"]["bids"] },
-            |value| -> &serde_json::Value { &value["data"]["asks"] },
-        ) {
-            Ok(levels) => {
-                let snapshot = OrderbookSnapshot::new(
-                    self.exchange,
-                    self.config.symbol.clone(),
-                    levels);
-                self.on_deserialized(&self.sender, snapshot);
-            }
-            Err(error) => self.on_deserialization_error(error)
-        }
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message)?)
     }
 }
 
 impl Bitstamp {
-    async fn subscribe(
-        &self,
-        ws_write_stream: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-        ws_read_stream: &mut SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    ) {
-        let subscription_msg =
-            super::subscription::Subscription::new(&self.config.symbol.to_string()).serialize();
-
-        match ws_write_stream.send(Message::text(&subscription_msg)).await {
-            Ok(()) => {
-                println!(
-                    "Subscribing to `{}` : `{}`",
-                    self.exchange,
-                    &subscription_msg
-                )
+    /// Handles an [Event] of the Bitstamp websocket.
+    ///
+    /// Publishes the orderbook snapshot of [Event::Data].
+    /// Returns [ExchangeClientError::ReconnectRequested] on [Event::RequestReconnect]
+    /// and [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::Data(data) => {
+                let snapshot = self.deserialize_snapshot(&data)?;
+                self.on_deserialized(&self.sender, snapshot)
+            }
+            Event::RequestReconnect => {
+                println!("`{}` requested a reconnect", self.exchange);
+                Err(ExchangeClientError::ReconnectRequested)
+            }
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::SubscriptionSucceeded(channel) => {
+                println!("Subscribed successfully to `{}` : `{}`", self.exchange, channel);
+                Ok(())
             }
-            Err(error) => {
-                panic!(
-                    "Could not subscribe to `{}` : `{}`",
-                    self.exchange,
-                    error
-                )
+            Event::Unknown(event) => {
+                println!("Ignoring `{}` event `{}`", self.exchange, event);
+                Ok(())
             }
         }
+    }
+
+    /// Deserializes the `data` of an order book event into an [OrderbookSnapshot].
+    /// Bitstamp does not publish an update id, so the snapshot only carries the
+    /// exchange timestamp, read from `microtimestamp` or, if missing, from `timestamp`.
+    fn deserialize_snapshot(&self, data: &serde_json::Value) -> Result<OrderbookSnapshot, serde_json::Error> {
+        let received_timestamp = timestamp::now_micros();
+
+        let levels = levels::deserialize(
+            self.config.depth,
+            data,
+            |value| -> &serde_json::Value { &value["bids"] },
+            |value| -> &serde_json::Value { &value["asks"] },
+        )?;
+
+        let exchange_timestamp = metadata::as_u64(&data["microtimestamp"])
+            .or_else(|| metadata::as_u64(&data["timestamp"]).map(|seconds| seconds * 1_000_000));
+
+        Ok(OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), levels)
+            .with_exchange_timestamp(exchange_timestamp)
+            .with_received_timestamp(received_timestamp))
+    }
 
-        match ws_read_stream.next().await {
-            None => {}
-            Some(result) => match result {
-                Ok(message) => {
-                    println!("Subscribed successfully to `{}` : `{}`", self.exchange, message.into_text().unwrap())
-                }
-                Err(err) => {
-                    panic!("Could not subscribe to `{}` : `{}`", self.exchange, err)
-                }
+    /// Subscribes to the order book channel and waits for Bitstamp to confirm the subscription,
+    /// see [ExchangeClient::await_confirmation]. Order book updates that arrive before the confirmation are published.
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        let subscription = Subscription::new(&self.config.instrument.exchange_symbol(self.exchange));
+        self.send_subscription(ws_write_stream, &subscription.serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::SubscriptionSucceeded(channel) if channel == subscription.channel() => {
+                self.on_event(Event::SubscriptionSucceeded(channel)).map(|()| true)
+            }
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => self.on_event(event).map(|()| false),
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, SinkExt};
+    use rust_decimal_macros::dec;
+    use serde_json::json;
+
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::Level;
+
+    use super::*;
+
+    fn client(sender: MailboxSender) -> Bitstamp {
+        Bitstamp::new(ExchangeClientConfig::new("wss://localhost".to_string(), 1, InstrumentRegistry::default().get("ethbtc").unwrap().clone()), sender)
+    }
+
+    fn data_message(microtimestamp: u64) -> Message {
+        Message::text(json!({
+            "data": {
+                "microtimestamp": microtimestamp.to_string(),
+                "bids": [["0.06870000", "12.30450000"]],
+                "asks": [["0.06871000", "1.00000000"]]
             },
-        }
+            "channel": "order_book_ethbtc",
+            "event": "data"
+        }).to_string())
+    }
+
+    fn event_message(event: &str, channel: &str, data: serde_json::Value) -> Message {
+        Message::text(json!({"event": event, "channel": channel, "data": data}).to_string())
+    }
+
+    #[test]
+    fn deserialize_snapshot_test() {
+        let (sender, _) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+        let data = json!({
+            "timestamp": "1686751543",
+            "microtimestamp": "1686751543123456",
+            "bids": [["0.06870000", "12.30450000"]],
+            "asks": [["0.06871000", "1.00000000"]]
+        });
+
+        let snapshot = client.deserialize_snapshot(&data).unwrap();
+
+        assert_eq!(snapshot.exchange, Exchange::Bitstamp);
+        assert_eq!(snapshot.sequence, None);
+        assert_eq!(snapshot.exchange_timestamp, Some(1686751543123456));
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.06870000), dec!(12.30450000))]);
+        assert_eq!(snapshot.levels.asks, vec![Level::new(dec!(0.06871000), dec!(1.00000000))]);
+    }
+
+    #[tokio::test]
+    async fn subscribe_publishes_data_before_confirmation_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let mut sent = Vec::<Message>::new();
+        let mut messages = stream::iter(vec![
+            Ok(data_message(1)),
+            Ok(event_message("bts:subscription_succeeded", "order_book_ethbtc", json!({}))),
+            Ok(data_message(2)),
+        ]);
+
+        let result = client(sender).subscribe(&mut (&mut sent).sink_map_err(|_| Error::ConnectionClosed), &mut messages).await;
+
+        assert!(result.is_ok());
+        assert_eq!(sent, vec![Message::text(Subscription::new("ethbtc").serialize())]);
+        assert_eq!(receiver.recv_snapshot().await.unwrap().exchange_timestamp, Some(1));
+        // The messages after the confirmation are left for process_stream
+        assert!(receiver.try_recv().is_none());
+        assert!(messages.next().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn process_stream_reconnect_request_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let messages = vec![
+            Ok(data_message(1)),
+            Ok(event_message("bts:request_reconnect", "", json!(""))),
+            Ok(data_message(2)),
+        ];
+        let mut sent = Vec::<Message>::new();
+
+        let result = client(sender).process_stream((&mut sent).sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::ReconnectRequested)));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().exchange_timestamp, Some(1));
+        assert!(receiver.try_recv().is_none());
     }
 }
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// The envelope of every message Bitstamp publishes on its websocket.
+#[derive(Deserialize)]
+struct Envelope {
+    event: String,
+    #[serde(default)]
+    channel: String,
+    #[serde(default)]
+    data: Value,
+}
+
+/// The events of the Bitstamp websocket the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The subscription to the given channel was confirmed.
+    SubscriptionSucceeded(String),
+    /// An update of the subscribed channel, holding the `data` of the message.
+    Data(Value),
+    /// Bitstamp is about to close the connection, e.g. for maintenance, and asks the client to reconnect.
+    RequestReconnect,
+    /// Bitstamp rejected a request, holding the error message.
+    Error(String),
+    /// Any other event, holding its name.
+    Unknown(String),
+}
+
+impl Event {
+    /// Parses the event envelope of a Bitstamp message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse(message: &str) -> Result<Event, serde_json::Error> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+
+        Ok(match envelope.event.as_str() {
+            "data" => Event::Data(envelope.data),
+            "bts:subscription_succeeded" => Event::SubscriptionSucceeded(envelope.channel),
+            "bts:request_reconnect" => Event::RequestReconnect,
+            "bts:error" => Event::Error(match &envelope.data["message"] {
+                Value::String(message) => message.clone(),
+                _ => envelope.data.to_string(),
+            }),
+            _ => Event::Unknown(envelope.event),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use serde_json::json;
+
+    use crate::implementation::bitstamp::event::Event;
+
+    #[rstest]
+    #[case(json!({"event": "bts:subscription_succeeded", "channel": "order_book_ethbtc", "data": {}}), Event::SubscriptionSucceeded("order_book_ethbtc".to_string()))]
+    #[case(json!({"event": "data", "channel": "order_book_ethbtc", "data": {"bids": []}}), Event::Data(json!({"bids": []})))]
+    #[case(json!({"event": "bts:request_reconnect", "channel": "", "data": ""}), Event::RequestReconnect)]
+    #[case(json!({"event": "bts:error", "channel": "", "data": {"code": null, "message": "Incorrect JSON format."}}), Event::Error("Incorrect JSON format.".to_string()))]
+    #[case(json!({"event": "bts:unsubscription_succeeded"}), Event::Unknown("bts:unsubscription_succeeded".to_string()))]
+    fn parse_test(#[case] message: serde_json::Value, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(&message.to_string()).unwrap(), expected_event);
+    }
+
+    #[test]
+    fn parse_fails_test() {
+        assert!(Event::parse(r#"{"channel": "order_book_ethbtc"}"#).is_err());
+        assert!(Event::parse("[]").is_err());
+    }
+}
@@ -1,2 +1,3 @@
 pub mod client;
-mod subscription;
+mod event;
+mod subscription;
\ No newline at end of file
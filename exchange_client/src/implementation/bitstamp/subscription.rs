@@ -12,7 +12,7 @@ struct Data {
 }
 
 impl Subscription {
-    pub fn new(symbol: &String) -> Self {
+    pub fn new(symbol: &str) -> Self {
         Subscription {
             event: "bts:subscribe".into(),
             data: Data {
@@ -21,6 +21,10 @@ impl Subscription {
         }
     }
 
+    pub fn channel(&self) -> &str {
+        &self.data.channel
+    }
+
     pub fn serialize(&self) -> String {
         serde_json::to_string(self).unwrap()
     }
@@ -0,0 +1,153 @@
+//! Maintains a local orderbook from the snapshot and the `l2update`s of the Coinbase level2 channel.
+//!
+//! The level2 messages carry no sequence numbers, so the feed is checked through the heartbeat channel instead:
+//! Coinbase publishes a heartbeat every second with the latest sequence number of the product. The local orderbook
+//! is out of sync if the sequence goes back, e.g. because the feed was reset, or if the heartbeats go missing while
+//! the updates keep coming, since the updates may then be incomplete as well.
+
+use std::time::Duration;
+
+use data_models::levels::Levels;
+
+use crate::api::error::ExchangeClientError;
+use crate::local_book::LocalBook;
+
+use super::event::{Heartbeat, L2Update};
+
+/// The longest interval between two heartbeats, or between the last heartbeat and an update,
+/// before the heartbeats are considered missing.
+pub const MAX_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The local orderbook of a product.
+pub struct CoinbaseBook {
+    book: LocalBook,
+    /// The sequence number and the time of the latest heartbeat.
+    heartbeat: Option<(u64, u64)>,
+}
+
+impl CoinbaseBook {
+    /// Constructs a new [CoinbaseBook] from the snapshot of a subscription.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - The bids and asks of the snapshot.
+    pub fn new(levels: &Levels) -> Self {
+        let mut book = LocalBook::default();
+        book.replace(levels);
+
+        CoinbaseBook {
+            book,
+            heartbeat: None,
+        }
+    }
+
+    /// Applies an update to the local orderbook.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if no heartbeat was received within [MAX_HEARTBEAT_INTERVAL]
+    /// before the update.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The [L2Update] of the level2 channel.
+    pub fn apply(&mut self, update: &L2Update) -> Result<(), ExchangeClientError> {
+        if let (Some((sequence, heartbeat_time)), Some(time)) = (self.heartbeat, update.time) {
+            if time.saturating_sub(heartbeat_time) > MAX_HEARTBEAT_INTERVAL.as_micros() as u64 {
+                return Err(ExchangeClientError::OutOfSync(format!(
+                    "No heartbeat was received within {:?} of the update of `{}` after sequence {}",
+                    MAX_HEARTBEAT_INTERVAL, update.product_id, sequence
+                )));
+            }
+        }
+
+        self.book.update(&update.levels);
+        Ok(())
+    }
+
+    /// Records a heartbeat.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if the sequence of the heartbeat is lower than the sequence of the
+    /// previous heartbeat, or if the heartbeat arrived more than [MAX_HEARTBEAT_INTERVAL] after the previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `heartbeat` - The [Heartbeat] of the heartbeat channel.
+    pub fn on_heartbeat(&mut self, heartbeat: &Heartbeat) -> Result<(), ExchangeClientError> {
+        if let Some((sequence, time)) = self.heartbeat {
+            if heartbeat.sequence < sequence {
+                return Err(ExchangeClientError::OutOfSync(format!(
+                    "The sequence of `{}` went back from {} to {}", heartbeat.product_id, sequence, heartbeat.sequence
+                )));
+            }
+            if heartbeat.time.saturating_sub(time) > MAX_HEARTBEAT_INTERVAL.as_micros() as u64 {
+                return Err(ExchangeClientError::OutOfSync(format!(
+                    "The heartbeats of `{}` between sequence {} and {} are missing", heartbeat.product_id, sequence, heartbeat.sequence
+                )));
+            }
+        }
+
+        self.heartbeat = Some((heartbeat.sequence, heartbeat.time));
+        Ok(())
+    }
+
+    /// Returns up to `depth` of the best bids and asks of the local orderbook.
+    pub fn levels(&self, depth: usize) -> Levels {
+        self.book.levels(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::api::error::ExchangeClientError;
+    use crate::implementation::coinbase::book::CoinbaseBook;
+    use crate::implementation::coinbase::event::{Heartbeat, L2Update};
+
+    fn heartbeat(sequence: u64, seconds: u64) -> Heartbeat {
+        Heartbeat { product_id: "BTC-USD".to_string(), sequence, time: seconds * 1_000_000 }
+    }
+
+    fn update(seconds: u64, bids: Vec<Level>) -> L2Update {
+        L2Update { product_id: "BTC-USD".to_string(), levels: Levels::new(bids, vec![]), time: Some(seconds * 1_000_000) }
+    }
+
+    #[test]
+    fn apply_test() {
+        let mut book = CoinbaseBook::new(&Levels::new(vec![Level::new(dec!(100.00), dec!(1.0))], vec![Level::new(dec!(101.00), dec!(2.0))]));
+
+        book.apply(&update(1, vec![Level::new(dec!(100.50), dec!(3.0)), Level::new(dec!(100.00), dec!(0))])).unwrap();
+
+        assert_eq!(book.levels(10), Levels::new(vec![Level::new(dec!(100.50), dec!(3.0))], vec![Level::new(dec!(101.00), dec!(2.0))]));
+    }
+
+    #[rstest]
+    #[case(vec![heartbeat(10, 1), heartbeat(10, 2), heartbeat(15, 3)], Some(8), true)]
+    // The updates are only checked against the heartbeats once the first heartbeat arrived
+    #[case(vec![], Some(100), true)]
+    #[case(vec![heartbeat(10, 1)], Some(6), true)]
+    #[case(vec![heartbeat(10, 1)], None, true)]
+    // The heartbeats went missing while the updates kept coming
+    #[case(vec![heartbeat(10, 1)], Some(7), false)]
+    // The sequence went back
+    #[case(vec![heartbeat(10, 1), heartbeat(9, 2)], None, false)]
+    // Heartbeats were missed between two heartbeats
+    #[case(vec![heartbeat(10, 1), heartbeat(20, 7)], None, false)]
+    fn heartbeat_test(#[case] heartbeats: Vec<Heartbeat>, #[case] update_seconds: Option<u64>, #[case] in_sync: bool) {
+        let mut book = CoinbaseBook::new(&Levels::new(vec![], vec![]));
+
+        let result = heartbeats.iter()
+            .try_for_each(|heartbeat| book.on_heartbeat(heartbeat))
+            .and_then(|_| match update_seconds {
+                Some(seconds) => book.apply(&update(seconds, vec![])),
+                None => Ok(()),
+            });
+
+        match in_sync {
+            true => assert!(result.is_ok()),
+            false => assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_)))),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! A client implementation for the Coinbase exchange, subscribing to the level2 and heartbeat channels of the websocket feed.
+//! The client is instantiated by [crate::api::provider].
+
+use std::sync::{Mutex, PoisonError};
+
+use futures_util::Sink;
+
+use crate::client_re_exports::*;
+
+use super::book::CoinbaseBook;
+use super::event::Event;
+use super::subscription::{Subscription, LEVEL2_CHANNEL};
+
+pub struct Coinbase {
+    config: ExchangeClientConfig,
+    sender: MailboxSender,
+    exchange: Exchange,
+    /// The local orderbook, replaced by the snapshot of every subscription.
+    book: Mutex<Option<CoinbaseBook>>,
+}
+
+impl ExchangeClient for Coinbase {
+
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+        Coinbase {
+            config,
+            sender,
+            exchange: Exchange::Coinbase,
+            book: Mutex::new(None),
+        }
+    }
+
+    fn build_url(&self) -> Url {
+        self.config.base_url.clone()
+    }
+
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        // The local orderbook is rebuilt from the snapshot of the new subscription
+        *self.book.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        Ok((ws_write_stream, ws_read_stream))
+    }
+
+    fn on_ping(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_pong(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_close(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message)?)
+    }
+}
+
+impl Coinbase {
+    /// Handles an [Event] of the Coinbase websocket feed.
+    ///
+    /// Applies the snapshot and the updates of the level2 channel to the local orderbook and publishes its top levels.
+    /// Returns [ExchangeClientError::OutOfSync] if an update arrives before the snapshot, or if the heartbeats reveal
+    /// that the feed was reset or interrupted, see [CoinbaseBook], so that the client reconnects and resubscribes to a new snapshot.
+    /// Returns [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::Snapshot { product_id, levels } if self.is_instrument(&product_id) => {
+                let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+                let local_book = book.insert(CoinbaseBook::new(&levels));
+                self.publish(local_book, None)
+            }
+            Event::L2Update(update) if self.is_instrument(&update.product_id) => {
+                let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+                let local_book = book.as_mut()
+                    .ok_or_else(|| ExchangeClientError::OutOfSync("An update was received before the snapshot".to_string()))?;
+                if let Err(error) = local_book.apply(&update) {
+                    *book = None;
+                    return Err(error);
+                }
+                self.publish(local_book, update.time)
+            }
+            Event::Heartbeat(heartbeat) if self.is_instrument(&heartbeat.product_id) => {
+                let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+                // The heartbeats that arrive before the snapshot are not needed to check the updates
+                let result = book.as_mut().map_or(Ok(()), |local_book| local_book.on_heartbeat(&heartbeat));
+                if result.is_err() {
+                    *book = None;
+                }
+                result
+            }
+            Event::Snapshot { product_id, .. } => Self::ignore(&product_id),
+            Event::L2Update(update) => Self::ignore(&update.product_id),
+            Event::Heartbeat(heartbeat) => Self::ignore(&heartbeat.product_id),
+            Event::Subscriptions(channels) => {
+                println!("Subscribed successfully to `{}` : `{:?}`", self.exchange, channels);
+                Ok(())
+            }
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::Unknown(message_type) => {
+                println!("Ignoring `{}` message `{}`", self.exchange, message_type);
+                Ok(())
+            }
+        }
+    }
+
+    fn ignore(product_id: &str) -> Result<(), ExchangeClientError> {
+        println!("Ignoring `{}` message of `{}`", Exchange::Coinbase, product_id);
+        Ok(())
+    }
+
+    /// Publishes the top levels of the local orderbook after the snapshot or an update.
+    /// The level2 channel does not publish a sequence, so the snapshot only carries the exchange timestamp of the update.
+    fn publish(&self, book: &CoinbaseBook, exchange_timestamp: Option<u64>) -> Result<(), ExchangeClientError> {
+        let snapshot = OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), book.levels(self.config.depth))
+            .with_exchange_timestamp(exchange_timestamp)
+            .with_received_timestamp(timestamp::now_micros());
+        self.on_deserialized(&self.sender, snapshot)
+    }
+
+    /// Returns true if the given Coinbase product id, e.g. `BTC-USD`, is the instrument of the client.
+    fn is_instrument(&self, product_id: &str) -> bool {
+        product_id == self.config.instrument.exchange_symbol(self.exchange)
+    }
+
+    /// Subscribes to the level2 and heartbeat channels and waits for Coinbase to confirm the subscriptions,
+    /// see [ExchangeClient::await_confirmation].
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        let subscription = Subscription::new(&self.config.instrument.exchange_symbol(self.exchange));
+        self.send_subscription(ws_write_stream, &subscription.serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::Subscriptions(channels) => {
+                let confirmed = channels.iter().any(|(channel, product_ids)| {
+                    channel == LEVEL2_CHANNEL && product_ids.iter().any(|product_id| self.is_instrument(product_id))
+                });
+                self.on_event(Event::Subscriptions(channels)).map(|()| confirmed)
+            }
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => self.on_event(event).map(|()| false),
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{sink, stream, SinkExt};
+    use rust_decimal_macros::dec;
+
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+
+    use super::*;
+
+    /// Hand-built messages of the level2 and heartbeat channels of `BTC-USD`, in the format of the Coinbase feed.
+    const SNAPSHOT: &str = r#"{"type":"snapshot","product_id":"BTC-USD","bids":[["29350.12","0.45054140"],["29350.01","1.20000000"]],"asks":[["29351.77","0.57753524"]]}"#;
+    const HEARTBEAT: &str = r#"{"type":"heartbeat","last_trade_id":541285714,"product_id":"BTC-USD","sequence":62933290110,"time":"2023-09-21T08:12:30.100000Z"}"#;
+    const L2UPDATE: &str = r#"{"type":"l2update","product_id":"BTC-USD","changes":[["buy","29350.12","0.00000000"],["sell","29351.50","0.01000000"]],"time":"2023-09-21T08:12:30.250000Z"}"#;
+    const LATE_L2UPDATE: &str = r#"{"type":"l2update","product_id":"BTC-USD","changes":[["buy","29349.00","2.00000000"]],"time":"2023-09-21T08:12:36.000000Z"}"#;
+
+    fn client(sender: MailboxSender) -> Coinbase {
+        Coinbase::new(ExchangeClientConfig::new("wss://localhost".to_string(), 1, InstrumentRegistry::default().get("btcusd").unwrap().clone()), sender)
+    }
+
+    #[tokio::test]
+    async fn process_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(HEARTBEAT)), Ok(Message::text(L2UPDATE))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(result.is_ok());
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange, Exchange::Coinbase);
+        assert_eq!(snapshot.symbol, "btcusd");
+        assert_eq!(snapshot.sequence, None);
+        assert_eq!(snapshot.exchange_timestamp, Some(1695283950250000));
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(29350.01), dec!(1.20000000))], vec![Level::new(dec!(29351.50), dec!(0.01000000))]));
+    }
+
+    #[tokio::test]
+    async fn process_stream_missing_heartbeats_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+
+        // The update arrives more than 5 seconds after the last heartbeat
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(HEARTBEAT)), Ok(Message::text(LATE_L2UPDATE))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().levels.bids, vec![Level::new(dec!(29350.12), dec!(0.45054140))]);
+        assert!(receiver.try_recv().is_none());
+        // The local orderbook is dropped until the client reconnects and resubscribes
+        assert!(client.book.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn process_stream_sequence_reset_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+
+        let reset_heartbeat = HEARTBEAT.replace("62933290110", "1").replace("30.100000", "31.100000");
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(HEARTBEAT)), Ok(Message::text(reset_heartbeat))];
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(reason)) if reason.contains("went back")));
+    }
+
+    #[tokio::test]
+    async fn process_stream_update_before_snapshot_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+
+        let messages = vec![Ok(Message::text(HEARTBEAT)), Ok(Message::text(L2UPDATE))];
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+}
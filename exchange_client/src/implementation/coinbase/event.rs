@@ -0,0 +1,161 @@
+use serde::de::Unexpected;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use data_models::levels::Levels;
+use data_models::timestamp;
+
+use crate::deserialization::levels;
+use crate::deserialization::serde::OrderbookPriceSizeVisitor;
+
+/// The envelope of every message Coinbase publishes on its websocket feed.
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    message_type: String,
+    #[serde(flatten)]
+    fields: Value,
+}
+
+/// An `l2update` of the level2 channel.
+#[derive(Debug, PartialEq)]
+pub struct L2Update {
+    /// The Coinbase product id of the instrument, e.g. `BTC-USD`.
+    pub product_id: String,
+    /// The levels that changed. A zero size removes the level.
+    pub levels: Levels,
+    /// The time of the update in microseconds since the unix epoch.
+    pub time: Option<u64>,
+}
+
+/// A heartbeat of the heartbeat channel, published every second.
+#[derive(Debug, PartialEq)]
+pub struct Heartbeat {
+    pub product_id: String,
+    /// The latest sequence number of the product.
+    pub sequence: u64,
+    /// The time of the heartbeat in microseconds since the unix epoch.
+    pub time: u64,
+}
+
+/// The events of the Coinbase websocket feed the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The subscriptions of the connection, holding the product ids of each subscribed channel.
+    Subscriptions(Vec<(String, Vec<String>)>),
+    /// The snapshot of the orderbook of a product that follows the subscription.
+    Snapshot { product_id: String, levels: Levels },
+    L2Update(L2Update),
+    Heartbeat(Heartbeat),
+    /// Coinbase rejected a request, holding the error message and its reason.
+    Error(String),
+    /// Any other message, holding its type.
+    Unknown(String),
+}
+
+impl Event {
+    /// Parses a message of the Coinbase websocket feed.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse(message: &str) -> Result<Event, serde_json::Error> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+        let fields = &envelope.fields;
+        let product_id = || -> Result<String, serde_json::Error> {
+            fields["product_id"].as_str().map(str::to_string).ok_or_else(|| serde::de::Error::missing_field("product_id"))
+        };
+
+        Ok(match envelope.message_type.as_str() {
+            "snapshot" => Event::Snapshot {
+                product_id: product_id()?,
+                levels: levels::deserialize_all(
+                    fields,
+                    |value| -> &Value { &value["bids"] },
+                    |value| -> &Value { &value["asks"] },
+                )?,
+            },
+            "l2update" => Event::L2Update(L2Update {
+                product_id: product_id()?,
+                levels: deserialize_changes(&fields["changes"])?,
+                time: fields["time"].as_str().and_then(timestamp::parse_rfc3339_micros),
+            }),
+            "heartbeat" => Event::Heartbeat(Heartbeat {
+                product_id: product_id()?,
+                sequence: fields["sequence"].as_u64().ok_or_else(|| serde::de::Error::missing_field("sequence"))?,
+                time: fields["time"].as_str().and_then(timestamp::parse_rfc3339_micros)
+                    .ok_or_else(|| serde::de::Error::missing_field("time"))?,
+            }),
+            "subscriptions" => Event::Subscriptions(fields["channels"].as_array().into_iter().flatten()
+                .map(|channel| (
+                    channel["name"].as_str().unwrap_or_default().to_string(),
+                    channel["product_ids"].as_array().into_iter().flatten().filter_map(Value::as_str).map(str::to_string).collect(),
+                ))
+                .collect()),
+            "error" => Event::Error(match (fields["message"].as_str(), fields["reason"].as_str()) {
+                (Some(message), Some(reason)) => format!("{} : {}", message, reason),
+                (Some(message), None) => message.to_string(),
+                _ => fields.to_string(),
+            }),
+            _ => Event::Unknown(envelope.message_type),
+        })
+    }
+}
+
+/// Deserializes the `changes` of an `l2update`, e.g. `[["buy", "10101.80000000", "0.162567"]]`, into [Levels].
+fn deserialize_changes(changes: &Value) -> Result<Levels, serde_json::Error> {
+    let changes = changes.as_array().ok_or_else(|| serde::de::Error::missing_field("changes"))?;
+    let mut levels = Levels::new(vec![], vec![]);
+
+    for change in changes {
+        let Some([side, price, size]) = change.as_array().map(Vec::as_slice) else {
+            return Err(serde::de::Error::invalid_value(Unexpected::Other(&change.to_string()), &"a change [\"<side>\", \"<price>\", \"<size>\"]"));
+        };
+        let level = Value::Array(vec![price.clone(), size.clone()]).deserialize_seq(OrderbookPriceSizeVisitor {})?;
+
+        match side.as_str() {
+            Some("buy") => levels.bids.push(level),
+            Some("sell") => levels.asks.push(level),
+            _ => return Err(serde::de::Error::invalid_value(Unexpected::Other(&side.to_string()), &"buy or sell")),
+        }
+    }
+    Ok(levels)
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::implementation::coinbase::event::{Event, Heartbeat, L2Update};
+
+    #[rstest]
+    #[case(r#"{"type":"subscriptions","channels":[{"name":"level2_batch","product_ids":["BTC-USD"]},{"name":"heartbeat","product_ids":["BTC-USD"]}]}"#,
+           Event::Subscriptions(vec![("level2_batch".to_string(), vec!["BTC-USD".to_string()]), ("heartbeat".to_string(), vec!["BTC-USD".to_string()])]))]
+    #[case(r#"{"type":"snapshot","product_id":"BTC-USD","bids":[["10101.10","0.45054140"]],"asks":[["10102.55","0.57753524"]]}"#,
+           Event::Snapshot { product_id: "BTC-USD".to_string(), levels: Levels::new(vec![Level::new(dec!(10101.10), dec!(0.45054140))], vec![Level::new(dec!(10102.55), dec!(0.57753524))]) })]
+    #[case(r#"{"type":"l2update","product_id":"BTC-USD","changes":[["buy","10101.80000000","0.162567"],["sell","10102.55","0.00000000"]],"time":"2019-08-14T20:42:27.265Z"}"#,
+           Event::L2Update(L2Update {
+               product_id: "BTC-USD".to_string(),
+               levels: Levels::new(vec![Level::new(dec!(10101.80000000), dec!(0.162567))], vec![Level::new(dec!(10102.55), dec!(0.00000000))]),
+               time: Some(1565815347265000),
+           }))]
+    #[case(r#"{"type":"heartbeat","sequence":90,"last_trade_id":20,"product_id":"BTC-USD","time":"2014-11-07T08:19:28.464459Z"}"#,
+           Event::Heartbeat(Heartbeat { product_id: "BTC-USD".to_string(), sequence: 90, time: 1415348368464459 }))]
+    #[case(r#"{"type":"error","message":"Failed to subscribe","reason":"BTC-XYZ is not a valid product"}"#,
+           Event::Error("Failed to subscribe : BTC-XYZ is not a valid product".to_string()))]
+    #[case(r#"{"type":"ticker","product_id":"BTC-USD"}"#, Event::Unknown("ticker".to_string()))]
+    fn parse_test(#[case] message: &str, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(message).unwrap(), expected_event);
+    }
+
+    #[test]
+    fn parse_fails_test() {
+        assert!(Event::parse(r#"{"product_id":"BTC-USD"}"#).is_err());
+        assert!(Event::parse(r#"{"type":"l2update","product_id":"BTC-USD","changes":[["hold","1.0","1.0"]]}"#).is_err());
+        assert!(Event::parse(r#"{"type":"l2update","product_id":"BTC-USD","changes":[["buy","1.0"]]}"#).is_err());
+        assert!(Event::parse(r#"{"type":"heartbeat","product_id":"BTC-USD","time":"2014-11-07T08:19:28.464459Z"}"#).is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use serde::Serialize;
+
+/// The channel of the orderbook updates. It publishes the same `snapshot` and `l2update` messages as the
+/// `level2` channel, batched every 50 milliseconds, and unlike `level2` it does not require authentication.
+pub const LEVEL2_CHANNEL: &str = "level2_batch";
+/// The channel of the heartbeats, which carry the latest sequence number of the product.
+pub const HEARTBEAT_CHANNEL: &str = "heartbeat";
+
+#[derive(Serialize)]
+pub struct Subscription {
+    #[serde(rename = "type")]
+    message_type: String,
+    product_ids: Vec<String>,
+    channels: Vec<String>,
+}
+
+impl Subscription {
+    pub fn new(product_id: &str) -> Self {
+        Subscription {
+            message_type: "subscribe".into(),
+            product_ids: vec![product_id.to_string()],
+            channels: vec![LEVEL2_CHANNEL.to_string(), HEARTBEAT_CHANNEL.to_string()],
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,196 @@
+//! A client implementation for the Huobi (HTX) exchange, subscribing to the `market.$symbol.depth.step0` channel.
+//! Huobi compresses every message with gzip and expects the client to answer its pings at the application level.
+//! The client is instantiated by [crate::api::provider].
+
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use futures_util::Sink;
+
+use crate::client_re_exports::*;
+
+use super::event::{Depth, Event};
+use super::subscription::{Pong, Subscription};
+
+pub struct Huobi {
+    config: ExchangeClientConfig,
+    sender: MailboxSender,
+    exchange: Exchange,
+}
+
+impl ExchangeClient for Huobi {
+
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+        Huobi {
+            config,
+            sender,
+            exchange: Exchange::Huobi,
+        }
+    }
+
+    fn build_url(&self) -> Url {
+        self.config.base_url.clone()
+    }
+
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        Ok((ws_write_stream, ws_read_stream))
+    }
+
+    fn on_ping(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_pong(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_close(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message, self.config.depth)?)
+    }
+
+    /// Decompresses a gzip compressed message of Huobi.
+    fn decode_binary(&self, data: &[u8]) -> Result<String, ExchangeClientError> {
+        let mut message = String::new();
+        GzDecoder::new(data).read_to_string(&mut message).map_err(ExchangeClientError::decode)?;
+        Ok(message)
+    }
+
+    /// Answers the `{"ping": <ts>}` of Huobi with `{"pong": <ts>}`.
+    fn reply(&self, message: &str) -> Option<Message> {
+        Event::parse_ping(message).map(|ping| Message::text(Pong::new(ping).serialize()))
+    }
+}
+
+impl Huobi {
+    /// Handles an [Event] of the Huobi market websocket.
+    ///
+    /// Every depth message holds the top levels of the orderbook, which are published as they are.
+    /// Returns [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::Depth(depth) if depth.channel == self.topic() => self.publish(depth),
+            Event::Depth(depth) => {
+                println!("Ignoring `{}` message of `{}`", self.exchange, depth.channel);
+                Ok(())
+            }
+            // The pings are answered by [ExchangeClient::reply] before they are deserialized
+            Event::Ping(_) => Ok(()),
+            Event::Subscribed(topic) => {
+                println!("Subscribed successfully to `{}` : `{}`", self.exchange, topic);
+                Ok(())
+            }
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::Unknown(channel) => {
+                println!("Ignoring `{}` message `{}`", self.exchange, channel);
+                Ok(())
+            }
+        }
+    }
+
+    fn publish(&self, depth: Depth) -> Result<(), ExchangeClientError> {
+        let snapshot = OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), depth.levels)
+            .with_sequence(Some(depth.version))
+            .with_exchange_timestamp(Some(depth.timestamp))
+            .with_received_timestamp(timestamp::now_micros());
+        self.on_deserialized(&self.sender, snapshot)
+    }
+
+    /// Returns the topic of the orderbook of the instrument, e.g. `market.ethbtc.depth.step0`.
+    /// The `step0` depth publishes the top 150 levels per side, without aggregating the prices.
+    fn topic(&self) -> String {
+        format!("market.{}.depth.step0", self.config.instrument.exchange_symbol(self.exchange))
+    }
+
+    /// Subscribes to the depth channel and waits for Huobi to confirm the subscription,
+    /// see [ExchangeClient::await_confirmation]. Messages that arrive before the confirmation are
+    /// decompressed and handled, and pings are answered.
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        self.send_subscription(ws_write_stream, &Subscription::new(&self.topic()).serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message, self.config.depth)? {
+            Event::Subscribed(topic) if topic == self.topic() => self.on_event(Event::Subscribed(topic)).map(|()| true),
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => self.on_event(event).map(|()| false),
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use futures_util::{sink, stream, SinkExt};
+    use rust_decimal_macros::dec;
+
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+
+    use super::*;
+
+    /// Hand-built messages of the depth channel of `ethusdt` in the format of the Huobi feed, before they are compressed.
+    const PING: &str = r#"{"ping":1630983549450}"#;
+    const DEPTH: &str = r#"{"ch":"market.ethusdt.depth.step0","ts":1630983549503,"tick":{"bids":[[3421.57,0.8120],[3421.12,4.5]],"asks":[[3421.89,2.0375],[3422.40,0.15]],"version":100434317651,"ts":1630983549500}}"#;
+
+    fn gzip(message: &str) -> Message {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(message.as_bytes()).unwrap();
+        Message::binary(encoder.finish().unwrap())
+    }
+
+    fn client(sender: MailboxSender) -> Huobi {
+        Huobi::new(ExchangeClientConfig::new("wss://localhost/ws".to_string(), 1, InstrumentRegistry::default().get("ethusdt").unwrap().clone()), sender)
+    }
+
+    #[tokio::test]
+    async fn process_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let mut sent = Vec::<Message>::new();
+
+        // A frame that is not gzip compressed is skipped
+        let messages = vec![Ok(gzip(PING)), Ok(Message::binary(vec![1, 2, 3])), Ok(gzip(DEPTH))];
+        let result = client(sender).process_stream((&mut sent).sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(sent, vec![Message::text(r#"{"pong":1630983549450}"#)]);
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange, Exchange::Huobi);
+        assert_eq!(snapshot.symbol, "ethusdt");
+        assert_eq!(snapshot.sequence, Some(100434317651));
+        assert_eq!(snapshot.exchange_timestamp, Some(1630983549500000));
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(3421.57), dec!(0.8120))], vec![Level::new(dec!(3421.89), dec!(2.0375))]));
+    }
+
+    #[tokio::test]
+    async fn process_stream_error_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+
+        let error = r#"{"status":"error","err-code":"invalid-parameter","err-msg":"invalid pong","ts":1630983549401}"#;
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(vec![Ok(gzip(error))])).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::Protocol(reason)) if reason.contains("invalid pong")));
+    }
+}
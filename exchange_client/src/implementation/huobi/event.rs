@@ -0,0 +1,152 @@
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use data_models::levels::{Level, Levels};
+
+use crate::deserialization::serde::decimal;
+
+/// The envelope of every message Huobi publishes on its market websocket, once decompressed.
+/// Channel messages carry a `ch`, the responses to requests carry a `status`.
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    #[serde(default)]
+    ping: Option<u64>,
+    #[serde(default)]
+    ch: Option<String>,
+    #[serde(default, borrow)]
+    tick: Option<&'a RawValue>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    subbed: Option<String>,
+    #[serde(default, rename = "err-msg")]
+    error: Option<String>,
+}
+
+/// The `tick` of a depth message. The prices and amounts are JSON numbers, which are kept raw
+/// until the levels within the depth of the client are parsed.
+#[derive(Deserialize)]
+struct RawTick<'a> {
+    #[serde(borrow)]
+    bids: Vec<(&'a RawValue, &'a RawValue)>,
+    #[serde(borrow)]
+    asks: Vec<(&'a RawValue, &'a RawValue)>,
+    version: u64,
+    ts: u64,
+}
+
+/// A message of the `market.$symbol.depth.step0` channel, which publishes the top 150 levels per side.
+#[derive(Debug, PartialEq)]
+pub struct Depth {
+    /// The channel of the message, e.g. `market.ethbtc.depth.step0`.
+    pub channel: String,
+    pub levels: Levels,
+    /// The version of the orderbook, which increases with every update.
+    pub version: u64,
+    /// The time of the orderbook in microseconds since the unix epoch.
+    pub timestamp: u64,
+}
+
+/// The events of the Huobi market websocket the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The ping Huobi sends every 5 seconds, holding its timestamp. The connection is closed
+    /// if two consecutive pings are not answered.
+    Ping(u64),
+    /// The subscription to the given topic was confirmed.
+    Subscribed(String),
+    /// Huobi rejected a request, holding the error message.
+    Error(String),
+    Depth(Depth),
+    /// Any other message, holding its channel.
+    Unknown(String),
+}
+
+impl Event {
+    /// Parses a decompressed message of the Huobi market websocket.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    /// * `depth` - The number of levels per side to parse of a depth message.
+    pub fn parse(message: &str, depth: usize) -> Result<Event, serde_json::Error> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+
+        if let Some(ping) = envelope.ping {
+            return Ok(Event::Ping(ping));
+        }
+
+        match (envelope.status.as_deref(), envelope.subbed, envelope.ch, envelope.tick) {
+            (Some("ok"), Some(topic), _, _) => Ok(Event::Subscribed(topic)),
+            (Some("error"), _, _, _) => Ok(Event::Error(envelope.error.unwrap_or_else(|| message.to_string()))),
+            (_, _, Some(channel), Some(tick)) if channel.ends_with(".depth.step0") => {
+                let tick: RawTick = serde_json::from_str(tick.get())?;
+                let levels = |levels: &[(&RawValue, &RawValue)]| -> Result<Vec<Level>, serde_json::Error> {
+                    levels.iter().take(depth).map(|(price, amount)| Ok(Level::new(decimal(price)?, decimal(amount)?))).collect()
+                };
+
+                Ok(Event::Depth(Depth {
+                    levels: Levels::new(levels(&tick.bids)?, levels(&tick.asks)?),
+                    channel,
+                    version: tick.version,
+                    timestamp: tick.ts * 1000,
+                }))
+            }
+            (_, _, Some(channel), _) => Ok(Event::Unknown(channel)),
+            _ => Err(serde::de::Error::missing_field("ch")),
+        }
+    }
+
+    /// Returns the timestamp of a ping, e.g. `{"ping":1492420473027}`, without parsing any other message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse_ping(message: &str) -> Option<u64> {
+        message.strip_prefix(r#"{"ping":"#)?.strip_suffix('}')?.trim().parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::implementation::huobi::event::{Depth, Event};
+
+    #[rstest]
+    #[case(r#"{"ping":1492420473027}"#, Event::Ping(1492420473027))]
+    #[case(r#"{"id":"market.ethbtc.depth.step0","status":"ok","subbed":"market.ethbtc.depth.step0","ts":1489474081631}"#,
+           Event::Subscribed("market.ethbtc.depth.step0".to_string()))]
+    #[case(r#"{"id":"market.ethxyz.depth.step0","status":"error","err-code":"bad-request","err-msg":"invalid symbol ethxyz","ts":1489474081631}"#,
+           Event::Error("invalid symbol ethxyz".to_string()))]
+    #[case(r#"{"ch":"market.ethbtc.depth.step0","ts":1630983549503,"tick":{"bids":[[0.05310,1.2500],[0.05309,0.004]],"asks":[[0.05312,10.5],[0.05313,0.5]],"version":100434317651,"ts":1630983549500}}"#,
+           Event::Depth(Depth {
+               channel: "market.ethbtc.depth.step0".to_string(),
+               levels: Levels::new(vec![Level::new(dec!(0.05310), dec!(1.2500))], vec![Level::new(dec!(0.05312), dec!(10.5))]),
+               version: 100434317651,
+               timestamp: 1630983549500000,
+           }))]
+    #[case(r#"{"ch":"market.ethbtc.trade.detail","ts":1630983549503,"tick":{"data":[]}}"#, Event::Unknown("market.ethbtc.trade.detail".to_string()))]
+    fn parse_test(#[case] message: &str, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(message, 1).unwrap(), expected_event);
+    }
+
+    #[test]
+    fn parse_fails_test() {
+        assert!(Event::parse(r#"{"ts":1630983549503}"#, 1).is_err());
+        assert!(Event::parse(r#"{"ch":"market.ethbtc.depth.step0","tick":{"bids":[["a",1]],"asks":[],"version":1,"ts":1}}"#, 1).is_err());
+        assert!(Event::parse(r#"{"ch":"market.ethbtc.depth.step0","tick":{"bids":[],"asks":[]}}"#, 1).is_err());
+    }
+
+    #[rstest]
+    #[case(r#"{"ping":1492420473027}"#, Some(1492420473027))]
+    #[case(r#"{"ping": 1492420473027 }"#, Some(1492420473027))]
+    #[case(r#"{"ch":"market.ethbtc.depth.step0"}"#, None)]
+    #[case(r#"{"ping":"a"}"#, None)]
+    fn parse_ping_test(#[case] message: &str, #[case] expected: Option<u64>) {
+        assert_eq!(Event::parse_ping(message), expected);
+    }
+}
@@ -0,0 +1,3 @@
+pub mod client;
+mod event;
+mod subscription;
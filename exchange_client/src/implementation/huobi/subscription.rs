@@ -0,0 +1,37 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct Subscription {
+    sub: String,
+    id: String,
+}
+
+impl Subscription {
+    /// Subscribes to the given topic, e.g. `market.ethbtc.depth.step0`. The id is echoed by the confirmation.
+    pub fn new(topic: &str) -> Self {
+        Subscription {
+            sub: topic.to_string(),
+            id: topic.to_string(),
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// The reply to a ping of Huobi, echoing its timestamp.
+#[derive(Serialize)]
+pub struct Pong {
+    pong: u64,
+}
+
+impl Pong {
+    pub fn new(ping: u64) -> Self {
+        Pong { pong: ping }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,173 @@
+//! Maintains a local orderbook from the snapshot and the updates of the Kraken book channel, and verifies it
+//! against the checksum of every message, as documented in <https://docs.kraken.com/api/docs/guides/spot-ws-book-v2>.
+
+use data_models::levels::{Level, Levels};
+use data_models::precision::Precision;
+
+use crate::api::error::ExchangeClientError;
+use crate::local_book::LocalBook;
+
+use super::event::BookUpdate;
+
+/// The number of levels per side the checksum is computed over.
+const CHECKSUM_DEPTH: usize = 10;
+
+/// The local orderbook of the book channel.
+pub struct KrakenBook {
+    book: LocalBook,
+    /// The depth of the subscription. Kraken does not publish the removal of the levels
+    /// that fall out of the subscribed depth, they have to be truncated.
+    depth: usize,
+    /// The decimal places the levels are formatted with in the checksum.
+    precision: Precision,
+}
+
+impl KrakenBook {
+    /// Constructs a new [KrakenBook] from the snapshot of a subscription.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if the snapshot does not match its checksum.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The depth of the subscription.
+    /// * `precision` - The [Precision] of the instrument on Kraken.
+    /// * `snapshot` - The [BookUpdate] of the snapshot.
+    pub fn new(depth: usize, precision: Precision, snapshot: &BookUpdate) -> Result<Self, ExchangeClientError> {
+        let mut book = KrakenBook {
+            book: LocalBook::default(),
+            depth,
+            precision,
+        };
+        book.book.replace(&snapshot.levels);
+        book.verify(snapshot)?;
+        Ok(book)
+    }
+
+    /// Applies an update to the local orderbook.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if the orderbook does not match the checksum of the update,
+    /// e.g. because an update was missed.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The [BookUpdate] of the book channel.
+    pub fn apply(&mut self, update: &BookUpdate) -> Result<(), ExchangeClientError> {
+        self.book.update(&update.levels);
+        self.verify(update)
+    }
+
+    /// Returns up to `depth` of the best bids and asks of the local orderbook.
+    pub fn levels(&self, depth: usize) -> Levels {
+        self.book.levels(depth)
+    }
+
+    fn verify(&mut self, update: &BookUpdate) -> Result<(), ExchangeClientError> {
+        self.book.truncate(self.depth);
+
+        let checksum = checksum(&self.book.levels(CHECKSUM_DEPTH), self.precision);
+        if checksum != update.checksum {
+            return Err(ExchangeClientError::OutOfSync(format!(
+                "The checksum {} of the local orderbook does not match the checksum {} of `{}`",
+                checksum, update.checksum, update.symbol
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Computes the CRC32 checksum of the top levels of an orderbook: the asks from the lowest price and then the bids
+/// from the highest price, each formatted as its price followed by its quantity without decimal points and leading zeros.
+///
+/// Kraken publishes the numbers without their trailing zeros, e.g. `10.0`, but checksums them with the
+/// `price_precision` and `qty_precision` of the pair, e.g. `10.00000000`.
+///
+/// # Arguments
+///
+/// * `levels` - The top 10 levels per side of the orderbook.
+/// * `precision` - The [Precision] of the instrument on Kraken.
+pub fn checksum(levels: &Levels, precision: Precision) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    for level in levels.asks.iter().chain(levels.bids.iter()) {
+        hasher.update(format_level(level, precision).as_bytes());
+    }
+    hasher.finalize()
+}
+
+fn format_level(level: &Level, precision: Precision) -> String {
+    let format = |value: String| -> String {
+        let digits = value.replace('.', "");
+        match digits.trim_start_matches('0') {
+            "" => "0".to_string(),
+            digits => digits.to_string(),
+        }
+    };
+    format!("{}{}", format(Precision::format(&level.price, precision.price)),
+            format(Precision::format(&level.amount, precision.amount)))
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+    use data_models::precision::Precision;
+
+    use crate::api::error::ExchangeClientError;
+    use crate::implementation::kraken::book::{checksum, KrakenBook};
+    use crate::implementation::kraken::event::BookUpdate;
+
+    /// The Kraken precision of `ETH/BTC`.
+    const PRECISION: Precision = Precision { price: 5, amount: 8 };
+
+    fn update(bids: Vec<Level>, asks: Vec<Level>, checksum: u32) -> BookUpdate {
+        BookUpdate {
+            symbol: "ETH/BTC".to_string(),
+            levels: Levels::new(bids, asks),
+            checksum,
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn checksum_test() {
+        let levels = Levels::new(
+            vec![Level::new(dec!(0.0531), dec!(1.25)), Level::new(dec!(0.05309), dec!(0.004))],
+            vec![Level::new(dec!(0.05312), dec!(10.0))],
+        );
+
+        // The levels are padded to the precision of the pair, e.g. `10.0` is checksummed as `10.00000000`
+        // zlib.crc32(b"53121000000000" + b"5310125000000" + b"5309400000")
+        assert_eq!(checksum(&levels, PRECISION), 1018467415);
+        assert_eq!(checksum(&Levels::new(vec![], vec![]), PRECISION), 0);
+    }
+
+    #[test]
+    fn apply_test() {
+        let snapshot = update(
+            vec![Level::new(dec!(0.0531), dec!(1.25)), Level::new(dec!(0.05309), dec!(0.004))],
+            vec![Level::new(dec!(0.05312), dec!(10.0))],
+            1018467415,
+        );
+        let mut book = KrakenBook::new(2, PRECISION, &snapshot).unwrap();
+        assert_eq!(book.levels(10), snapshot.levels);
+
+        // The new best bid pushes the worst bid out of the subscribed depth
+        let levels = Levels::new(
+            vec![Level::new(dec!(0.05311), dec!(2.0)), Level::new(dec!(0.0531), dec!(1.25))],
+            vec![Level::new(dec!(0.05312), dec!(10.0))],
+        );
+        book.apply(&update(vec![Level::new(dec!(0.05311), dec!(2.0))], vec![], checksum(&levels, PRECISION))).unwrap();
+        assert_eq!(book.levels(10), levels);
+
+        // The checksum still includes the removed ask, as if an update had been missed
+        let result = book.apply(&update(vec![], vec![Level::new(dec!(0.05312), dec!(0))], checksum(&levels, PRECISION)));
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+
+    #[test]
+    fn new_fails_test() {
+        let snapshot = update(vec![Level::new(dec!(0.0531), dec!(1.25))], vec![], 1);
+
+        assert!(matches!(KrakenBook::new(10, PRECISION, &snapshot), Err(ExchangeClientError::OutOfSync(_))));
+    }
+}
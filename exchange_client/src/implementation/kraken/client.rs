@@ -0,0 +1,233 @@
+//! A client implementation for the Kraken exchange, subscribing to the book channel of the WebSocket v2 api.
+//! The client is instantiated by [crate::api::provider].
+
+use std::sync::{Mutex, PoisonError};
+
+use futures_util::Sink;
+
+use data_models::symbol_format::SymbolFormat;
+
+use crate::client_re_exports::*;
+
+use super::book::KrakenBook;
+use super::event::{BookUpdate, Event};
+use super::subscription::Subscription;
+
+pub struct Kraken {
+    config: ExchangeClientConfig,
+    sender: MailboxSender,
+    exchange: Exchange,
+    /// The local orderbook, replaced by the snapshot of every subscription.
+    book: Mutex<Option<KrakenBook>>,
+}
+
+impl ExchangeClient for Kraken {
+
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+        Kraken {
+            config,
+            sender,
+            exchange: Exchange::Kraken,
+            book: Mutex::new(None),
+        }
+    }
+
+    fn build_url(&self) -> Url {
+        self.config.base_url.clone()
+    }
+
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        // The local orderbook is rebuilt from the snapshot of the new subscription
+        *self.book.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        Ok((ws_write_stream, ws_read_stream))
+    }
+
+    fn on_ping(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_pong(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_close(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message)?)
+    }
+}
+
+impl Kraken {
+    /// Handles an [Event] of the Kraken websocket.
+    ///
+    /// Applies the snapshots and updates of the book channel to the local orderbook and publishes its top levels.
+    /// Returns [ExchangeClientError::OutOfSync] if the local orderbook does not match the checksum of a message,
+    /// so that the client reconnects and resubscribes to a new snapshot.
+    /// Returns [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::Snapshot(snapshots) => {
+                for snapshot in snapshots.iter().filter(|snapshot| self.is_instrument(&snapshot.symbol)) {
+                    let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+                    let precision = self.config.instrument.precision(self.exchange);
+                    *book = Some(KrakenBook::new(self.subscription().depth(), precision, snapshot)?);
+                    self.publish(book.as_ref().unwrap(), snapshot)?;
+                }
+                Ok(())
+            }
+            Event::Update(updates) => {
+                for update in updates.iter().filter(|update| self.is_instrument(&update.symbol)) {
+                    let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+                    let local_book = book.as_mut()
+                        .ok_or_else(|| ExchangeClientError::OutOfSync("An update was received before the snapshot".to_string()))?;
+                    if let Err(error) = local_book.apply(update) {
+                        *book = None;
+                        return Err(error);
+                    }
+                    self.publish(local_book, update)?;
+                }
+                Ok(())
+            }
+            Event::Subscribed { channel, symbol } => {
+                println!("Subscribed successfully to `{}` : `{}` `{}`", self.exchange, channel, symbol);
+                Ok(())
+            }
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::Heartbeat => Ok(()),
+            Event::Unknown(message) => {
+                println!("Ignoring `{}` message `{}`", self.exchange, message);
+                Ok(())
+            }
+        }
+    }
+
+    /// Publishes the top levels of the local orderbook after a snapshot or an update.
+    /// Kraken does not publish an update id, so the snapshot only carries the exchange timestamp.
+    fn publish(&self, book: &KrakenBook, update: &BookUpdate) -> Result<(), ExchangeClientError> {
+        let snapshot = OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), book.levels(self.config.depth))
+            .with_exchange_timestamp(update.timestamp)
+            .with_received_timestamp(timestamp::now_micros());
+        self.on_deserialized(&self.sender, snapshot)
+    }
+
+    fn subscription(&self) -> Subscription {
+        Subscription::new(&self.config.instrument.exchange_symbol(self.exchange), self.config.depth)
+    }
+
+    /// Returns true if the given Kraken symbol is the symbol of the instrument of the client.
+    /// Both the v2 symbols, e.g. `ETH/BTC`, and the legacy symbols, e.g. `ETH/XBT`, are accepted.
+    fn is_instrument(&self, symbol: &str) -> bool {
+        symbol == self.config.instrument.exchange_symbol(self.exchange)
+            || symbol == self.config.instrument.native_symbol(&SymbolFormat::KRAKEN)
+    }
+
+    /// Subscribes to the book channel and waits for Kraken to confirm the subscription,
+    /// see [ExchangeClient::await_confirmation]. Messages that arrive before the confirmation,
+    /// e.g. the system status, are handled.
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        self.send_subscription(ws_write_stream, &self.subscription().serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::Subscribed { channel, symbol } if channel == "book" && self.is_instrument(&symbol) => {
+                self.on_event(Event::Subscribed { channel, symbol }).map(|()| true)
+            }
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => self.on_event(event).map(|()| false),
+        }).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{sink, stream, SinkExt};
+    use rust_decimal_macros::dec;
+
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+
+    use super::*;
+
+    /// Messages of the book channel of `ETH/BTC`, with the numbers written without trailing zeros like the live feed.
+    /// The checksums are the CRC32 of the levels formatted with the Kraken precision of the pair (5 and 8 decimal places):
+    /// zlib.crc32(b"53121000000000" + b"531350000000" + b"5310125000000" + b"5309400000") for the snapshot and
+    /// zlib.crc32(b"53121000000000" + b"531350000000" + b"5309400000") after the update.
+    const SNAPSHOT: &str = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"ETH/BTC","bids":[{"price":0.0531,"qty":1.25},{"price":0.05309,"qty":0.004}],"asks":[{"price":0.05312,"qty":10.0},{"price":0.05313,"qty":0.5}],"checksum":1537734987,"timestamp":"2023-10-06T17:35:55.440295Z"}]}"#;
+    const UPDATE: &str = r#"{"channel":"book","type":"update","data":[{"symbol":"ETH/BTC","bids":[{"price":0.0531,"qty":0.0}],"asks":[],"checksum":2042343340,"timestamp":"2023-10-06T17:35:56.012345Z"}]}"#;
+    const HEARTBEAT: &str = r#"{"channel":"heartbeat"}"#;
+
+    fn client(sender: MailboxSender) -> Kraken {
+        Kraken::new(ExchangeClientConfig::new("wss://localhost/v2".to_string(), 1, InstrumentRegistry::default().get("ethbtc").unwrap().clone()), sender)
+    }
+
+    #[tokio::test]
+    async fn process_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(HEARTBEAT))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange, Exchange::Kraken);
+        assert_eq!(snapshot.symbol, "ethbtc");
+        assert_eq!(snapshot.sequence, None);
+        assert_eq!(snapshot.exchange_timestamp, Some(1696613755440295));
+        assert_eq!(snapshot.levels, Levels::new(vec![Level::new(dec!(0.0531), dec!(1.25))], vec![Level::new(dec!(0.05312), dec!(10))]));
+
+        // The legacy symbol of the instrument is accepted as well
+        let messages = vec![Ok(Message::text(UPDATE.replace("ETH/BTC", "ETH/XBT")))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+        assert!(result.is_ok());
+
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange_timestamp, Some(1696613756012345));
+        assert_eq!(snapshot.levels.bids, vec![Level::new(dec!(0.05309), dec!(0.004))]);
+    }
+
+    #[tokio::test]
+    async fn process_stream_checksum_mismatch_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender);
+
+        // The checksum of the update does not match, e.g. because an update was missed
+        let messages = vec![Ok(Message::text(SNAPSHOT)), Ok(Message::text(UPDATE)), Ok(Message::text(UPDATE.replace("2042343340", "1")))];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+        assert_eq!(receiver.recv_snapshot().await.unwrap().levels.bids, vec![Level::new(dec!(0.05309), dec!(0.004))]);
+        assert!(receiver.try_recv().is_none());
+        // The local orderbook is dropped until the client reconnects and resubscribes
+        assert!(client.book.lock().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn process_stream_update_before_snapshot_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+
+        let messages = vec![Ok(Message::text(UPDATE))];
+        let result = client(sender).process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+}
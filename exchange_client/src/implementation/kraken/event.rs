@@ -0,0 +1,182 @@
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use data_models::levels::{Level, Levels};
+use data_models::timestamp;
+
+use crate::deserialization::serde::decimal;
+
+/// The envelope of every message Kraken publishes on its WebSocket v2 api.
+/// Channel messages carry a `channel`, the responses to requests carry a `method`.
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default, rename = "type")]
+    message_type: Option<String>,
+    #[serde(default, borrow)]
+    data: Option<&'a RawValue>,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    success: Option<bool>,
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// An entry of the `data` of a book message. The prices and quantities are kept as raw JSON numbers,
+/// so that they are parsed as exact decimals rather than as floats.
+#[derive(Deserialize)]
+struct RawBook<'a> {
+    symbol: String,
+    #[serde(borrow)]
+    bids: Vec<RawLevel<'a>>,
+    #[serde(borrow)]
+    asks: Vec<RawLevel<'a>>,
+    checksum: u32,
+    #[serde(default)]
+    timestamp: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RawLevel<'a> {
+    #[serde(borrow)]
+    price: &'a RawValue,
+    #[serde(borrow)]
+    qty: &'a RawValue,
+}
+
+/// A snapshot or an update of the book channel.
+#[derive(Debug, PartialEq)]
+pub struct BookUpdate {
+    /// The Kraken symbol of the instrument, e.g. `ETH/BTC`.
+    pub symbol: String,
+    /// The levels of the snapshot, or the levels that changed. A zero quantity removes the level.
+    pub levels: Levels,
+    /// The CRC32 checksum of the top 10 levels per side of the book after the update.
+    pub checksum: u32,
+    /// The time of the update in microseconds since the unix epoch.
+    pub timestamp: Option<u64>,
+}
+
+/// The events of the Kraken WebSocket the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The subscription to the given channel and symbol was confirmed.
+    Subscribed { channel: String, symbol: String },
+    /// Kraken rejected a request, holding the error message.
+    Error(String),
+    /// The snapshot of the book that follows a subscription.
+    Snapshot(Vec<BookUpdate>),
+    /// Incremental updates of the book.
+    Update(Vec<BookUpdate>),
+    /// The heartbeat Kraken sends every second in the absence of other updates.
+    Heartbeat,
+    /// Any other message, holding its channel or method.
+    Unknown(String),
+}
+
+impl Event {
+    /// Parses a message of the Kraken WebSocket v2 api.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse(message: &str) -> Result<Event, serde_json::Error> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+
+        if let Some(method) = envelope.method {
+            return Ok(match (envelope.success, envelope.error) {
+                (Some(false), error) => Event::Error(error.unwrap_or_else(|| format!("`{}` failed", method))),
+                _ if method == "subscribe" => Event::Subscribed {
+                    channel: envelope.result["channel"].as_str().unwrap_or_default().to_string(),
+                    symbol: envelope.result["symbol"].as_str().unwrap_or_default().to_string(),
+                },
+                _ => Event::Unknown(method),
+            });
+        }
+
+        let channel = envelope.channel.ok_or_else(|| serde::de::Error::missing_field("channel"))?;
+        Ok(match (channel.as_str(), envelope.message_type.as_deref()) {
+            ("book", Some(message_type @ ("snapshot" | "update"))) => {
+                let data = envelope.data.ok_or_else(|| serde::de::Error::missing_field("data"))?;
+                let books: Vec<RawBook> = serde_json::from_str(data.get())?;
+                let updates = books.into_iter().map(BookUpdate::try_from).collect::<Result<_, _>>()?;
+                match message_type {
+                    "snapshot" => Event::Snapshot(updates),
+                    _ => Event::Update(updates),
+                }
+            }
+            ("heartbeat", _) => Event::Heartbeat,
+            _ => Event::Unknown(channel),
+        })
+    }
+}
+
+impl TryFrom<RawBook<'_>> for BookUpdate {
+    type Error = serde_json::Error;
+
+    fn try_from(book: RawBook) -> Result<Self, Self::Error> {
+        let levels = |levels: Vec<RawLevel>| -> Result<Vec<Level>, serde_json::Error> {
+            levels.iter().map(|level| Ok(Level::new(decimal(level.price)?, decimal(level.qty)?))).collect()
+        };
+
+        Ok(BookUpdate {
+            symbol: book.symbol,
+            levels: Levels::new(levels(book.bids)?, levels(book.asks)?),
+            checksum: book.checksum,
+            timestamp: book.timestamp.as_deref().and_then(timestamp::parse_rfc3339_micros),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::implementation::kraken::event::{BookUpdate, Event};
+
+    #[rstest]
+    #[case(r#"{"method":"subscribe","result":{"channel":"book","depth":10,"snapshot":true,"symbol":"ETH/BTC"},"success":true,"time_in":"2023-10-06T17:35:55.408372Z","time_out":"2023-10-06T17:35:55.408426Z"}"#,
+           Event::Subscribed { channel: "book".to_string(), symbol: "ETH/BTC".to_string() })]
+    #[case(r#"{"error":"Currency pair not supported ETH/XBT","method":"subscribe","success":false,"symbol":"ETH/XBT","time_in":"2023-10-06T17:35:55.408372Z","time_out":"2023-10-06T17:35:55.408426Z"}"#,
+           Event::Error("Currency pair not supported ETH/XBT".to_string()))]
+    #[case(r#"{"channel":"heartbeat"}"#, Event::Heartbeat)]
+    #[case(r#"{"channel":"status","type":"update","data":[{"api_version":"v2","connection_id":12393906104898154338,"system":"online","version":"2.0.0"}]}"#,
+           Event::Unknown("status".to_string()))]
+    #[case(r#"{"method":"pong","time_in":"2023-10-06T17:35:55.408372Z","time_out":"2023-10-06T17:35:55.408426Z"}"#,
+           Event::Unknown("pong".to_string()))]
+    fn parse_test(#[case] message: &str, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(message).unwrap(), expected_event);
+    }
+
+    #[test]
+    fn parse_book_test() {
+        let message = r#"{"channel":"book","type":"update","data":[{"symbol":"ETH/BTC","bids":[{"price":0.05310,"qty":0.00000000}],"asks":[{"price":0.05312,"qty":1.50000000},{"price":1.2e-4,"qty":3}],"checksum":2439117997,"timestamp":"2023-10-06T17:35:55.440295Z"}]}"#;
+
+        let event = Event::parse(message).unwrap();
+
+        assert_eq!(event, Event::Update(vec![BookUpdate {
+            symbol: "ETH/BTC".to_string(),
+            levels: Levels::new(
+                vec![Level::new(dec!(0.05310), dec!(0.00000000))],
+                vec![Level::new(dec!(0.05312), dec!(1.50000000)), Level::new(dec!(0.00012), dec!(3))],
+            ),
+            checksum: 2439117997,
+            timestamp: Some(1696613755440295),
+        }]));
+    }
+
+    #[test]
+    fn parse_fails_test() {
+        assert!(Event::parse(r#"{"type":"update"}"#).is_err());
+        assert!(Event::parse(r#"{"channel":"book","type":"snapshot","data":[{"symbol":"ETH/BTC","bids":[{"price":"a","qty":1}],"asks":[],"checksum":1}]}"#).is_err());
+        assert!(Event::parse("[]").is_err());
+    }
+}
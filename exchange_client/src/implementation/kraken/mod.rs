@@ -0,0 +1,4 @@
+mod book;
+pub mod client;
+mod event;
+mod subscription;
\ No newline at end of file
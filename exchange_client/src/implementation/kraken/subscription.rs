@@ -0,0 +1,43 @@
+use serde::Serialize;
+
+/// The depths of the book channel Kraken publishes.
+const DEPTHS: [usize; 5] = [10, 25, 100, 500, 1000];
+
+#[derive(Serialize)]
+pub struct Subscription {
+    method: String,
+    params: Params,
+}
+
+#[derive(Serialize)]
+struct Params {
+    channel: String,
+    symbol: Vec<String>,
+    depth: usize,
+    snapshot: bool,
+}
+
+impl Subscription {
+    /// Subscribes to the book channel of the given symbol, with the smallest depth Kraken publishes
+    /// that covers the given depth.
+    pub fn new(symbol: &str, depth: usize) -> Self {
+        Subscription {
+            method: "subscribe".into(),
+            params: Params {
+                channel: "book".into(),
+                symbol: vec![symbol.to_string()],
+                depth: DEPTHS.into_iter().find(|published| *published >= depth).unwrap_or(DEPTHS[DEPTHS.len() - 1]),
+                snapshot: true,
+            },
+        }
+    }
+
+    /// Returns the number of levels per side of the subscribed book.
+    pub fn depth(&self) -> usize {
+        self.params.depth
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
@@ -0,0 +1,106 @@
+//! Maintains a local orderbook from the REST snapshot and the updates of the KuCoin level2 channel,
+//! as documented in <https://www.kucoin.com/docs/websocket/spot-trading/public-channels/level2-market-data>.
+//!
+//! The public snapshot holds the top 100 levels per side. The levels beyond them are only known once an update
+//! changes them, which does not affect the top levels the client publishes.
+
+use data_models::levels::{Level, Levels};
+
+use crate::api::error::ExchangeClientError;
+use crate::local_book::LocalBook;
+
+use super::event::{L2Update, Snapshot};
+
+/// The local orderbook of the level2 channel.
+pub struct KucoinBook {
+    book: LocalBook,
+    /// The sequence number of the latest change applied to the local orderbook.
+    sequence: u64,
+}
+
+impl KucoinBook {
+    /// Constructs a new [KucoinBook] from a REST snapshot.
+    pub fn new(snapshot: &Snapshot) -> Self {
+        let mut book = LocalBook::default();
+        book.replace(&snapshot.levels);
+
+        KucoinBook {
+            book,
+            sequence: snapshot.sequence,
+        }
+    }
+
+    /// Applies the changes of an update that are newer than the local orderbook.
+    ///
+    /// Returns false if the update is older than the local orderbook, e.g. an update that was buffered
+    /// while the snapshot was fetched.
+    /// Returns [ExchangeClientError::OutOfSync] if the update does not follow on from the local orderbook,
+    /// because the updates in between were missed.
+    ///
+    /// # Arguments
+    ///
+    /// * `update` - The [L2Update] of the level2 channel.
+    pub fn apply(&mut self, update: &L2Update) -> Result<bool, ExchangeClientError> {
+        if update.sequence_end <= self.sequence {
+            return Ok(false);
+        }
+        if update.sequence_start > self.sequence + 1 {
+            return Err(ExchangeClientError::OutOfSync(format!(
+                "The update of `{}` starts at sequence {}, the local orderbook is at sequence {}",
+                update.symbol, update.sequence_start, self.sequence
+            )));
+        }
+
+        let newer = |changes: &[(Level, u64)]| -> Vec<Level> {
+            changes.iter().filter(|(_, sequence)| *sequence > self.sequence).map(|(level, _)| *level).collect()
+        };
+        let levels = Levels::new(newer(&update.bids), newer(&update.asks));
+        self.book.update(&levels);
+        self.sequence = update.sequence_end;
+        Ok(true)
+    }
+
+    /// Returns up to `depth` of the best bids and asks of the local orderbook.
+    pub fn levels(&self, depth: usize) -> Levels {
+        self.book.levels(depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::api::error::ExchangeClientError;
+    use crate::implementation::kucoin::book::KucoinBook;
+    use crate::implementation::kucoin::event::{L2Update, Snapshot};
+
+    fn update(sequence_start: u64, sequence_end: u64, bids: Vec<(Level, u64)>) -> L2Update {
+        L2Update { symbol: "BTC-USDT".to_string(), sequence_start, sequence_end, bids, asks: vec![], time: None }
+    }
+
+    #[test]
+    fn apply_test() {
+        let mut book = KucoinBook::new(&Snapshot {
+            sequence: 10,
+            levels: Levels::new(vec![Level::new(dec!(26931.4), dec!(0.0521))], vec![Level::new(dec!(26932.1), dec!(0.8764))]),
+        });
+
+        // The update was buffered while the snapshot was fetched
+        assert!(!book.apply(&update(9, 10, vec![(Level::new(dec!(26931.4), dec!(0)), 9)])).unwrap());
+
+        // The update straddles the snapshot, only its newer changes are applied
+        let straddling = update(9, 12, vec![(Level::new(dec!(26931.4), dec!(0)), 9), (Level::new(dec!(26931.7), dec!(2)), 11), (Level::new(dec!(26931.4), dec!(3)), 12)]);
+        assert!(book.apply(&straddling).unwrap());
+        assert_eq!(book.sequence, 12);
+        assert_eq!(book.levels(10).bids, vec![Level::new(dec!(26931.7), dec!(2)), Level::new(dec!(26931.4), dec!(3))]);
+
+        assert!(book.apply(&update(13, 13, vec![(Level::new(dec!(26931.7), dec!(0)), 13)])).unwrap());
+        assert_eq!(book.levels(10).bids, vec![Level::new(dec!(26931.4), dec!(3))]);
+
+        // The updates of sequence 14 were missed
+        let result = book.apply(&update(15, 15, vec![(Level::new(dec!(26930.9), dec!(1)), 15)]));
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+}
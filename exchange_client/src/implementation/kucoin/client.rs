@@ -0,0 +1,447 @@
+//! A client implementation for the KuCoin exchange, subscribing to the `/market/level2:$symbol` channel.
+//! KuCoin hands out the websocket endpoint and a connection token over REST, which the client requests
+//! in [ExchangeClient::bootstrap] before every connection attempt.
+//! The client is instantiated by [crate::api::provider].
+
+use std::sync::{Mutex, PoisonError};
+
+use futures_util::Sink;
+
+use crate::api::heartbeat::HeartbeatPolicy;
+use crate::client_re_exports::*;
+
+use super::book::KucoinBook;
+use super::event::{Bullet, Event, L2Update, Snapshot};
+use super::subscription::{Ping, Subscription};
+
+/// The base Url of the REST api that serves the connection tokens and the orderbook snapshots.
+const REST_URL: &str = "https://api.kucoin.com";
+
+pub struct Kucoin {
+    config: ExchangeClientConfig,
+    sender: MailboxSender,
+    exchange: Exchange,
+    /// The [HeartbeatPolicy] of the current connection, as specified by its [Bullet].
+    heartbeat: Mutex<Option<HeartbeatPolicy>>,
+    /// The local orderbook of the level2 channel, synchronized on every connect.
+    book: Mutex<Option<KucoinBook>>,
+}
+
+impl ExchangeClient for Kucoin {
+
+    fn new(config: ExchangeClientConfig, sender: MailboxSender) -> Self {
+        Kucoin {
+            config,
+            sender,
+            exchange: Exchange::Kucoin,
+            heartbeat: Mutex::new(None),
+            book: Mutex::new(None),
+        }
+    }
+
+    /// Returns the configured base Url. The url of a connection is returned by [ExchangeClient::bootstrap].
+    fn build_url(&self) -> Url {
+        self.config.base_url.clone()
+    }
+
+    /// Requests a connection token with `POST /api/v1/bullet-public` and returns the endpoint of the token.
+    /// The ping interval and timeout of the token replace the configured [HeartbeatPolicy] of the connection.
+    ///
+    /// Returns [ExchangeClientError::Fetch] if the request fails or KuCoin does not hand out a token.
+    async fn bootstrap(&self) -> Result<Url, ExchangeClientError> {
+        let mut url = self.rest_url();
+        url.set_path("/api/v1/bullet-public");
+
+        println!("Requesting a connection token of `{}` : `{}`", self.exchange, url.as_str());
+        let body = self.http_post(&url).await?;
+        let bullet = Bullet::parse(&body)
+            .map_err(|error| ExchangeClientError::Fetch(format!("Could not request a connection token of `{}` : `{}`", url, error)))?;
+
+        *self.heartbeat.lock().unwrap_or_else(PoisonError::into_inner) =
+            Some(HeartbeatPolicy::new(bullet.ping_interval, bullet.ping_interval + bullet.ping_timeout));
+
+        let mut connection_url = Url::parse(&bullet.endpoint)
+            .map_err(|error| ExchangeClientError::Fetch(format!("Invalid endpoint `{}` : `{}`", bullet.endpoint, error)))?;
+        connection_url.query_pairs_mut()
+            .append_pair("token", &bullet.token)
+            .append_pair("connectId", &timestamp::now_micros().to_string());
+        Ok(connection_url)
+    }
+
+    fn config(&self) -> &ExchangeClientConfig {
+        &self.config
+    }
+
+    fn exchange(&self) -> Exchange {
+        self.exchange
+    }
+
+    fn sender(&self) -> &MailboxSender {
+        &self.sender
+    }
+
+    async fn connect(&self, url: &Url) -> Result<(impl Sink<Message, Error=Error> + Unpin, impl Stream<Item=Result<Message, Error>> + Unpin), ExchangeClientError> {
+        let (mut ws_write_stream, mut ws_read_stream) = self.connect_websocket(url).await?;
+        *self.book.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        self.subscribe(&mut ws_write_stream, &mut ws_read_stream).await?;
+        // The updates are buffered by the unread stream until the local orderbook is synchronized
+        self.synchronize().await?;
+        Ok((ws_write_stream, ws_read_stream))
+    }
+
+    fn on_ping(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_pong(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn on_close(&self, message: &Message) {
+        println!("{}", message)
+    }
+
+    fn deserialize(&self, message: &str) -> Result<(), ExchangeClientError> {
+        self.on_event(Event::parse(message)?)
+    }
+
+    /// Returns the [HeartbeatPolicy] of the connection token once one was requested,
+    /// because KuCoin closes the connections that are not pinged within the ping interval of their token.
+    fn heartbeat(&self) -> Option<HeartbeatPolicy> {
+        self.heartbeat.lock().unwrap_or_else(PoisonError::into_inner).clone()
+            .or_else(|| self.config.heartbeat.clone())
+    }
+
+    /// Replaces the ping frame with the `{"id": <n>, "type": "ping"}` message KuCoin expects.
+    fn ping(&self, ping: Message) -> Message {
+        let id = match &ping {
+            Message::Ping(payload) => payload.as_slice().try_into().map(u64::from_be_bytes).unwrap_or_default(),
+            _ => 0,
+        };
+        Message::text(Ping::new(id).serialize())
+    }
+
+    /// Returns the id of the `{"id": <n>, "type": "pong"}` KuCoin answers the ping with the same id with.
+    fn pong(&self, message: &str) -> Option<u64> {
+        match Event::parse(message) {
+            Ok(Event::Pong(id)) => id.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Kucoin {
+    /// Handles an [Event] of the KuCoin websocket.
+    ///
+    /// Returns [ExchangeClientError::OutOfSync] if an update was missed, so that the client reconnects
+    /// and synchronizes the local orderbook from a new REST snapshot.
+    /// Returns [ExchangeClientError::Protocol] on [Event::Error].
+    fn on_event(&self, event: Event) -> Result<(), ExchangeClientError> {
+        match event {
+            Event::L2Update(update) if update.symbol == self.symbol() => self.apply(update),
+            Event::L2Update(update) => {
+                println!("Ignoring `{}` update of `{}`", self.exchange, update.symbol);
+                Ok(())
+            }
+            Event::Welcome | Event::Pong(_) => Ok(()),
+            Event::Ack(id) => {
+                println!("Subscribed successfully to `{}` : `{}`", self.exchange, id);
+                Ok(())
+            }
+            Event::Error(message) => Err(ExchangeClientError::Protocol(format!("`{}` error : `{}`", self.exchange, message))),
+            Event::Unknown(topic) => {
+                println!("Ignoring `{}` message `{}`", self.exchange, topic);
+                Ok(())
+            }
+        }
+    }
+
+    /// Applies an update to the local orderbook and publishes its top levels.
+    /// The end of the sequence range of the update is used as the sequence of the snapshot.
+    fn apply(&self, update: L2Update) -> Result<(), ExchangeClientError> {
+        let received_timestamp = timestamp::now_micros();
+        let mut book = self.book.lock().unwrap_or_else(PoisonError::into_inner);
+        let local_book = book.as_mut()
+            .ok_or_else(|| ExchangeClientError::OutOfSync("The orderbook snapshot has not been fetched".to_string()))?;
+
+        let levels = match local_book.apply(&update) {
+            Ok(true) => local_book.levels(self.config.depth),
+            Ok(false) => return Ok(()),
+            Err(error) => {
+                *book = None;
+                return Err(error);
+            }
+        };
+        drop(book);
+
+        let snapshot = OrderbookSnapshot::new(self.exchange, self.config.instrument.symbol(), levels)
+            .with_sequence(Some(update.sequence_end))
+            .with_exchange_timestamp(update.time)
+            .with_received_timestamp(received_timestamp);
+        self.on_deserialized(&self.sender, snapshot)
+    }
+
+    /// Returns the KuCoin symbol of the instrument, e.g. `ETH-BTC`.
+    fn symbol(&self) -> String {
+        self.config.instrument.exchange_symbol(self.exchange)
+    }
+
+    /// Returns the topic of the level2 channel of the instrument, e.g. `/market/level2:ETH-BTC`.
+    fn topic(&self) -> String {
+        format!("/market/level2:{}", self.symbol())
+    }
+
+    fn rest_url(&self) -> Url {
+        match &self.config.rest_url {
+            Some(rest_url) => rest_url.clone(),
+            None => Url::parse(REST_URL).unwrap(),
+        }
+    }
+
+    /// Fetches the top 100 levels with `GET /api/v1/market/orderbook/level2_100` and replaces the local orderbook with them.
+    async fn synchronize(&self) -> Result<(), ExchangeClientError> {
+        let mut url = self.rest_url();
+        url.set_path("/api/v1/market/orderbook/level2_100");
+        url.query_pairs_mut().append_pair("symbol", &self.symbol());
+
+        println!("Fetching the orderbook snapshot of `{}` : `{}`", self.exchange, url.as_str());
+        let body = self.http_get(&url).await?;
+        let snapshot = Snapshot::parse(&body)
+            .map_err(|error| ExchangeClientError::Fetch(format!("Invalid orderbook snapshot of `{}` : `{}`", url, error)))?;
+
+        *self.book.lock().unwrap_or_else(PoisonError::into_inner) = Some(KucoinBook::new(&snapshot));
+        Ok(())
+    }
+
+    /// Waits for KuCoin to welcome the connection, subscribes to the level2 channel and waits for KuCoin
+    /// to acknowledge the subscription, see [ExchangeClient::await_confirmation].
+    async fn subscribe(
+        &self,
+        ws_write_stream: &mut (impl Sink<Message, Error=Error> + Unpin),
+        ws_read_stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+    ) -> Result<(), ExchangeClientError> {
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::Welcome => Ok(true),
+            event => self.before_confirmation(event),
+        }).await?;
+
+        self.send_subscription(ws_write_stream, &Subscription::new(&self.topic()).serialize()).await?;
+
+        self.await_confirmation(ws_write_stream, ws_read_stream, |message| match Event::parse(message)? {
+            Event::Ack(id) if id == self.topic() => self.on_event(Event::Ack(id)).map(|()| true),
+            event => self.before_confirmation(event),
+        }).await
+    }
+
+    /// Handles an [Event] that arrives before the subscription is acknowledged. The updates are left unapplied,
+    /// the local orderbook is synchronized afterwards.
+    /// Returns [ExchangeClientError::Subscribe] on [Event::Error].
+    fn before_confirmation(&self, event: Event) -> Result<bool, ExchangeClientError> {
+        match event {
+            Event::Error(reason) => Err(ExchangeClientError::rejected(reason)),
+            event => {
+                println!("Ignoring `{}` message before the subscription was confirmed : `{:?}`", self.exchange, event);
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use futures_util::{sink, stream, SinkExt};
+    use rust_decimal_macros::dec;
+
+    use crate::api::http_fetcher::{FetchFuture, HttpFetcher};
+    use data_models::instrument_registry::InstrumentRegistry;
+    use data_models::levels::{Level, Levels};
+
+    use super::*;
+
+    /// Hand-built messages of the level2 channel of `BTC-USDT`, in the format of the KuCoin feed.
+    const WELCOME: &str = r#"{"id":"hQvf8jkno","type":"welcome"}"#;
+    const ACK: &str = r#"{"id":"/market/level2:BTC-USDT","type":"ack"}"#;
+    const BULLET: &str = r#"{"code":"200000","data":{"token":"2neAiuYvAU61ZDXANAGAsiL4","instanceServers":[{"endpoint":"wss://ws-api-spot.kucoin.com/","encrypt":true,"protocol":"websocket","pingInterval":18000,"pingTimeout":10000}]}}"#;
+    const SNAPSHOT: &str = r#"{"code":"200000","data":{"time":1663747970273,"sequence":"14103844","bids":[["26931.4","0.0521"],["26930.9","1.2"]],"asks":[["26932.1","0.8764"]]}}"#;
+
+    fn l2update(sequence_start: u64, sequence_end: u64, bids: &str) -> Message {
+        Message::text(format!(
+            r#"{{"type":"message","topic":"/market/level2:BTC-USDT","subject":"trade.l2update","data":{{"changes":{{"asks":[],"bids":{}}},"sequenceEnd":{},"sequenceStart":{},"symbol":"BTC-USDT","time":1663747970273}}}}"#,
+            bids, sequence_end, sequence_start
+        ))
+    }
+
+    /// An [HttpFetcher] that serves hand-built responses of the connection token and the orderbook snapshot.
+    struct StubFetcher {
+        urls: Mutex<Vec<Url>>,
+    }
+
+    impl HttpFetcher for StubFetcher {
+        fn get<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.urls.lock().unwrap().push(url.clone());
+            Box::pin(async move {
+                match url.path() {
+                    "/api/v1/market/orderbook/level2_100" => Ok(SNAPSHOT.to_string()),
+                    path => Err(ExchangeClientError::Fetch(format!("GET {} not found", path))),
+                }
+            })
+        }
+
+        fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.urls.lock().unwrap().push(url.clone());
+            Box::pin(async move {
+                match url.path() {
+                    "/api/v1/bullet-public" => Ok(BULLET.to_string()),
+                    path => Err(ExchangeClientError::Fetch(format!("POST {} not found", path))),
+                }
+            })
+        }
+    }
+
+    fn client(sender: MailboxSender, fetcher: Arc<StubFetcher>) -> Kucoin {
+        Kucoin::new(ExchangeClientConfig::new("wss://ws-api-spot.kucoin.com".to_string(), 2, InstrumentRegistry::default().get("btcusdt").unwrap().clone())
+                        .with_rest_url("http://localhost:8080".to_string())
+                        .with_http_fetcher(fetcher), sender)
+    }
+
+    fn fetcher() -> Arc<StubFetcher> {
+        Arc::new(StubFetcher { urls: Mutex::new(Vec::new()) })
+    }
+
+    #[tokio::test]
+    async fn bootstrap_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let fetcher = fetcher();
+        let client = client(sender, fetcher.clone());
+        assert_eq!(client.heartbeat(), client.config.heartbeat);
+
+        let url = client.bootstrap().await.unwrap();
+
+        assert_eq!(fetcher.urls.lock().unwrap().clone(), vec![Url::parse("http://localhost:8080/api/v1/bullet-public").unwrap()]);
+        assert_eq!(url.host_str(), Some("ws-api-spot.kucoin.com"));
+        assert!(url.query_pairs().any(|(key, value)| key == "token" && value == "2neAiuYvAU61ZDXANAGAsiL4"));
+        assert!(url.query_pairs().any(|(key, _)| key == "connectId"));
+        assert_eq!(client.heartbeat(), Some(HeartbeatPolicy::new(Duration::from_secs(18), Duration::from_secs(28))));
+    }
+
+    /// An [HttpFetcher] whose requests are rejected by KuCoin.
+    struct RejectingFetcher;
+
+    impl HttpFetcher for RejectingFetcher {
+        fn get<'a>(&'a self, _url: &'a Url) -> FetchFuture<'a> {
+            Box::pin(async { Ok(r#"{"code":"429000","msg":"Too Many Requests"}"#.to_string()) })
+        }
+
+        fn post<'a>(&'a self, url: &'a Url) -> FetchFuture<'a> {
+            self.get(url)
+        }
+    }
+
+    #[tokio::test]
+    async fn bootstrap_error_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = Kucoin::new(ExchangeClientConfig::new("wss://ws-api-spot.kucoin.com".to_string(), 2, InstrumentRegistry::default().get("btcusdt").unwrap().clone())
+                                     .with_http_fetcher(Arc::new(RejectingFetcher)), sender);
+
+        let result = client.bootstrap().await;
+
+        assert!(matches!(result, Err(ExchangeClientError::Fetch(reason)) if reason.contains("Too Many Requests")));
+        assert_eq!(client.heartbeat(), client.config.heartbeat);
+    }
+
+    #[tokio::test]
+    async fn subscribe_waits_for_welcome_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let mut sent = Vec::<Message>::new();
+        let mut messages = stream::iter(vec![
+            Ok(l2update(14103844, 14103844, "[]")), Ok(Message::text(WELCOME)), Ok(Message::text(ACK)), Ok(l2update(14103845, 14103845, "[]")),
+        ]);
+
+        let result = client(sender, fetcher()).subscribe(&mut (&mut sent).sink_map_err(|_| Error::ConnectionClosed), &mut messages).await;
+
+        assert!(result.is_ok());
+        assert_eq!(sent, vec![Message::text(r#"{"id":"/market/level2:BTC-USDT","type":"subscribe","topic":"/market/level2:BTC-USDT","privateChannel":false,"response":true}"#)]);
+        // The update after the acknowledgement is left for process_stream
+        assert!(receiver.try_recv().is_none());
+        assert!(messages.next().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn process_stream_test() {
+        let (sender, mut receiver) = crate::api::mailbox::channel(16);
+        let fetcher = fetcher();
+        let client = client(sender, fetcher.clone());
+        client.synchronize().await.unwrap();
+        assert_eq!(fetcher.urls.lock().unwrap().clone(), vec![Url::parse("http://localhost:8080/api/v1/market/orderbook/level2_100?symbol=BTC-USDT").unwrap()]);
+
+        let messages = vec![
+            // Buffered while the snapshot was fetched
+            Ok(l2update(14103843, 14103844, r#"[["26931.4","0","14103843"]]"#)),
+            Ok(l2update(14103844, 14103846, r#"[["26931.4","0","14103844"],["26931.7","2","14103846"]]"#)),
+            Ok(Message::text(r#"{"id":"1","type":"pong"}"#)),
+        ];
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(messages)).await;
+
+        assert!(result.is_ok());
+        let snapshot = receiver.recv_snapshot().await.unwrap();
+        assert_eq!(snapshot.exchange, Exchange::Kucoin);
+        assert_eq!(snapshot.symbol, "btcusdt");
+        assert_eq!(snapshot.sequence, Some(14103846));
+        assert_eq!(snapshot.exchange_timestamp, Some(1663747970273000));
+        assert_eq!(snapshot.levels, Levels::new(
+            vec![Level::new(dec!(26931.7), dec!(2)), Level::new(dec!(26931.4), dec!(0.0521))],
+            vec![Level::new(dec!(26932.1), dec!(0.8764))],
+        ));
+        assert!(receiver.try_recv().is_none());
+    }
+
+    #[tokio::test]
+    async fn process_stream_out_of_sync_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender, fetcher());
+
+        // The orderbook snapshot has not been fetched
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(vec![Ok(l2update(14103845, 14103845, "[]"))])).await;
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+
+        // The updates of sequence 14103845 were missed
+        client.synchronize().await.unwrap();
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), stream::iter(vec![Ok(l2update(14103846, 14103846, "[]"))])).await;
+        assert!(matches!(result, Err(ExchangeClientError::OutOfSync(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn process_stream_pings_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender, fetcher());
+        client.bootstrap().await.unwrap();
+        let mut pings = Vec::<Message>::new();
+
+        let result = client.process_stream((&mut pings).sink_map_err(|_| Error::ConnectionClosed), stream::pending()).await;
+
+        // The policy of the token pings every 18s and times out after 28s without any message
+        assert!(matches!(result, Err(ExchangeClientError::HeartbeatTimeout(timeout)) if timeout == Duration::from_secs(28)));
+        assert_eq!(pings, vec![Message::text(r#"{"id":"1","type":"ping"}"#)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn process_stream_records_round_trip_time_test() {
+        let (sender, _receiver) = crate::api::mailbox::channel(16);
+        let client = client(sender, fetcher());
+        client.bootstrap().await.unwrap();
+        let round_trip_time = client.config.round_trip_time.clone();
+        // The pong arrives 40ms after the first ping, then KuCoin closes the connection
+        let messages = stream::once(async {
+            tokio::time::sleep(Duration::from_millis(18_040)).await;
+            Ok(Message::text(r#"{"id":"1","type":"pong"}"#))
+        }).chain(stream::iter(vec![Ok(Message::Close(None))]));
+
+        let result = client.process_stream(sink::drain().sink_map_err(|_| Error::ConnectionClosed), Box::pin(messages)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(round_trip_time.last(), Some(Duration::from_millis(40)));
+    }
+}
@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use data_models::level::Decimal;
+use data_models::levels::{Level, Levels};
+
+use crate::deserialization::levels;
+use crate::deserialization::metadata;
+
+/// The response of the REST api of KuCoin, e.g. of `POST /api/v1/bullet-public`.
+#[derive(Deserialize)]
+struct Response<T> {
+    code: String,
+    #[serde(default)]
+    msg: Option<String>,
+    data: Option<T>,
+}
+
+/// The connection token and the websocket endpoint KuCoin hands out through `POST /api/v1/bullet-public`.
+#[derive(Debug, PartialEq)]
+pub struct Bullet {
+    pub token: String,
+    pub endpoint: String,
+    /// The interval within which the client has to ping the server.
+    pub ping_interval: Duration,
+    /// The time after which the server closes the connection if no ping was received.
+    pub ping_timeout: Duration,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBullet {
+    token: String,
+    instance_servers: Vec<RawInstanceServer>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawInstanceServer {
+    endpoint: String,
+    ping_interval: u64,
+    ping_timeout: u64,
+}
+
+impl Bullet {
+    /// Parses the response of `POST /api/v1/bullet-public`. The first instance server is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the response.
+    pub fn parse(body: &str) -> Result<Bullet, String> {
+        let bullet: RawBullet = parse_response(body)?;
+        let server = bullet.instance_servers.into_iter().next().ok_or("The response has no instance server")?;
+
+        Ok(Bullet {
+            token: bullet.token,
+            endpoint: server.endpoint,
+            ping_interval: Duration::from_millis(server.ping_interval),
+            ping_timeout: Duration::from_millis(server.ping_timeout),
+        })
+    }
+}
+
+/// The orderbook snapshot of `GET /api/v1/market/orderbook/level2_100`.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    pub sequence: u64,
+    pub levels: Levels,
+}
+
+impl Snapshot {
+    /// Parses the response of `GET /api/v1/market/orderbook/level2_100`.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the response.
+    pub fn parse(body: &str) -> Result<Snapshot, String> {
+        let data: Value = parse_response(body)?;
+        let sequence = metadata::as_u64(&data["sequence"]).ok_or("The snapshot has no sequence")?;
+        let levels = levels::deserialize_all(
+            &data,
+            |value| -> &Value { &value["bids"] },
+            |value| -> &Value { &value["asks"] },
+        ).map_err(|error| error.to_string())?;
+
+        Ok(Snapshot { sequence, levels })
+    }
+}
+
+fn parse_response<T: for<'de> Deserialize<'de>>(body: &str) -> Result<T, String> {
+    let response: Response<T> = serde_json::from_str(body).map_err(|error| error.to_string())?;
+    match (response.code.as_str(), response.data) {
+        ("200000", Some(data)) => Ok(data),
+        (code, _) => Err(format!("{} : {}", code, response.msg.unwrap_or_default())),
+    }
+}
+
+/// An incremental update of the level2 channel. Every change carries its own sequence number,
+/// the update carries the range of the sequence numbers of its changes.
+#[derive(Debug, PartialEq)]
+pub struct L2Update {
+    /// The KuCoin symbol of the instrument, e.g. `ETH-BTC`.
+    pub symbol: String,
+    pub sequence_start: u64,
+    pub sequence_end: u64,
+    /// The bids that changed with the sequence number of each change. A zero size removes the level.
+    pub bids: Vec<(Level, u64)>,
+    /// The asks that changed with the sequence number of each change.
+    pub asks: Vec<(Level, u64)>,
+    /// The time of the update in microseconds since the unix epoch.
+    pub time: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawL2Update {
+    symbol: String,
+    sequence_start: u64,
+    sequence_end: u64,
+    changes: RawChanges,
+    #[serde(default)]
+    time: Option<u64>,
+}
+
+/// The changes of an update, `[price, size, sequence]` as strings.
+#[derive(Deserialize)]
+struct RawChanges {
+    bids: Vec<(String, String, String)>,
+    asks: Vec<(String, String, String)>,
+}
+
+/// The events of the KuCoin websocket the client handles.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The first message of a connection.
+    Welcome,
+    /// The subscription with the given id was acknowledged.
+    Ack(String),
+    /// The answer to the ping with the given id.
+    Pong(String),
+    L2Update(L2Update),
+    /// KuCoin rejected a request, holding the error code and message.
+    Error(String),
+    /// Any other message, holding its type or topic.
+    Unknown(String),
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "type")]
+    message_type: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    topic: Option<String>,
+    #[serde(default)]
+    subject: Option<String>,
+    #[serde(default)]
+    code: Value,
+    #[serde(default)]
+    data: Value,
+}
+
+impl Event {
+    /// Parses a message of the KuCoin websocket.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The text of a websocket message.
+    pub fn parse(message: &str) -> Result<Event, serde_json::Error> {
+        let envelope: Envelope = serde_json::from_str(message)?;
+
+        Ok(match (envelope.message_type.as_str(), envelope.subject.as_deref()) {
+            ("welcome", _) => Event::Welcome,
+            ("ack", _) => Event::Ack(envelope.id.unwrap_or_default()),
+            ("pong", _) => Event::Pong(envelope.id.unwrap_or_default()),
+            ("message", Some("trade.l2update")) => {
+                let update = RawL2Update::deserialize(&envelope.data)?;
+                Event::L2Update(L2Update {
+                    symbol: update.symbol,
+                    sequence_start: update.sequence_start,
+                    sequence_end: update.sequence_end,
+                    bids: changes(update.changes.bids)?,
+                    asks: changes(update.changes.asks)?,
+                    time: update.time.map(|time| time * 1000),
+                })
+            }
+            ("message", _) => Event::Unknown(envelope.topic.unwrap_or(envelope.message_type)),
+            ("error", _) => Event::Error(format!("{} : {}", envelope.code, envelope.data.as_str().unwrap_or_default())),
+            _ => Event::Unknown(envelope.message_type),
+        })
+    }
+}
+
+fn changes(changes: Vec<(String, String, String)>) -> Result<Vec<(Level, u64)>, serde_json::Error> {
+    let number = |value: &str| Decimal::from_str_exact(value)
+        .map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(value), &"a decimal number"));
+
+    changes.iter()
+        .map(|(price, size, sequence)| Ok((
+            Level::new(number(price)?, number(size)?),
+            sequence.parse().map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(sequence), &"a sequence number"))?,
+        )))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::implementation::kucoin::event::{Bullet, Event, L2Update, Snapshot};
+
+    #[rstest]
+    #[case(r#"{"id":"hQvf8jkno","type":"welcome"}"#, Event::Welcome)]
+    #[case(r#"{"id":"/market/level2:BTC-USDT","type":"ack"}"#, Event::Ack("/market/level2:BTC-USDT".to_string()))]
+    #[case(r#"{"id":"1","type":"pong","timestamp":1663747970273000}"#, Event::Pong("1".to_string()))]
+    #[case(r#"{"id":"/market/level2:ETH-XYZ","type":"error","code":404,"data":"topic /market/level2:ETH-XYZ is not found"}"#,
+           Event::Error("404 : topic /market/level2:ETH-XYZ is not found".to_string()))]
+    #[case(r#"{"type":"message","topic":"/market/ticker:BTC-USDT","subject":"trade.ticker","data":{}}"#, Event::Unknown("/market/ticker:BTC-USDT".to_string()))]
+    #[case(r#"{"type":"message","topic":"/market/level2:BTC-USDT","subject":"trade.l2update","data":{"changes":{"asks":[["26932.1","0","14103845"]],"bids":[["26931.4","0.0521","14103846"]]},"sequenceEnd":14103846,"sequenceStart":14103845,"symbol":"BTC-USDT","time":1663747970273}}"#,
+           Event::L2Update(L2Update {
+               symbol: "BTC-USDT".to_string(),
+               sequence_start: 14103845,
+               sequence_end: 14103846,
+               bids: vec![(Level::new(dec!(26931.4), dec!(0.0521)), 14103846)],
+               asks: vec![(Level::new(dec!(26932.1), dec!(0)), 14103845)],
+               time: Some(1663747970273000),
+           }))]
+    fn parse_test(#[case] message: &str, #[case] expected_event: Event) {
+        assert_eq!(Event::parse(message).unwrap(), expected_event);
+    }
+
+    #[test]
+    fn parse_fails_test() {
+        assert!(Event::parse(r#"{"id":"1"}"#).is_err());
+        assert!(Event::parse(r#"{"type":"message","subject":"trade.l2update","data":{"changes":{"asks":[],"bids":[["a","1","1"]]},"sequenceEnd":1,"sequenceStart":1,"symbol":"BTC-USDT"}}"#).is_err());
+        assert!(Event::parse(r#"{"type":"message","subject":"trade.l2update","data":{"changes":{"asks":[],"bids":[["1","1"]]},"sequenceEnd":1,"sequenceStart":1,"symbol":"BTC-USDT"}}"#).is_err());
+    }
+
+    #[test]
+    fn bullet_test() {
+        let body = r#"{"code":"200000","data":{"token":"2neAiuYvAU61ZDXANAGAsiL4","instanceServers":[{"endpoint":"wss://ws-api-spot.kucoin.com/","encrypt":true,"protocol":"websocket","pingInterval":18000,"pingTimeout":10000}]}}"#;
+
+        assert_eq!(Bullet::parse(body).unwrap(), Bullet {
+            token: "2neAiuYvAU61ZDXANAGAsiL4".to_string(),
+            endpoint: "wss://ws-api-spot.kucoin.com/".to_string(),
+            ping_interval: Duration::from_secs(18),
+            ping_timeout: Duration::from_secs(10),
+        });
+        assert!(Bullet::parse(r#"{"code":"200000","data":{"token":"2neAiuYvAU61ZDXANAGAsiL4","instanceServers":[]}}"#).is_err());
+        assert!(Bullet::parse(r#"{"code":"429000","msg":"Too Many Requests"}"#).is_err_and(|error| error.contains("Too Many Requests")));
+    }
+
+    #[test]
+    fn snapshot_test() {
+        let body = r#"{"code":"200000","data":{"time":1663747970273,"sequence":"14103844","bids":[["26931.4","0.0521"],["26930.9","1.2"]],"asks":[["26932.1","0.8764"]]}}"#;
+
+        assert_eq!(Snapshot::parse(body).unwrap(), Snapshot {
+            sequence: 14103844,
+            levels: Levels::new(vec![Level::new(dec!(26931.4), dec!(0.0521)), Level::new(dec!(26930.9), dec!(1.2))], vec![Level::new(dec!(26932.1), dec!(0.8764))]),
+        });
+        assert!(Snapshot::parse(r#"{"code":"200000","data":{"bids":[],"asks":[]}}"#).is_err());
+    }
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    id: String,
+    #[serde(rename = "type")]
+    message_type: String,
+    topic: String,
+    private_channel: bool,
+    /// Asks KuCoin to acknowledge the subscription.
+    response: bool,
+}
+
+impl Subscription {
+    /// Subscribes to the given topic, e.g. `/market/level2:ETH-BTC`. The id is echoed by the acknowledgement.
+    pub fn new(topic: &str) -> Self {
+        Subscription {
+            id: topic.to_string(),
+            message_type: "subscribe".into(),
+            topic: topic.to_string(),
+            private_channel: false,
+            response: true,
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// The ping KuCoin expects within the ping interval of the connection, which it answers with a `pong` of the same id.
+#[derive(Serialize)]
+pub struct Ping {
+    id: String,
+    #[serde(rename = "type")]
+    message_type: String,
+}
+
+impl Ping {
+    pub fn new(id: u64) -> Self {
+        Ping {
+            id: id.to_string(),
+            message_type: "ping".into(),
+        }
+    }
+
+    pub fn serialize(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
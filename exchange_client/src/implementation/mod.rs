@@ -1,2 +1,7 @@
 pub mod binance;
-pub mod bitstamp;
\ No newline at end of file
+pub mod bitfinex;
+pub mod bitstamp;
+pub mod coinbase;
+pub mod huobi;
+pub mod kraken;
+pub mod kucoin;
\ No newline at end of file
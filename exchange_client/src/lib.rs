@@ -7,3 +7,4 @@ mod client_re_exports;
 mod deserialization;
 mod exchange_client;
 mod implementation;
+mod local_book;
@@ -0,0 +1,111 @@
+//! A local orderbook for the exchange clients that receive incremental updates
+//! and have to maintain the orderbook themselves, e.g. the Binance diff depth stream.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use data_models::level::Decimal;
+use data_models::levels::{Level, Levels};
+
+/// The bids and asks of an exchange orderbook, keyed by price.
+#[derive(Default)]
+pub struct LocalBook {
+    bids: BTreeMap<Reverse<Decimal>, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl LocalBook {
+    /// Replaces all levels of the orderbook with the levels of a snapshot.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - The bids and asks of the snapshot.
+    pub fn replace(&mut self, levels: &Levels) {
+        self.bids.clear();
+        self.asks.clear();
+        self.update(levels);
+    }
+
+    /// Applies an incremental update to the orderbook.
+    /// A level with a zero amount removes the level with the same price.
+    ///
+    /// # Arguments
+    ///
+    /// * `levels` - The bids and asks that changed.
+    pub fn update(&mut self, levels: &Levels) {
+        for level in levels.bids.iter() {
+            match level.amount.is_zero() {
+                true => self.bids.remove(&Reverse(level.price)),
+                false => self.bids.insert(Reverse(level.price), level.amount),
+            };
+        }
+
+        for level in levels.asks.iter() {
+            match level.amount.is_zero() {
+                true => self.asks.remove(&level.price),
+                false => self.asks.insert(level.price, level.amount),
+            };
+        }
+    }
+
+    /// Removes the levels beyond the best `depth` bids and asks, e.g. of an exchange that
+    /// only maintains a limited number of levels per side.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The maximum number of levels per side.
+    pub fn truncate(&mut self, depth: usize) {
+        while self.bids.len() > depth {
+            self.bids.pop_last();
+        }
+        while self.asks.len() > depth {
+            self.asks.pop_last();
+        }
+    }
+
+    /// Returns up to `depth` of the best bids and asks, bids in descending and asks in ascending order of price.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The maximum number of levels per side.
+    pub fn levels(&self, depth: usize) -> Levels {
+        Levels::new(
+            self.bids.iter().take(depth).map(|(Reverse(price), amount)| Level::new(*price, *amount)).collect(),
+            self.asks.iter().take(depth).map(|(price, amount)| Level::new(*price, *amount)).collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::levels::{Level, Levels};
+
+    use crate::local_book::LocalBook;
+
+    #[test]
+    fn update_test() {
+        let mut book = LocalBook::default();
+        book.replace(&Levels::new(
+            vec![Level::new(dec!(0.9), dec!(1)), Level::new(dec!(1.0), dec!(2))],
+            vec![Level::new(dec!(1.2), dec!(3)), Level::new(dec!(1.1), dec!(4))],
+        ));
+        assert_eq!(book.levels(1), Levels::new(vec![Level::new(dec!(1.0), dec!(2))], vec![Level::new(dec!(1.1), dec!(4))]));
+
+        book.update(&Levels::new(
+            vec![Level::new(dec!(1.0), dec!(0)), Level::new(dec!(0.8), dec!(5))],
+            vec![Level::new(dec!(1.1), dec!(6)), Level::new(dec!(1.3), dec!(0))],
+        ));
+        assert_eq!(book.levels(10), Levels::new(
+            vec![Level::new(dec!(0.9), dec!(1)), Level::new(dec!(0.8), dec!(5))],
+            vec![Level::new(dec!(1.1), dec!(6)), Level::new(dec!(1.2), dec!(3))],
+        ));
+
+        book.truncate(1);
+        assert_eq!(book.levels(10), Levels::new(vec![Level::new(dec!(0.9), dec!(1))], vec![Level::new(dec!(1.1), dec!(6))]));
+
+        book.replace(&Levels::new(vec![], vec![Level::new(dec!(1.5), dec!(1))]));
+        assert_eq!(book.levels(10), Levels::new(vec![], vec![Level::new(dec!(1.5), dec!(1))]));
+    }
+}
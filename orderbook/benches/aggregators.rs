@@ -0,0 +1,75 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+
+use data_models::exchange_level::Exchange;
+use data_models::exchange_orderbook::OrderbookSnapshot;
+use data_models::level::{Decimal, Level};
+use data_models::levels::Levels;
+use orderbook::api::provider::{self, AggregatorConfig, AggregatorType};
+
+/// The number of levels per side each exchange publishes.
+const DEPTHS: [usize; 3] = [10, 100, 1000];
+/// The number of levels per side of the aggregated orderbook, the default of the server.
+const OUTPUT_DEPTH: usize = 10;
+const AGGREGATORS: [AggregatorType; 3] = [
+    AggregatorType::HashMapOrderbookAggegator,
+    AggregatorType::PriceConsolidatedAggregator,
+    AggregatorType::SortedMapAggregator,
+];
+
+/// The number of levels per side that change between consecutive snapshots of an update.
+const CHANGED_LEVELS: i64 = 3;
+
+/// Builds a snapshot of `depth` levels per side around a mid price of 1000.
+///
+/// # Arguments
+///
+/// * `offset` - The number of ticks every price is shifted away from the mid price.
+/// * `revision` - Changes the amount of the best [CHANGED_LEVELS] levels per side.
+fn snapshot(exchange: Exchange, depth: usize, offset: i64, revision: i64) -> OrderbookSnapshot {
+    let level = |price: i64, i: i64| {
+        let amount = if i < CHANGED_LEVELS { 100 + revision } else { 100 + i % 13 };
+        Level::new(Decimal::new(price, 2), Decimal::new(amount, 1))
+    };
+    let bids = (0..depth as i64).map(|i| level(100_000 - offset - i, i)).collect();
+    let asks = (0..depth as i64).map(|i| level(100_001 + offset + i, i)).collect();
+    OrderbookSnapshot::new(exchange, "ethbtc".to_string(), Levels::new(bids, asks))
+}
+
+/// Measures a single snapshot of one exchange while another exchange already contributes
+/// `depth` levels per side, which is the steady state of the server.
+///
+/// In the `replace` group every price of the snapshot moves, in the `update` group only
+/// the amounts of the best few levels change, which is the common case for a live book.
+fn on_orderbook_snapshot(c: &mut Criterion) {
+    bench_group(c, "on_orderbook_snapshot/replace", |depth, i| snapshot(Exchange::Binance, depth, i % 5, 0));
+    bench_group(c, "on_orderbook_snapshot/update", |depth, i| snapshot(Exchange::Binance, depth, 0, i % 5));
+}
+
+fn bench_group(c: &mut Criterion, name: &str, next_snapshot: impl Fn(usize, i64) -> OrderbookSnapshot) {
+    let mut group = c.benchmark_group(name);
+
+    for aggregator_type in AGGREGATORS {
+        for depth in DEPTHS {
+            group.bench_with_input(BenchmarkId::new(aggregator_type.to_string(), depth), &depth, |b, &depth| {
+                let mut aggregator = provider::get(aggregator_type, AggregatorConfig::new(OUTPUT_DEPTH));
+                aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, depth, 0, 0));
+                aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, depth, 1, 0));
+
+                let mut i = 0;
+                b.iter_batched(
+                    || {
+                        i += 1;
+                        next_snapshot(depth, i)
+                    },
+                    |snapshot| aggregator.on_orderbook_snapshot(snapshot),
+                    BatchSize::SmallInput,
+                );
+            });
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, on_orderbook_snapshot);
+criterion_main!(benches);
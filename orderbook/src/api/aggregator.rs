@@ -1,18 +1,54 @@
 use data_models::aggregated_orderbook::AggregatedOrderbook;
+use data_models::connection_event::ConnectionEvent;
 use data_models::exchange_orderbook::OrderbookSnapshot;
 
+use crate::api::configuration::AggregatorConfig;
+
 pub trait OrderbookSnapshotAggregator {
 
-    fn new() -> Self;
+    /// Constructs a new aggregator.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The [AggregatorConfig] of the aggregator.
+    fn new(config: AggregatorConfig) -> Self where Self: Sized;
 
-    /// TCalled when any exchange client publishes an orderbook snapshot.
+    /// Called when any exchange client publishes an orderbook snapshot.
+    /// The received timestamp of the snapshot is used as the current time
+    /// to drop the levels of any exchange that became stale.
     ///
     /// # Arguments
     ///
     /// * `orderbook` - The [OrderbookSnapshot] that will be merged into the [AggregatedOrderbook].
     ///
     /// Returns the updated [AggregatedOrderbook]. The return value contains
-    /// the aggregated orderbooks of one or more exchanges.
+    /// the aggregated orderbooks of one or more exchanges, truncated to
+    /// the best [AggregatorConfig::depth] levels per side.
+    /// Returns [None] if the snapshot is a duplicate or arrived out of order,
+    /// see [OrderbookSnapshot::supersedes]. Rejected snapshots are not merged.
     fn on_orderbook_snapshot(&mut self, orderbook_snapshot: OrderbookSnapshot)
-                             -> AggregatedOrderbook;
+                             -> Option<AggregatedOrderbook>;
+
+    /// Called periodically to drop the levels of the exchanges that stopped publishing,
+    /// see [AggregatorConfig::staleness_timeout].
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time in microseconds since the unix epoch.
+    ///
+    /// Returns the updated [AggregatedOrderbook] of every symbol for which an exchange
+    /// became stale since the previous update, or an empty [Vec] if none did.
+    fn on_timer(&mut self, now: u64) -> Vec<AggregatedOrderbook>;
+
+    /// Called when an exchange client reports a change of its connection state.
+    /// The levels of an exchange are cleared as soon as its client is no longer subscribed,
+    /// see [data_models::connection_state::ConnectionState::is_subscribed].
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The [ConnectionEvent] of the exchange client.
+    ///
+    /// Returns the updated [AggregatedOrderbook] of the symbol of the event, whose exchange
+    /// summaries carry the connection state of every exchange that reported one.
+    fn on_connection_event(&mut self, event: &ConnectionEvent) -> Option<AggregatedOrderbook>;
 }
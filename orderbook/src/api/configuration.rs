@@ -0,0 +1,126 @@
+//! Configuration for the aggregators implementing [crate::api::aggregator::OrderbookSnapshotAggregator].
+//! The configuration must be instantiated by the caller and then provided to [crate::api::provider].
+
+use std::{fmt, str::FromStr};
+use std::time::Duration;
+
+use data_models::exchange_orderbook::OrderbookSnapshot;
+
+/// The aggregator configuration that is supplied to the [crate::api::provider].
+#[derive(Debug, Clone)]
+pub struct AggregatorConfig {
+    pub depth: usize,
+    /// The time after which the latest snapshot of an exchange is considered stale.
+    /// The levels of stale exchanges are dropped from the aggregated orderbook.
+    /// Stale exchanges are never evicted if it is [None].
+    pub staleness_timeout: Option<Duration>,
+    /// How the aggregator resolves the levels of a crossed orderbook.
+    pub crossed_book_policy: CrossedBookPolicy,
+}
+
+/// Enumeration of the ways an aggregator can resolve a crossed orderbook, i.e. an orderbook
+/// where the best bid of one exchange is above the best ask of another exchange.
+/// Locked orderbooks, where the best bid is equal to the best ask, are always passed through.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CrossedBookPolicy {
+    /// Publishes the crossed levels as they are.
+    PassThrough,
+    /// Trims the crossed amounts of the top levels, as if the crossed bids and asks
+    /// had traded against each other, until the best bid is not above the best ask.
+    TrimCrossed,
+    /// Drops the bids or asks of the exchange whose latest snapshot was received first,
+    /// until the best bid is not above the best ask.
+    DropStaler,
+}
+
+impl FromStr for CrossedBookPolicy {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<CrossedBookPolicy, Self::Err> {
+        match input {
+            "passthrough" => Ok(CrossedBookPolicy::PassThrough),
+            "trim" => Ok(CrossedBookPolicy::TrimCrossed),
+            "dropstaler" => Ok(CrossedBookPolicy::DropStaler),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for CrossedBookPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrossedBookPolicy::PassThrough => write!(f, "passthrough"),
+            CrossedBookPolicy::TrimCrossed => write!(f, "trim"),
+            CrossedBookPolicy::DropStaler => write!(f, "dropstaler"),
+        }
+    }
+}
+
+impl AggregatorConfig {
+    /// Constructs a new [AggregatorConfig] without a staleness timeout
+    /// that passes crossed orderbooks through.
+    ///
+    /// # Arguments
+    ///
+    /// * `depth` - The maximum number of merged levels per side of the aggregated orderbook.
+    ///   It is independent of the depth each exchange client subscribes to.
+    pub fn new(depth: usize) -> Self {
+        AggregatorConfig {
+            depth,
+            staleness_timeout: None,
+            crossed_book_policy: CrossedBookPolicy::PassThrough,
+        }
+    }
+
+    /// Sets how the aggregator resolves the levels of a crossed orderbook.
+    pub fn with_crossed_book_policy(mut self, crossed_book_policy: CrossedBookPolicy) -> Self {
+        self.crossed_book_policy = crossed_book_policy;
+        self
+    }
+
+    /// Sets the time after which the latest snapshot of an exchange is considered stale.
+    pub fn with_staleness_timeout(mut self, staleness_timeout: Duration) -> Self {
+        self.staleness_timeout = Some(staleness_timeout);
+        self
+    }
+
+    /// Returns true if the given snapshot was received longer than the staleness timeout ago.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - The latest [OrderbookSnapshot] of an exchange.
+    /// * `now` - The current time in microseconds since the unix epoch.
+    pub fn is_stale(&self, snapshot: &OrderbookSnapshot, now: u64) -> bool {
+        match self.staleness_timeout {
+            Some(timeout) => now.saturating_sub(snapshot.received_timestamp) > timeout.as_micros() as u64,
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use data_models::exchange_level::Exchange;
+    use data_models::exchange_orderbook::OrderbookSnapshot;
+    use data_models::levels::Levels;
+
+    use crate::api::configuration::AggregatorConfig;
+
+    #[test]
+    fn is_stale_test() {
+        let snapshot = OrderbookSnapshot::new(Exchange::Binance, "ethbtc".to_string(), Levels::new(vec![], vec![]))
+            .with_received_timestamp(1_000_000);
+
+        let config = AggregatorConfig::new(10);
+        assert!(!config.is_stale(&snapshot, u64::MAX));
+
+        let config = config.with_staleness_timeout(Duration::from_secs(1));
+        assert!(!config.is_stale(&snapshot, 1_500_000));
+        assert!(!config.is_stale(&snapshot, 2_000_000));
+        assert!(config.is_stale(&snapshot, 2_000_001));
+        // Snapshots received after `now` are never stale
+        assert!(!config.is_stale(&snapshot, 0));
+    }
+}
@@ -1,2 +1,3 @@
 pub mod provider;
-pub mod aggregator;
\ No newline at end of file
+pub mod aggregator;
+pub mod configuration;
\ No newline at end of file
@@ -1,11 +1,56 @@
+use std::{fmt, str::FromStr};
+
 pub use crate::api::aggregator::OrderbookSnapshotAggregator;
+pub use crate::api::configuration::{AggregatorConfig, CrossedBookPolicy};
 
+/// Enumeration of the available aggregator implementations.
+/// To add a new implementation add it in this enumeration, implement the
+/// match arms in [`FromStr`] and [`fmt::Display`] and return it from [get].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AggregatorType {
+    /// Keeps every exchange level as a separate row of the aggregated orderbook.
     HashMapOrderbookAggegator,
+    /// Merges the levels of all exchanges with the same price into one level
+    /// that holds the total amount and a per exchange breakdown.
+    PriceConsolidatedAggregator,
+    /// Keeps each side of the aggregated orderbook in an ordered set and
+    /// only updates the levels of the exchange that published a snapshot.
+    SortedMapAggregator,
+}
+
+impl FromStr for AggregatorType {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<AggregatorType, Self::Err> {
+        match input {
+            "hashmap" => Ok(AggregatorType::HashMapOrderbookAggegator),
+            "consolidated" => Ok(AggregatorType::PriceConsolidatedAggregator),
+            "sortedmap" => Ok(AggregatorType::SortedMapAggregator),
+            _ => Err(()),
+        }
+    }
 }
 
-pub fn get(aggregator: AggregatorType) -> impl OrderbookSnapshotAggregator {
+impl fmt::Display for AggregatorType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AggregatorType::HashMapOrderbookAggegator => write!(f, "hashmap"),
+            AggregatorType::PriceConsolidatedAggregator => write!(f, "consolidated"),
+            AggregatorType::SortedMapAggregator => write!(f, "sortedmap"),
+        }
+    }
+}
+
+/// Returns a new aggregator of the given [AggregatorType].
+///
+/// # Arguments
+///
+/// * `aggregator` - The [AggregatorType] to instantiate.
+/// * `config` - The [AggregatorConfig] of the aggregator.
+pub fn get(aggregator: AggregatorType, config: AggregatorConfig) -> Box<dyn OrderbookSnapshotAggregator + Send> {
     match aggregator {
-        AggregatorType::HashMapOrderbookAggegator => crate::implementation::hashmap_aggregator::HashMapAggregator::new()
+        AggregatorType::HashMapOrderbookAggegator => Box::new(crate::implementation::hashmap_aggregator::HashMapAggregator::new(config)),
+        AggregatorType::PriceConsolidatedAggregator => Box::new(crate::implementation::consolidated_aggregator::ConsolidatedAggregator::new(config)),
+        AggregatorType::SortedMapAggregator => Box::new(crate::implementation::sorted_map_aggregator::SortedMapAggregator::new(config)),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+//! Applies the connection states reported by the exchange clients to the exchanges of an aggregated orderbook.
+
+use std::collections::BTreeMap;
+
+use data_models::connection_state::ConnectionState;
+use data_models::exchange::Exchange;
+use data_models::exchange_summary::ExchangeSummary;
+
+/// The latest [ConnectionState] of the exchange clients of a symbol.
+pub type ConnectionStates = BTreeMap<Exchange, ConnectionState>;
+
+/// Sets the connection state of each exchange summary and adds a summary without levels for every
+/// exchange that reported a connection state but has no snapshot, e.g. because it disconnected.
+/// Exchanges that never reported a connection state are [ConnectionState::Subscribed].
+///
+/// # Arguments
+///
+/// * `connection_states` - The [ConnectionStates] of the symbol.
+/// * `exchanges` - The [ExchangeSummary] of every exchange with a snapshot.
+pub fn summarize(connection_states: &ConnectionStates, mut exchanges: Vec<ExchangeSummary>) -> Vec<ExchangeSummary> {
+    for summary in exchanges.iter_mut() {
+        if let Some(state) = connection_states.get(&summary.exchange) {
+            summary.connection_state = *state;
+        }
+    }
+
+    for (exchange, state) in connection_states.iter() {
+        if !exchanges.iter().any(|summary| summary.exchange == *exchange) {
+            exchanges.push(ExchangeSummary::new(*exchange, None, None).with_connection_state(*state));
+        }
+    }
+
+    exchanges
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::connection_state::ConnectionState;
+    use data_models::exchange::Exchange;
+    use data_models::exchange_summary::ExchangeSummary;
+    use data_models::level::Level;
+
+    use crate::implementation::connection_states::{summarize, ConnectionStates};
+
+    #[test]
+    fn summarize_test() {
+        let exchanges = vec![ExchangeSummary::new(Exchange::Binance, Some(Level::new(dec!(1.0), dec!(1.0))), None)];
+
+        let summaries = summarize(&ConnectionStates::new(), exchanges.clone());
+        assert_eq!(summaries, exchanges);
+        assert_eq!(summaries[0].connection_state, ConnectionState::Subscribed);
+
+        let connection_states = ConnectionStates::from([
+            (Exchange::Binance, ConnectionState::Subscribed),
+            (Exchange::Bitstamp, ConnectionState::Reconnecting),
+        ]);
+        let summaries = summarize(&connection_states, exchanges.clone());
+        assert_eq!(summaries, vec![
+            exchanges[0],
+            ExchangeSummary::new(Exchange::Bitstamp, None, None).with_connection_state(ConnectionState::Reconnecting),
+        ]);
+    }
+}
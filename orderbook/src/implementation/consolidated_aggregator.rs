@@ -0,0 +1,149 @@
+use data_models::aggregated_orderbook::AggregatedOrderbook;
+use data_models::connection_event::ConnectionEvent;
+use data_models::consolidated_level::ConsolidatedLevel;
+use data_models::consolidated_levels::ConsolidatedLevels;
+use data_models::exchange_level::ExchangeLevel;
+use data_models::exchange_orderbook::OrderbookSnapshot;
+
+use crate::api::aggregator::OrderbookSnapshotAggregator;
+use crate::api::configuration::AggregatorConfig;
+use crate::implementation::hashmap_aggregator::HashMapAggregator;
+
+/// An aggregator that merges the levels of all exchanges with the same price.
+///
+/// It delegates the merging and sorting of the exchange levels to a [HashMapAggregator]
+/// and then consolidates the sorted rows by price. The configured depth applies to
+/// the number of price levels, so the inner aggregator keeps the full depth.
+pub struct ConsolidatedAggregator {
+    config: AggregatorConfig,
+    aggregator: HashMapAggregator,
+}
+
+impl OrderbookSnapshotAggregator for ConsolidatedAggregator {
+    /// Constructs a new [ConsolidatedAggregator].
+    fn new(config: AggregatorConfig) -> Self {
+        let mut inner_config = config.clone();
+        inner_config.depth = usize::MAX;
+
+        ConsolidatedAggregator {
+            config,
+            aggregator: HashMapAggregator::new(inner_config),
+        }
+    }
+
+    fn on_orderbook_snapshot(&mut self, os: OrderbookSnapshot) -> Option<AggregatedOrderbook> {
+        let aggregated_orderbook = self.aggregator.on_orderbook_snapshot(os)?;
+        Some(self.consolidate(aggregated_orderbook))
+    }
+
+    fn on_timer(&mut self, now: u64) -> Vec<AggregatedOrderbook> {
+        self.aggregator
+            .on_timer(now)
+            .into_iter()
+            .map(|aggregated_orderbook| self.consolidate(aggregated_orderbook))
+            .collect()
+    }
+
+    fn on_connection_event(&mut self, event: &ConnectionEvent) -> Option<AggregatedOrderbook> {
+        let aggregated_orderbook = self.aggregator.on_connection_event(event)?;
+        Some(self.consolidate(aggregated_orderbook))
+    }
+}
+
+impl ConsolidatedAggregator {
+    /// Consolidates the sorted rows of the inner aggregator by price and truncates them to the configured depth.
+    fn consolidate(&self, mut aggregated_orderbook: AggregatedOrderbook) -> AggregatedOrderbook {
+        let consolidated = ConsolidatedLevels::new(
+            consolidate_exchange_levels(&aggregated_orderbook.bids, self.config.depth),
+            consolidate_exchange_levels(&aggregated_orderbook.asks, self.config.depth),
+        );
+
+        aggregated_orderbook.bids.truncate(self.config.depth);
+        aggregated_orderbook.asks.truncate(self.config.depth);
+        aggregated_orderbook.with_consolidated(consolidated)
+    }
+}
+
+/// Merges consecutive exchange levels with the same price into [ConsolidatedLevel]s.
+///
+/// # Arguments
+///
+/// * `exchange_levels` - The bids or asks sorted by price.
+/// * `depth` - The maximum number of price levels to return.
+fn consolidate_exchange_levels(exchange_levels: &[ExchangeLevel], depth: usize) -> Vec<ConsolidatedLevel> {
+    exchange_levels
+        .chunk_by(|el1, el2| el1.level.price == el2.level.price)
+        .take(depth)
+        .map(|breakdown| ConsolidatedLevel::new(breakdown.to_vec()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::consolidated_level::ConsolidatedLevel;
+    use data_models::exchange_level::{Exchange, ExchangeLevel};
+    use data_models::exchange_orderbook::OrderbookSnapshot;
+    use data_models::level::Level;
+    use data_models::levels::Levels;
+
+    use crate::api::aggregator::OrderbookSnapshotAggregator;
+    use crate::api::configuration::AggregatorConfig;
+    use crate::implementation::consolidated_aggregator::{consolidate_exchange_levels, ConsolidatedAggregator};
+
+    #[rstest]
+    #[case(
+        vec![
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(3.0))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.00), dec!(1.5))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(1.0), dec!(1.0))),
+        ],
+        10,
+        vec![
+            ConsolidatedLevel::new(vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(3.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(1.5)))]),
+            ConsolidatedLevel::new(vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(1.0), dec!(1.0)))]),
+        ]
+    )]
+    #[case(
+        vec![
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(3.0))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(1.5))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(1.0), dec!(1.0))),
+        ],
+        1,
+        vec![
+            ConsolidatedLevel::new(vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(3.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(1.5)))]),
+        ]
+    )]
+    #[case(vec![], 10, vec![])]
+    fn consolidate_exchange_levels_test(
+        #[case] exchange_levels: Vec<ExchangeLevel>,
+        #[case] depth: usize,
+        #[case] expected: Vec<ConsolidatedLevel>,
+    ) {
+        assert_eq!(consolidate_exchange_levels(&exchange_levels, depth), expected);
+    }
+
+    #[test]
+    fn on_orderbook_snapshot_test() {
+        let mut aggregator = ConsolidatedAggregator::new(AggregatorConfig::new(2));
+        aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Binance, "test-symbol".to_string(), Levels::new(
+            vec![Level::new(dec!(3.0), dec!(1.0)), Level::new(dec!(2.0), dec!(1.0)), Level::new(dec!(1.0), dec!(1.0))],
+            vec![Level::new(dec!(4.0), dec!(1.0)), Level::new(dec!(5.0), dec!(1.0))])));
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Bitstamp, "test-symbol".to_string(), Levels::new(
+            vec![Level::new(dec!(3.0), dec!(2.0)), Level::new(dec!(2.5), dec!(2.0))],
+            vec![Level::new(dec!(4.0), dec!(2.0))]))).unwrap();
+
+        let consolidated = aggregated_orderbook.consolidated.unwrap();
+        assert_eq!(consolidated.bids.iter().map(|cl| cl.level).collect::<Vec<Level>>(), vec![Level::new(dec!(3.0), dec!(3.0)), Level::new(dec!(2.5), dec!(2.0))]);
+        assert_eq!(consolidated.asks.iter().map(|cl| cl.level).collect::<Vec<Level>>(), vec![Level::new(dec!(4.0), dec!(3.0)), Level::new(dec!(5.0), dec!(1.0))]);
+        assert_eq!(consolidated.bids[0].breakdown, vec![
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0))),
+        ]);
+        assert_eq!(aggregated_orderbook.bids.len(), 2);
+        assert_eq!(aggregated_orderbook.asks.len(), 2);
+    }
+}
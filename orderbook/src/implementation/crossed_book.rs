@@ -0,0 +1,196 @@
+use data_models::aggregated_orderbook::AggregatedOrderbook;
+use data_models::book_condition::BookCondition;
+use data_models::exchange::Exchange;
+use data_models::exchange_level::ExchangeLevel;
+use data_models::exchange_summary::ExchangeSummary;
+
+use crate::api::configuration::CrossedBookPolicy;
+
+/// Detects whether the merged orderbook is crossed or locked, resolves it according to the
+/// given [CrossedBookPolicy] and returns the best `depth` levels per side.
+///
+/// The returned [AggregatedOrderbook] carries the [BookCondition] detected before the resolution.
+///
+/// # Arguments
+///
+/// * `bids` - The bids of all exchanges sorted with the best price first.
+/// * `asks` - The asks of all exchanges sorted with the best price first.
+/// * `exchanges` - The [ExchangeSummary] of each exchange, used to find the staler exchange.
+/// * `policy` - The [CrossedBookPolicy] to apply.
+/// * `depth` - The maximum number of levels per side to return.
+pub fn resolve<B, A>(bids: B, asks: A, exchanges: &[ExchangeSummary], policy: CrossedBookPolicy, depth: usize) -> AggregatedOrderbook
+where
+    B: Iterator<Item=ExchangeLevel> + Clone,
+    A: Iterator<Item=ExchangeLevel> + Clone,
+{
+    let condition = BookCondition::from_top_of_book(
+        bids.clone().next().as_ref().map(|el| &el.level),
+        asks.clone().next().as_ref().map(|el| &el.level));
+
+    let (bids, asks) = match (condition, policy) {
+        (BookCondition::Crossed, CrossedBookPolicy::TrimCrossed) => trim_crossed(bids, asks, depth),
+        (BookCondition::Crossed, CrossedBookPolicy::DropStaler) => drop_staler(bids, asks, exchanges, depth),
+        _ => (bids.take(depth).collect(), asks.take(depth).collect()),
+    };
+
+    AggregatedOrderbook::new(bids, asks).with_condition(condition)
+}
+
+/// Subtracts the smaller amount of the best bid and the best ask from both,
+/// dropping the levels that have no amount left, while the best bid is above the best ask.
+fn trim_crossed(mut bids: impl Iterator<Item=ExchangeLevel>, mut asks: impl Iterator<Item=ExchangeLevel>, depth: usize)
+                -> (Vec<ExchangeLevel>, Vec<ExchangeLevel>) {
+    let mut best_bid = bids.next();
+    let mut best_ask = asks.next();
+
+    while let (Some(bid), Some(ask)) = (&mut best_bid, &mut best_ask) {
+        if bid.level.price <= ask.level.price {
+            break;
+        }
+
+        let traded_amount = bid.level.amount.min(ask.level.amount);
+        bid.level.amount -= traded_amount;
+        ask.level.amount -= traded_amount;
+
+        let (bid_filled, ask_filled) = (bid.level.amount.is_zero(), ask.level.amount.is_zero());
+        if bid_filled {
+            best_bid = bids.next();
+        }
+        if ask_filled {
+            best_ask = asks.next();
+        }
+    }
+
+    (
+        best_bid.into_iter().chain(bids).take(depth).collect(),
+        best_ask.into_iter().chain(asks).take(depth).collect(),
+    )
+}
+
+/// Drops the bids or the asks of the exchange whose latest snapshot was received first,
+/// while the best bid is above the best ask. If both belong to the same exchange its bids are dropped.
+fn drop_staler<B, A>(bids: B, asks: A, exchanges: &[ExchangeSummary], depth: usize) -> (Vec<ExchangeLevel>, Vec<ExchangeLevel>)
+where
+    B: Iterator<Item=ExchangeLevel> + Clone,
+    A: Iterator<Item=ExchangeLevel> + Clone,
+{
+    let received_timestamp = |exchange: Exchange| exchanges
+        .iter()
+        .find(|summary| summary.exchange == exchange)
+        .and_then(|summary| summary.received_timestamp);
+
+    let mut dropped_bids = Vec::<Exchange>::new();
+    let mut dropped_asks = Vec::<Exchange>::new();
+
+    loop {
+        let best_bid = bids.clone().find(|el| !dropped_bids.contains(&el.exchange));
+        let best_ask = asks.clone().find(|el| !dropped_asks.contains(&el.exchange));
+
+        match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) if bid.level.price > ask.level.price => {
+                if received_timestamp(bid.exchange) <= received_timestamp(ask.exchange) {
+                    dropped_bids.push(bid.exchange);
+                } else {
+                    dropped_asks.push(ask.exchange);
+                }
+            }
+            _ => break,
+        }
+    }
+
+    (
+        bids.filter(|el| !dropped_bids.contains(&el.exchange)).take(depth).collect(),
+        asks.filter(|el| !dropped_asks.contains(&el.exchange)).take(depth).collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rust_decimal_macros::dec;
+
+    use data_models::book_condition::BookCondition;
+    use data_models::exchange_level::{Exchange, ExchangeLevel};
+    use data_models::exchange_summary::ExchangeSummary;
+    use data_models::level::{Decimal, Level};
+
+    use crate::api::configuration::CrossedBookPolicy;
+    use crate::implementation::crossed_book::resolve;
+
+    fn exchange_level(exchange: Exchange, price: Decimal, amount: Decimal) -> ExchangeLevel {
+        ExchangeLevel::new(exchange, Level::new(price, amount))
+    }
+
+    /// Binance was received first and its best bid crosses the best ask of Bitstamp.
+    fn crossed_book() -> (Vec<ExchangeLevel>, Vec<ExchangeLevel>, Vec<ExchangeSummary>) {
+        let bids = vec![
+            exchange_level(Exchange::Binance, dec!(10.2), dec!(1.0)),
+            exchange_level(Exchange::Binance, dec!(10.1), dec!(2.0)),
+            exchange_level(Exchange::Bitstamp, dec!(9.9), dec!(1.0)),
+        ];
+        let asks = vec![
+            exchange_level(Exchange::Bitstamp, dec!(10.0), dec!(1.5)),
+            exchange_level(Exchange::Bitstamp, dec!(10.3), dec!(1.0)),
+            exchange_level(Exchange::Binance, dec!(10.4), dec!(1.0)),
+        ];
+        let mut binance = ExchangeSummary::new(Exchange::Binance, None, None);
+        binance.received_timestamp = Some(1);
+        let mut bitstamp = ExchangeSummary::new(Exchange::Bitstamp, None, None);
+        bitstamp.received_timestamp = Some(2);
+
+        (bids, asks, vec![binance, bitstamp])
+    }
+
+    #[rstest]
+    #[case(
+        CrossedBookPolicy::PassThrough,
+        vec![exchange_level(Exchange::Binance, dec!(10.2), dec!(1.0)), exchange_level(Exchange::Binance, dec!(10.1), dec!(2.0))],
+        vec![exchange_level(Exchange::Bitstamp, dec!(10.0), dec!(1.5)), exchange_level(Exchange::Bitstamp, dec!(10.3), dec!(1.0))],
+    )]
+    #[case(
+        CrossedBookPolicy::TrimCrossed,
+        vec![exchange_level(Exchange::Binance, dec!(10.1), dec!(1.5)), exchange_level(Exchange::Bitstamp, dec!(9.9), dec!(1.0))],
+        vec![exchange_level(Exchange::Bitstamp, dec!(10.3), dec!(1.0)), exchange_level(Exchange::Binance, dec!(10.4), dec!(1.0))],
+    )]
+    #[case(
+        CrossedBookPolicy::DropStaler,
+        vec![exchange_level(Exchange::Bitstamp, dec!(9.9), dec!(1.0))],
+        vec![exchange_level(Exchange::Bitstamp, dec!(10.0), dec!(1.5)), exchange_level(Exchange::Bitstamp, dec!(10.3), dec!(1.0))],
+    )]
+    fn resolve_crossed_test(
+        #[case] policy: CrossedBookPolicy,
+        #[case] expected_bids: Vec<ExchangeLevel>,
+        #[case] expected_asks: Vec<ExchangeLevel>,
+    ) {
+        let (bids, asks, exchanges) = crossed_book();
+
+        let aggregated_orderbook = resolve(bids.iter().copied(), asks.iter().copied(), &exchanges, policy, 2);
+
+        assert_eq!(aggregated_orderbook.condition, BookCondition::Crossed);
+        assert_eq!(aggregated_orderbook.bids, expected_bids);
+        assert_eq!(aggregated_orderbook.asks, expected_asks);
+    }
+
+    #[test]
+    fn drop_staler_asks_test() {
+        let (bids, asks, mut exchanges) = crossed_book();
+        exchanges[1].received_timestamp = Some(0);
+
+        let aggregated_orderbook = resolve(bids.iter().copied(), asks.iter().copied(), &exchanges, CrossedBookPolicy::DropStaler, 10);
+
+        assert_eq!(aggregated_orderbook.asks, vec![exchange_level(Exchange::Binance, dec!(10.4), dec!(1.0))]);
+        assert_eq!(aggregated_orderbook.bids.len(), 3);
+    }
+
+    #[test]
+    fn locked_book_is_passed_through_test() {
+        let bids = [exchange_level(Exchange::Binance, dec!(10.0), dec!(1.0))];
+        let asks = [exchange_level(Exchange::Bitstamp, dec!(10.00), dec!(1.0))];
+
+        let aggregated_orderbook = resolve(bids.iter().copied(), asks.iter().copied(), &[], CrossedBookPolicy::TrimCrossed, 10);
+
+        assert_eq!(aggregated_orderbook.condition, BookCondition::Locked);
+        assert_eq!(aggregated_orderbook.bids.len(), 1);
+        assert_eq!(aggregated_orderbook.asks.len(), 1);
+    }
+}
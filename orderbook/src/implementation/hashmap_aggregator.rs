@@ -1,67 +1,144 @@
 use data_models::{aggregated_orderbook::AggregatedOrderbook, exchange_orderbook::OrderbookSnapshot};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use data_models::connection_event::ConnectionEvent;
 use data_models::exchange::Exchange;
 use data_models::exchange_level::ExchangeLevel;
+use data_models::exchange_summary::ExchangeSummary;
 use data_models::levels::Levels;
-use crate::implementation::hashmap_aggregator::Order::{ASCENDING, DESCENDING};
+use crate::api::configuration::AggregatorConfig;
+use crate::implementation::connection_states::{self, ConnectionStates};
+use crate::implementation::crossed_book;
+use crate::implementation::hashmap_aggregator::Order::{Ascending, Descending};
 
 #[derive(Clone)]
 enum Order {
-    ASCENDING,
-    DESCENDING
+    Ascending,
+    Descending
 }
 
 pub struct HashMapAggregator {
-    orderbook_snapshots: HashMap<String, HashMap<Exchange, Levels>>
+    config: AggregatorConfig,
+    orderbook_snapshots: HashMap<String, HashMap<Exchange, OrderbookSnapshot>>,
+    stale_exchanges: HashMap<String, BTreeSet<Exchange>>,
+    connection_states: HashMap<String, ConnectionStates>,
 }
 
 impl crate::api::aggregator::OrderbookSnapshotAggregator for HashMapAggregator {
     /// Constructs a new [HashMapAggregator].
-    fn new() -> Self {
+    fn new(config: AggregatorConfig) -> Self {
         HashMapAggregator {
-            orderbook_snapshots: HashMap::<String, HashMap<Exchange, Levels>>::new()
+            config,
+            orderbook_snapshots: HashMap::<String, HashMap<Exchange, OrderbookSnapshot>>::new(),
+            stale_exchanges: HashMap::<String, BTreeSet<Exchange>>::new(),
+            connection_states: HashMap::<String, ConnectionStates>::new(),
         }
     }
 
-    fn on_orderbook_snapshot(&mut self, os: OrderbookSnapshot) -> AggregatedOrderbook {
-        let exchange_levels_map = match self.orderbook_snapshots.get_mut(&os.symbol) {
-            None => {
-                self.orderbook_snapshots.insert(os.symbol.clone(), HashMap::from([(os.exchange, os.levels)]));
-                self.orderbook_snapshots.get_mut(&os.symbol).unwrap()
-            },
-            Some(hash_map) => {
-                hash_map.insert(os.exchange, Levels::new(os.levels.bids, os.levels.asks));
-                self.orderbook_snapshots.get_mut(&os.symbol).unwrap()
+    fn on_orderbook_snapshot(&mut self, os: OrderbookSnapshot) -> Option<AggregatedOrderbook> {
+        let exchange_snapshots = self.orderbook_snapshots.entry(os.symbol.clone()).or_default();
+
+        if let Some(previous) = exchange_snapshots.get(&os.exchange) {
+            if !os.supersedes(previous) {
+                println!("Rejected out of order snapshot from `{}` : `{}`", os.exchange, os.symbol);
+                return None;
             }
+        }
+        let symbol = os.symbol.clone();
+        let now = os.received_timestamp;
+        exchange_snapshots.insert(os.exchange, os);
+
+        Some(self.aggregate(&symbol, now))
+    }
+
+    fn on_timer(&mut self, now: u64) -> Vec<AggregatedOrderbook> {
+        let symbols: Vec<String> = self.orderbook_snapshots
+            .keys()
+            .filter(|symbol| self.stale_exchanges.get(*symbol) != Some(&self.find_stale_exchanges(symbol, now)))
+            .cloned()
+            .collect();
+
+        symbols.iter().map(|symbol| self.aggregate(symbol, now)).collect()
+    }
+
+    fn on_connection_event(&mut self, event: &ConnectionEvent) -> Option<AggregatedOrderbook> {
+        self.connection_states.entry(event.symbol.clone()).or_default().insert(event.exchange, event.state);
+
+        let exchange_snapshots = self.orderbook_snapshots.entry(event.symbol.clone()).or_default();
+        if !event.state.is_subscribed() {
+            exchange_snapshots.remove(&event.exchange);
+        }
+
+        Some(self.aggregate(&event.symbol, event.timestamp))
+    }
+}
+
+impl HashMapAggregator {
+    /// Merges, sorts and truncates the levels of all exchanges of the given symbol that are not stale.
+    /// A crossed orderbook is resolved according to the configured [crate::api::configuration::CrossedBookPolicy].
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The symbol of the aggregated orderbook.
+    /// * `now` - The current time in microseconds since the unix epoch.
+    fn aggregate(&mut self, symbol: &str, now: u64) -> AggregatedOrderbook {
+        let stale_exchanges = self.find_stale_exchanges(symbol, now);
+        let exchange_snapshots = &self.orderbook_snapshots[symbol];
+
+        let mut merged_orderbook = aggregate_exchange_levels(exchange_snapshots
+            .iter()
+            .filter(|(exchange, _)| !stale_exchanges.contains(exchange))
+            .map(|(exchange, snapshot)| (exchange, &snapshot.levels)));
+        sort_aggregated_orderbook(&mut merged_orderbook);
+
+        let exchanges: Vec<ExchangeSummary> = exchange_snapshots
+            .values()
+            .map(|snapshot| ExchangeSummary::from_snapshot(snapshot).with_stale(stale_exchanges.contains(&snapshot.exchange)))
+            .collect();
+        let aggregated_orderbook = crossed_book::resolve(
+            merged_orderbook.bids.iter().copied(),
+            merged_orderbook.asks.iter().copied(),
+            &exchanges,
+            self.config.crossed_book_policy,
+            self.config.depth);
+
+        self.stale_exchanges.insert(symbol.to_string(), stale_exchanges);
+        let exchanges = match self.connection_states.get(symbol) {
+            Some(states) => connection_states::summarize(states, exchanges),
+            None => exchanges,
         };
+        aggregated_orderbook.with_symbol(symbol.to_string()).with_exchanges(exchanges)
+    }
 
-        let mut aggregated_orderbook = aggregate_exchange_levels(exchange_levels_map);
-        sort_aggregated_orderbook(&mut aggregated_orderbook);
-        aggregated_orderbook
+    fn find_stale_exchanges(&self, symbol: &str, now: u64) -> BTreeSet<Exchange> {
+        self.orderbook_snapshots[symbol]
+            .values()
+            .filter(|snapshot| self.config.is_stale(snapshot, now))
+            .map(|snapshot| snapshot.exchange)
+            .collect()
     }
 }
 
-fn aggregate_exchange_levels(exchange_levels: &HashMap<Exchange, Levels>) -> AggregatedOrderbook {
+fn aggregate_exchange_levels<'a>(exchange_levels: impl Iterator<Item=(&'a Exchange, &'a Levels)>) -> AggregatedOrderbook {
     let mut bids = Vec::<ExchangeLevel>::with_capacity(20);
     let mut asks = Vec::<ExchangeLevel>::with_capacity(20);
 
-    Vec::from_iter(exchange_levels.into_iter()).iter().for_each(|(exchange, levels)| {
-        bids.append(&mut levels.bids.iter().map(|level| ExchangeLevel::new((*exchange).clone(), level.clone())).collect::<Vec<ExchangeLevel>>());
-        asks.append(&mut levels.asks.iter().map(|level| ExchangeLevel::new((*exchange).clone(), level.clone())).collect::<Vec<ExchangeLevel>>());
+    exchange_levels.for_each(|(exchange, levels)| {
+        bids.extend(levels.bids.iter().map(|level| ExchangeLevel::new(*exchange, *level)));
+        asks.extend(levels.asks.iter().map(|level| ExchangeLevel::new(*exchange, *level)));
     });
 
     AggregatedOrderbook::new(bids, asks)
 }
 
 fn sort_aggregated_orderbook(aggregated_orderbook: &mut AggregatedOrderbook) {
-    sort_exchange_levels(&mut aggregated_orderbook.bids, DESCENDING, DESCENDING);
-    sort_exchange_levels(&mut aggregated_orderbook.asks, ASCENDING, DESCENDING);
+    sort_exchange_levels(&mut aggregated_orderbook.bids, Descending, Descending);
+    sort_exchange_levels(&mut aggregated_orderbook.asks, Ascending, Descending);
 }
 
-fn sort_exchange_levels(el: &mut Vec<ExchangeLevel>, price_order: Order, amount_order: Order) {
+fn sort_exchange_levels(el: &mut [ExchangeLevel], price_order: Order, amount_order: Order) {
     el.sort_by(|el1, el2| match (price_order.clone(), amount_order.clone()) {
-        (ASCENDING, DESCENDING) => el1.level.price.partial_cmp(&el2.level.price).unwrap().then(el1.level.amount.partial_cmp(&el2.level.amount).unwrap().reverse()),
-        (DESCENDING, DESCENDING) => el1.level.price.partial_cmp(&el2.level.price).unwrap().reverse().then(el1.level.amount.partial_cmp(&el2.level.amount).unwrap().reverse()),
+        (Ascending, Descending) => el1.level.price.cmp(&el2.level.price).then(el1.level.amount.cmp(&el2.level.amount).reverse()),
+        (Descending, Descending) => el1.level.price.cmp(&el2.level.price).reverse().then(el1.level.amount.cmp(&el2.level.amount).reverse()),
         _ => panic!("The provided sorting combination for price and amount is not supported")
     });
 }
@@ -69,38 +146,169 @@ fn sort_exchange_levels(el: &mut Vec<ExchangeLevel>, price_order: Order, amount_
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::time::Duration;
     use rstest::rstest;
+    use rust_decimal_macros::dec;
     use data_models::aggregated_orderbook::AggregatedOrderbook;
+    use data_models::connection_event::ConnectionEvent;
+    use data_models::connection_state::ConnectionState;
     use data_models::exchange_level::{Exchange, ExchangeLevel};
     use data_models::exchange_orderbook::OrderbookSnapshot;
     use data_models::level::Level;
     use data_models::levels::Levels;
     use crate::api::aggregator::OrderbookSnapshotAggregator;
+    use data_models::book_condition::BookCondition;
+    use crate::api::configuration::{AggregatorConfig, CrossedBookPolicy};
     use crate::implementation::hashmap_aggregator::{HashMapAggregator, Order, sort_exchange_levels, aggregate_exchange_levels, sort_aggregated_orderbook};
 
     #[test]
     fn on_orderbook_snapshot_test() {
-        let mut hashmap_aggregator = HashMapAggregator::new();
-        let aggregated_orderbook_1 = hashmap_aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Binance, "test-symbol".to_string(),  Levels::new(vec![Level::new(1.0, 100.0), Level::new(2.0, 110.0)], vec![Level::new(10.0, 200.0), Level::new(11.0, 200.0)])));
-        let aggregated_orderbook_2 = hashmap_aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Bitstamp, "test-symbol".to_string(), Levels::new(vec![Level::new(1.5, 110.0), Level::new(2.5, 110.0)], vec![Level::new(10.5, 210.0), Level::new(11.5, 210.0)])));
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(10));
+        let aggregated_orderbook_1 = hashmap_aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Binance, "test-symbol".to_string(),  Levels::new(vec![Level::new(dec!(1.0), dec!(100.0)), Level::new(dec!(2.0), dec!(110.0))], vec![Level::new(dec!(10.0), dec!(200.0)), Level::new(dec!(11.0), dec!(200.0))]))).unwrap();
+        let aggregated_orderbook_2 = hashmap_aggregator.on_orderbook_snapshot(OrderbookSnapshot::new(Exchange::Bitstamp, "test-symbol".to_string(), Levels::new(vec![Level::new(dec!(1.5), dec!(110.0)), Level::new(dec!(2.5), dec!(110.0))], vec![Level::new(dec!(10.5), dec!(210.0)), Level::new(dec!(11.5), dec!(210.0))]))).unwrap();
+
+        assert_eq!(aggregated_orderbook_1.bids.len(), 2);
+        assert_eq!(aggregated_orderbook_1.asks.len(), 2);
+        assert_eq!(aggregated_orderbook_2.bids.len(), 4);
+        assert_eq!(aggregated_orderbook_2.asks.len(), 4);
+        assert_eq!(aggregated_orderbook_2.best_bid(), Some(&ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.5), dec!(110.0)))));
+        assert_eq!(aggregated_orderbook_2.best_ask(), Some(&ExchangeLevel::new(Exchange::Binance, Level::new(dec!(10.0), dec!(200.0)))));
+    }
+
+    #[test]
+    fn keeps_separate_orderbook_per_symbol_test() {
+        let snapshot = |exchange, symbol: &str, price| OrderbookSnapshot::new(exchange, symbol.to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]));
+
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(10));
+        hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, "ethbtc", dec!(0.05)));
+        hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, "btcusdt", dec!(60000)));
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, "ethbtc", dec!(0.04))).unwrap();
+
+        assert_eq!(aggregated_orderbook.symbol, "ethbtc");
+        assert_eq!(aggregated_orderbook.bids.iter().map(|el| el.level.price).collect::<Vec<_>>(), vec![dec!(0.05), dec!(0.04)]);
+    }
+
+    #[test]
+    fn on_orderbook_snapshot_rejects_out_of_order_test() {
+        let snapshot = |exchange, sequence, price| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]))
+            .with_sequence(Some(sequence))
+            .with_exchange_timestamp(Some(sequence * 1000));
+
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(10));
+        assert!(hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, 2, dec!(1.0))).is_some());
+        // duplicate
+        assert!(hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, 2, dec!(2.0))).is_none());
+        // out of order
+        assert!(hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, 1, dec!(2.0))).is_none());
+        // sequences are tracked per exchange
+        assert!(hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, 1, dec!(0.5))).is_some());
+
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, 3, dec!(3.0))).unwrap();
+        assert_eq!(aggregated_orderbook.best_bid(), Some(&ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0)))));
+        assert_eq!(aggregated_orderbook.exchanges.len(), 2);
+        assert_eq!(aggregated_orderbook.exchanges[0].exchange, Exchange::Binance);
+        assert_eq!(aggregated_orderbook.exchanges[0].sequence, Some(3));
+        assert_eq!(aggregated_orderbook.exchanges[0].exchange_timestamp, Some(3000));
+        assert_eq!(aggregated_orderbook.exchanges[1].exchange, Exchange::Bitstamp);
+        assert_eq!(aggregated_orderbook.exchanges[1].best_bid, Some(Level::new(dec!(0.5), dec!(1.0))));
+    }
+
+    #[test]
+    fn on_orderbook_snapshot_truncates_to_depth_test() {
+        let levels = |start: u32| (start..start + 10).map(|i| Level::new(i.into(), dec!(1.0))).collect::<Vec<Level>>();
+        let snapshot = |exchange, bids: Vec<Level>, asks: Vec<Level>| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(bids, asks));
+
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(3));
+        hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, levels(0).into_iter().rev().collect(), levels(100)));
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, levels(5).into_iter().rev().collect(), levels(95))).unwrap();
+
+        let prices = |exchange_levels: &Vec<ExchangeLevel>| exchange_levels.iter().map(|el| el.level.price).collect::<Vec<_>>();
+        assert_eq!(prices(&aggregated_orderbook.bids), vec![dec!(14), dec!(13), dec!(12)]);
+        assert_eq!(prices(&aggregated_orderbook.asks), vec![dec!(95), dec!(96), dec!(97)]);
+        // the exchange summaries are not affected by the truncation
+        assert_eq!(aggregated_orderbook.exchanges.len(), 2);
+        assert_eq!(aggregated_orderbook.exchanges[0].best_bid, Some(Level::new(dec!(9), dec!(1.0))));
+    }
+
+    #[test]
+    fn evicts_stale_exchanges_test() {
+        let snapshot = |exchange, price, received_timestamp| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]))
+            .with_received_timestamp(received_timestamp);
+
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(10).with_staleness_timeout(Duration::from_secs(1)));
+        hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(2.0), 0));
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, dec!(1.0), 500_000)).unwrap();
+        assert_eq!(aggregated_orderbook.bids.len(), 2);
+        assert!(hashmap_aggregator.on_timer(1_000_000).is_empty());
+
+        // Bitstamp stopped publishing, its levels are dropped but it is still listed
+        let aggregated_orderbooks = hashmap_aggregator.on_timer(1_000_001);
+        assert_eq!(aggregated_orderbooks.len(), 1);
+        assert_eq!(aggregated_orderbooks[0].bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbooks[0].exchanges.iter().map(|es| (es.exchange, es.stale)).collect::<Vec<_>>(),
+                   vec![(Exchange::Binance, false), (Exchange::Bitstamp, true)]);
+        // the orderbook is only published when the stale exchanges change
+        assert!(hashmap_aggregator.on_timer(1_200_000).is_empty());
+
+        // Bitstamp resumes publishing
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(3.0), 1_300_000)).unwrap();
+        assert_eq!(aggregated_orderbook.best_bid(), Some(&ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(1.0)))));
+        assert!(aggregated_orderbook.exchanges.iter().all(|es| !es.stale));
+    }
+
+    #[test]
+    fn clears_disconnected_exchange_test() {
+        let snapshot = |exchange, price| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]));
+        let connection_event = |exchange, state| ConnectionEvent::new(exchange, "test-symbol".to_string(), state);
+
+        let mut aggregator = HashMapAggregator::new(AggregatorConfig::new(10));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, dec!(1.0)));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(2.0)));
+
+        // The levels of Bitstamp are cleared as soon as it disconnects, but it is still listed
+        let aggregated_orderbook = aggregator.on_connection_event(&connection_event(Exchange::Bitstamp, ConnectionState::Disconnected)).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbook.exchanges.iter().map(|es| (es.exchange, es.connection_state)).collect::<Vec<_>>(),
+                   vec![(Exchange::Binance, ConnectionState::Subscribed), (Exchange::Bitstamp, ConnectionState::Disconnected)]);
+
+        let aggregated_orderbook = aggregator.on_connection_event(&connection_event(Exchange::Bitstamp, ConnectionState::Subscribed)).unwrap();
+        assert_eq!(aggregated_orderbook.bids.len(), 1);
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(3.0))).unwrap();
+        assert_eq!(aggregated_orderbook.best_bid(), Some(&ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(1.0)))));
+        assert!(aggregated_orderbook.exchanges.iter().all(|es| es.connection_state == ConnectionState::Subscribed));
+    }
+
+    #[test]
+    fn resolves_crossed_orderbook_test() {
+        let snapshot = |exchange, bid, ask, received_timestamp| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(bid, dec!(1.0))], vec![Level::new(ask, dec!(1.0))]))
+            .with_received_timestamp(received_timestamp);
+
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(10).with_crossed_book_policy(CrossedBookPolicy::DropStaler));
+        hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, dec!(3.0), dec!(4.0), 1));
+        let aggregated_orderbook = hashmap_aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(1.0), dec!(2.0), 2)).unwrap();
+
+        assert_eq!(aggregated_orderbook.condition, BookCondition::Crossed);
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(1.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbook.asks.len(), 2);
+        assert_eq!(aggregated_orderbook.spread(), Some(dec!(1.0)));
     }
 
     #[rstest]
     #[case(
         HashMap::from([
-        (Exchange::Bitstamp, Levels::new(vec![Level::new(2.0, 102.0)], vec![Level::new(8.0, 108.0)])),
-        (Exchange::Binance, Levels::new(vec![Level::new(1.0, 101.0)], vec![Level::new(7.0, 107.0)]))
+        (Exchange::Bitstamp, Levels::new(vec![Level::new(dec!(2.0), dec!(102.0))], vec![Level::new(dec!(8.0), dec!(108.0))])),
+        (Exchange::Binance, Levels::new(vec![Level::new(dec!(1.0), dec!(101.0))], vec![Level::new(dec!(7.0), dec!(107.0))]))
         ]),
         AggregatedOrderbook::new(
-            vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(2.0, 102.0))],
-            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(8.0, 108.0)), ExchangeLevel::new(Exchange::Binance, Level::new(7.0, 107.0))]
+            vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(102.0)))],
+            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(8.0), dec!(108.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(7.0), dec!(107.0)))]
         )
     )]
     fn aggregate_exchange_levels_test(
         #[case] exchange_levels: HashMap<Exchange, Levels>,
         #[case] expected_aggregated_orderbook: AggregatedOrderbook,
     ) {
-        let aggregated_orderbook = aggregate_exchange_levels(&exchange_levels);
+        let aggregated_orderbook = aggregate_exchange_levels(exchange_levels.iter());
 
         assert!(aggregated_orderbook.asks.iter().all(|it| expected_aggregated_orderbook.asks.contains(it)));
         assert!(aggregated_orderbook.bids.iter().all(|it| expected_aggregated_orderbook.bids.contains(it)));
@@ -108,40 +316,40 @@ mod tests {
 
     #[rstest]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0))],
-        Order::ASCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0)))],
+        Order::Ascending,
+        Order::Descending
     )]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0))],
-        Order::ASCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0)))],
+        Order::Ascending,
+        Order::Descending
     )]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0)), ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0))],
-        Order::ASCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0)))],
+        Order::Ascending,
+        Order::Descending
     )]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0))],
-        Order::DESCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0)))],
+        Order::Descending,
+        Order::Descending
     )]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 101.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0))],
-        Order::DESCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(101.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0)))],
+        Order::Descending,
+        Order::Descending
     )]
     #[case(
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0)), ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 101.0))],
-        vec![ExchangeLevel::new(Exchange::Binance, Level::new(2.0, 101.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 100.0))],
-        Order::DESCENDING,
-        Order::DESCENDING
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(101.0)))],
+        vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(101.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(100.0)))],
+        Order::Descending,
+        Order::Descending
     )]
     fn sort_exchange_levels_test(
         #[case] mut exchange_levels: Vec<ExchangeLevel>,
@@ -157,12 +365,12 @@ mod tests {
     #[rstest]
     #[case(
         AggregatedOrderbook::new(
-            vec![ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 10.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(3.0, 30.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(2.0, 20.0))],
-            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(10.0, 110.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(12.0, 130.0)), ExchangeLevel::new(Exchange::Binance, Level::new(11.0, 120.0))],
+            vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(10.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(30.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(20.0)))],
+            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(10.0), dec!(110.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(12.0), dec!(130.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(11.0), dec!(120.0)))],
         ),
         AggregatedOrderbook::new(
-            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(3.0, 30.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(2.0, 20.0)), ExchangeLevel::new(Exchange::Binance, Level::new(1.0, 10.0))],
-            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(10.0, 110.0)), ExchangeLevel::new(Exchange::Binance, Level::new(11.0, 120.0)), ExchangeLevel::new(Exchange::Bitstamp, Level::new(12.0, 130.0))],
+            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(30.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(2.0), dec!(20.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(10.0)))],
+            vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(10.0), dec!(110.0))), ExchangeLevel::new(Exchange::Binance, Level::new(dec!(11.0), dec!(120.0))), ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(12.0), dec!(130.0)))],
         )
     )]
     fn sort_orderbook_test(
@@ -1 +1,5 @@
-pub mod hashmap_aggregator;
\ No newline at end of file
+pub mod connection_states;
+pub mod consolidated_aggregator;
+pub mod crossed_book;
+pub mod hashmap_aggregator;
+pub mod sorted_map_aggregator;
\ No newline at end of file
@@ -0,0 +1,378 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use data_models::aggregated_orderbook::AggregatedOrderbook;
+use data_models::connection_event::ConnectionEvent;
+use data_models::exchange::Exchange;
+use data_models::exchange_level::ExchangeLevel;
+use data_models::exchange_orderbook::OrderbookSnapshot;
+use data_models::exchange_summary::ExchangeSummary;
+use data_models::level::{Decimal, Level};
+
+use crate::api::aggregator::OrderbookSnapshotAggregator;
+use crate::api::configuration::AggregatorConfig;
+use crate::implementation::connection_states::{self, ConnectionStates};
+use crate::implementation::crossed_book;
+
+/// Bids are ordered by descending price and then by descending amount.
+type BidKey = (Reverse<Decimal>, Reverse<Decimal>, Exchange);
+/// Asks are ordered by ascending price and then by descending amount.
+type AskKey = (Decimal, Reverse<Decimal>, Exchange);
+
+/// The sorted bids and asks of all the exchanges of a single symbol, with the number of
+/// identical levels an exchange published for each key, so that they are not merged.
+/// The levels of stale exchanges are removed from the maps.
+#[derive(Default)]
+struct SortedOrderbook {
+    bids: BTreeMap<BidKey, usize>,
+    asks: BTreeMap<AskKey, usize>,
+    snapshots: HashMap<Exchange, OrderbookSnapshot>,
+    stale_exchanges: BTreeSet<Exchange>,
+    connection_states: ConnectionStates,
+}
+
+/// An aggregator that keeps each side of the aggregated orderbook in an ordered map.
+///
+/// On every snapshot the previous and the new levels of the publishing exchange are walked
+/// side by side, and only the levels that changed are removed from and inserted into the maps,
+/// without collecting or sorting the levels of the exchange. The cost of an update is
+/// proportional to the number of changed levels rather than to the depth of the whole book.
+/// The aggregated orderbook is then read from the front of each map, up to the configured depth.
+pub struct SortedMapAggregator {
+    config: AggregatorConfig,
+    orderbooks: HashMap<String, SortedOrderbook>,
+}
+
+impl OrderbookSnapshotAggregator for SortedMapAggregator {
+    /// Constructs a new [SortedMapAggregator].
+    fn new(config: AggregatorConfig) -> Self {
+        SortedMapAggregator {
+            config,
+            orderbooks: HashMap::new(),
+        }
+    }
+
+    fn on_orderbook_snapshot(&mut self, os: OrderbookSnapshot) -> Option<AggregatedOrderbook> {
+        let orderbook = self.orderbooks.entry(os.symbol.clone()).or_default();
+        let symbol = os.symbol.clone();
+
+        let previous = match orderbook.snapshots.get(&os.exchange) {
+            Some(previous) if !os.supersedes(previous) => {
+                println!("Rejected out of order snapshot from `{}` : `{}`", os.exchange, os.symbol);
+                return None;
+            }
+            // The levels of a stale exchange have already been removed
+            Some(_) if orderbook.stale_exchanges.remove(&os.exchange) => None,
+            previous => previous.map(|previous| &previous.levels),
+        };
+        let (previous_bids, previous_asks) = previous.map_or((&[][..], &[][..]), |levels| (&levels.bids[..], &levels.asks[..]));
+
+        let exchange = os.exchange;
+        apply_difference(&mut orderbook.bids, previous_bids, &os.levels.bids, |level| bid_key(exchange, level));
+        apply_difference(&mut orderbook.asks, previous_asks, &os.levels.asks, |level| ask_key(exchange, level));
+        let now = os.received_timestamp;
+        orderbook.snapshots.insert(os.exchange, os);
+
+        orderbook.evict_stale_exchanges(&self.config, now);
+        Some(orderbook.aggregate(&self.config).with_symbol(symbol))
+    }
+
+    fn on_timer(&mut self, now: u64) -> Vec<AggregatedOrderbook> {
+        self.orderbooks
+            .iter_mut()
+            .filter_map(|(symbol, orderbook)| orderbook
+                .evict_stale_exchanges(&self.config, now)
+                .then(|| orderbook.aggregate(&self.config).with_symbol(symbol.clone())))
+            .collect()
+    }
+
+    fn on_connection_event(&mut self, event: &ConnectionEvent) -> Option<AggregatedOrderbook> {
+        let orderbook = self.orderbooks.entry(event.symbol.clone()).or_default();
+        orderbook.connection_states.insert(event.exchange, event.state);
+
+        if !event.state.is_subscribed() {
+            orderbook.remove_exchange(event.exchange);
+        }
+
+        Some(orderbook.aggregate(&self.config).with_symbol(event.symbol.clone()))
+    }
+}
+
+impl SortedOrderbook {
+    /// Removes the levels of the exchanges that became stale from the sets.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The [AggregatorConfig] with the staleness timeout.
+    /// * `now` - The current time in microseconds since the unix epoch.
+    ///
+    /// Returns true if any exchange became stale.
+    fn evict_stale_exchanges(&mut self, config: &AggregatorConfig, now: u64) -> bool {
+        let mut evicted = false;
+
+        for snapshot in self.snapshots.values() {
+            if self.stale_exchanges.contains(&snapshot.exchange) || !config.is_stale(snapshot, now) {
+                continue;
+            }
+            for level in snapshot.levels.bids.iter() {
+                remove(&mut self.bids, bid_key(snapshot.exchange, level));
+            }
+            for level in snapshot.levels.asks.iter() {
+                remove(&mut self.asks, ask_key(snapshot.exchange, level));
+            }
+            self.stale_exchanges.insert(snapshot.exchange);
+            evicted = true;
+        }
+
+        evicted
+    }
+
+    /// Removes the snapshot and the levels of the given exchange.
+    fn remove_exchange(&mut self, exchange: Exchange) {
+        let Some(snapshot) = self.snapshots.remove(&exchange) else {
+            return;
+        };
+        // The levels of a stale exchange have already been removed
+        if self.stale_exchanges.remove(&exchange) {
+            return;
+        }
+        for level in snapshot.levels.bids.iter() {
+            remove(&mut self.bids, bid_key(exchange, level));
+        }
+        for level in snapshot.levels.asks.iter() {
+            remove(&mut self.asks, ask_key(exchange, level));
+        }
+    }
+
+    /// Reads the best `depth` levels per side from the front of the maps.
+    /// A crossed orderbook is resolved according to the configured [crate::api::configuration::CrossedBookPolicy].
+    fn aggregate(&self, config: &AggregatorConfig) -> AggregatedOrderbook {
+        let bids = self.bids
+            .iter()
+            .flat_map(|((Reverse(price), Reverse(amount), exchange), count)| std::iter::repeat_n(ExchangeLevel::new(*exchange, Level::new(*price, *amount)), *count));
+        let asks = self.asks
+            .iter()
+            .flat_map(|((price, Reverse(amount), exchange), count)| std::iter::repeat_n(ExchangeLevel::new(*exchange, Level::new(*price, *amount)), *count));
+
+        let exchanges: Vec<ExchangeSummary> = self.snapshots
+            .values()
+            .map(|snapshot| ExchangeSummary::from_snapshot(snapshot).with_stale(self.stale_exchanges.contains(&snapshot.exchange)))
+            .collect();
+        crossed_book::resolve(bids, asks, &exchanges, config.crossed_book_policy, config.depth)
+            .with_exchanges(connection_states::summarize(&self.connection_states, exchanges))
+    }
+}
+
+/// Walks the previous and the current levels of an exchange side by side, removing from the map
+/// the levels that are only in `previous` and inserting the levels that are only in `current`,
+/// leaving the levels that did not change untouched.
+///
+/// The levels are published sorted, so a level that did not change is matched without a lookup.
+/// Levels that are out of order are removed and inserted again, which keeps the map correct.
+///
+/// # Arguments
+///
+/// * `map` - The ordered map of one side of the orderbook.
+/// * `previous` - The levels of the previous snapshot of an exchange.
+/// * `current` - The levels of the current snapshot of the same exchange.
+/// * `key` - Maps a level of the exchange to its key in the map.
+fn apply_difference<K: Ord>(map: &mut BTreeMap<K, usize>, previous: &[Level], current: &[Level], key: impl Fn(&Level) -> K) {
+    let mut previous = previous.iter().map(&key).peekable();
+    let mut current = current.iter().map(&key).peekable();
+    loop {
+        match (previous.peek(), current.peek()) {
+            (Some(p), Some(c)) if p == c => {
+                previous.next();
+                current.next();
+            }
+            (Some(p), Some(c)) if p < c => remove(map, previous.next().unwrap()),
+            (Some(_), None) => remove(map, previous.next().unwrap()),
+            (_, Some(_)) => *map.entry(current.next().unwrap()).or_default() += 1,
+            (None, None) => break,
+        }
+    }
+}
+
+/// Removes one occurrence of the given key from the map.
+fn remove<K: Ord>(map: &mut BTreeMap<K, usize>, key: K) {
+    if let Some(count) = map.get_mut(&key) {
+        *count -= 1;
+        if *count == 0 {
+            map.remove(&key);
+        }
+    }
+}
+
+#[inline]
+fn bid_key(exchange: Exchange, level: &Level) -> BidKey {
+    (Reverse(level.price), Reverse(level.amount), exchange)
+}
+
+#[inline]
+fn ask_key(exchange: Exchange, level: &Level) -> AskKey {
+    (level.price, Reverse(level.amount), exchange)
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::connection_event::ConnectionEvent;
+    use data_models::connection_state::ConnectionState;
+    use data_models::exchange_level::{Exchange, ExchangeLevel};
+    use data_models::exchange_orderbook::OrderbookSnapshot;
+    use data_models::level::{Decimal, Level};
+    use data_models::levels::Levels;
+
+    use crate::api::aggregator::OrderbookSnapshotAggregator;
+    use crate::api::configuration::AggregatorConfig;
+    use crate::implementation::hashmap_aggregator::HashMapAggregator;
+    use crate::implementation::sorted_map_aggregator::{SortedMapAggregator, apply_difference};
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    fn snapshot(exchange: Exchange, bids: Vec<Level>, asks: Vec<Level>) -> OrderbookSnapshot {
+        OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(bids, asks))
+    }
+
+    #[test]
+    fn on_orderbook_snapshot_test() {
+        let mut aggregator = SortedMapAggregator::new(AggregatorConfig::new(3));
+
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance,
+            vec![Level::new(dec!(3.0), dec!(1.0)), Level::new(dec!(2.0), dec!(1.0))],
+            vec![Level::new(dec!(4.0), dec!(1.0)), Level::new(dec!(5.0), dec!(1.0))]));
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp,
+            vec![Level::new(dec!(3.0), dec!(2.0)), Level::new(dec!(1.0), dec!(2.0))],
+            vec![Level::new(dec!(4.5), dec!(2.0))])).unwrap();
+
+        assert_eq!(aggregated_orderbook.bids, vec![
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.0), dec!(1.0))),
+        ]);
+        assert_eq!(aggregated_orderbook.asks, vec![
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(4.0), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(4.5), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(5.0), dec!(1.0))),
+        ]);
+
+        // The previous levels of the exchange are replaced
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance,
+            vec![Level::new(dec!(2.5), dec!(1.0))],
+            vec![Level::new(dec!(6.0), dec!(1.0))])).unwrap();
+
+        assert_eq!(aggregated_orderbook.bids, vec![
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(2.5), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(1.0), dec!(2.0))),
+        ]);
+        assert_eq!(aggregated_orderbook.asks, vec![
+            ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(4.5), dec!(2.0))),
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(6.0), dec!(1.0))),
+        ]);
+        assert_eq!(aggregated_orderbook.exchanges.len(), 2);
+    }
+
+    #[test]
+    fn on_orderbook_snapshot_rejects_out_of_order_test() {
+        let mut aggregator = SortedMapAggregator::new(AggregatorConfig::new(3));
+
+        assert!(aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, vec![Level::new(dec!(3.0), dec!(1.0))], vec![]).with_sequence(Some(2))).is_some());
+        assert!(aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, vec![Level::new(dec!(4.0), dec!(1.0))], vec![]).with_sequence(Some(1))).is_none());
+
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, vec![], vec![])).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0)))]);
+    }
+
+    #[test]
+    fn matches_hashmap_aggregator_test() {
+        let levels = |exchange: Exchange, start: i64, step: i64| (0..50)
+            .map(|i| Level::new(Decimal::from(start + step * i), Decimal::from(exchange as i64 * 100 + i % 7)))
+            .collect::<Vec<Level>>();
+
+        let mut sorted_map_aggregator = SortedMapAggregator::new(AggregatorConfig::new(25));
+        let mut hashmap_aggregator = HashMapAggregator::new(AggregatorConfig::new(25));
+
+        for (round, exchange) in [Exchange::Binance, Exchange::Bitstamp, Exchange::Binance, Exchange::Bitstamp].into_iter().enumerate() {
+            let offset = round as i64 * 3 + exchange as i64;
+            let os = snapshot(exchange, levels(exchange, 1000 + offset, -2), levels(exchange, 1100 + offset, 2));
+            let expected = hashmap_aggregator.on_orderbook_snapshot(os.clone()).unwrap();
+            let actual = sorted_map_aggregator.on_orderbook_snapshot(os).unwrap();
+
+            assert_eq!(actual.bids, expected.bids);
+            assert_eq!(actual.asks, expected.asks);
+            assert_eq!(actual.exchanges, expected.exchanges);
+        }
+    }
+
+    #[test]
+    fn evicts_stale_exchanges_test() {
+        let snapshot = |exchange, price, received_timestamp| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]))
+            .with_received_timestamp(received_timestamp);
+
+        let mut aggregator = SortedMapAggregator::new(AggregatorConfig::new(10).with_staleness_timeout(Duration::from_secs(1)));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(2.0), 0));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, dec!(1.0), 500_000));
+        assert!(aggregator.on_timer(1_000_000).is_empty());
+
+        let aggregated_orderbooks = aggregator.on_timer(1_000_001);
+        assert_eq!(aggregated_orderbooks.len(), 1);
+        assert_eq!(aggregated_orderbooks[0].bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbooks[0].exchanges.iter().map(|es| (es.exchange, es.stale)).collect::<Vec<_>>(),
+                   vec![(Exchange::Binance, false), (Exchange::Bitstamp, true)]);
+        assert!(aggregator.on_timer(1_200_000).is_empty());
+
+        // Snapshots also evict the exchanges that became stale since the last timer
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(3.0), 1_600_000)).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbook.exchanges.iter().map(|es| (es.exchange, es.stale)).collect::<Vec<_>>(),
+                   vec![(Exchange::Binance, true), (Exchange::Bitstamp, false)]);
+    }
+
+    #[test]
+    fn clears_disconnected_exchange_test() {
+        let snapshot = |exchange, price| OrderbookSnapshot::new(exchange, "test-symbol".to_string(), Levels::new(vec![Level::new(price, dec!(1.0))], vec![]));
+        let connection_event = |exchange, state| ConnectionEvent::new(exchange, "test-symbol".to_string(), state);
+
+        let mut aggregator = SortedMapAggregator::new(AggregatorConfig::new(10));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance, dec!(1.0)));
+        aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(2.0)));
+
+        // The levels of Bitstamp are cleared as soon as it disconnects, but it is still listed
+        let aggregated_orderbook = aggregator.on_connection_event(&connection_event(Exchange::Bitstamp, ConnectionState::Disconnected)).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(1.0), dec!(1.0)))]);
+        assert_eq!(aggregated_orderbook.exchanges.iter().map(|es| (es.exchange, es.connection_state)).collect::<Vec<_>>(),
+                   vec![(Exchange::Binance, ConnectionState::Subscribed), (Exchange::Bitstamp, ConnectionState::Disconnected)]);
+
+        let aggregated_orderbook = aggregator.on_connection_event(&connection_event(Exchange::Bitstamp, ConnectionState::Subscribed)).unwrap();
+        assert_eq!(aggregated_orderbook.bids.len(), 1);
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Bitstamp, dec!(3.0))).unwrap();
+        assert_eq!(aggregated_orderbook.best_bid(), Some(&ExchangeLevel::new(Exchange::Bitstamp, Level::new(dec!(3.0), dec!(1.0)))));
+        assert!(aggregated_orderbook.exchanges.iter().all(|es| es.connection_state == ConnectionState::Subscribed));
+    }
+
+    #[test]
+    fn apply_difference_test() {
+        let levels = |prices: &[i64]| prices.iter().map(|price| Level::new(Decimal::from(*price), dec!(1.0))).collect::<Vec<Level>>();
+        let mut map = BTreeMap::from([(1, 1), (2, 1), (3, 1), (7, 1), (9, 2)]);
+
+        // The previous levels are out of order
+        apply_difference(&mut map, &levels(&[3, 1, 2, 9]), &levels(&[2, 3, 4, 5, 9]), |level| level.price.try_into().unwrap());
+
+        assert_eq!(map, BTreeMap::from([(2, 1), (3, 1), (4, 1), (5, 1), (7, 1), (9, 2)]));
+    }
+
+    #[test]
+    fn keeps_identical_levels_test() {
+        let mut aggregator = SortedMapAggregator::new(AggregatorConfig::new(10));
+
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance,
+            vec![Level::new(dec!(3.0), dec!(1.0)), Level::new(dec!(3.0), dec!(1.0))], vec![])).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0))); 2]);
+
+        let aggregated_orderbook = aggregator.on_orderbook_snapshot(snapshot(Exchange::Binance,
+            vec![Level::new(dec!(3.0), dec!(1.0))], vec![])).unwrap();
+        assert_eq!(aggregated_orderbook.bids, vec![ExchangeLevel::new(Exchange::Binance, Level::new(dec!(3.0), dec!(1.0)))]);
+    }
+}
@@ -0,0 +1,478 @@
+//! The configuration of the server, read from a TOML file, e.g. `server/config.toml`.
+//! Every value has a default, so the file only has to declare the values that differ from it.
+//! The command line arguments override the values of the file, see [ServerConfig::validate]
+//! for the checks that are run before anything is started.
+
+use std::fmt;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Deserialize;
+use url::Url;
+
+use data_models::exchange::Exchange;
+use data_models::instrument::Instrument;
+use data_models::instrument_registry::InstrumentRegistry;
+use exchange_client::api::configuration::{DepthStream, ExchangeClientConfig};
+use exchange_client::api::reconnect_policy::ReconnectPolicy;
+use orderbook::api::configuration::{AggregatorConfig, CrossedBookPolicy};
+use orderbook::api::provider::AggregatorType;
+
+/// The depths the Binance partial book depth stream is published with.
+const BINANCE_PARTIAL_DEPTHS: [usize; 3] = [5, 10, 20];
+/// The number of levels a client subscribes to if its depth is not configured.
+const DEFAULT_EXCHANGE_DEPTH: usize = 10;
+
+/// The error returned if the configuration cannot be read or is invalid.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file could not be read.
+    Read(String, io::Error),
+    /// The configuration file is not valid TOML or has unknown or mistyped keys.
+    Parse(String, toml::de::Error),
+    /// A value of the configuration is invalid.
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, error) => write!(f, "Could not read the config file `{}` : `{}`", path, error),
+            ConfigError::Parse(path, error) => write!(f, "Could not parse the config file `{}` : {}", path, error),
+            ConfigError::Invalid(reason) => write!(f, "Invalid config : {}", reason),
+        }
+    }
+}
+
+/// The configuration file of the server.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// The address the gRPC server listens on.
+    pub address: String,
+    /// The canonical symbols of the instruments, which must be registered in the [InstrumentRegistry].
+    pub symbols: Vec<String>,
+    pub aggregator: AggregatorSection,
+    /// The reconnect policy of every exchange client.
+    pub reconnect: ReconnectSection,
+    /// The exchanges a client is started for, per symbol.
+    pub exchanges: Vec<ExchangeSection>,
+}
+
+/// The `[aggregator]` section of the configuration file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AggregatorSection {
+    /// The [AggregatorType], e.g. `hashmap`.
+    #[serde(rename = "type")]
+    pub aggregator_type: String,
+    /// The maximum number of merged levels per side of the aggregated orderbook.
+    pub depth: usize,
+    /// The milliseconds after which an exchange that stopped publishing is stale, 0 disables it.
+    pub staleness_timeout_ms: u64,
+    /// The [CrossedBookPolicy], e.g. `passthrough`.
+    pub crossed_book_policy: String,
+}
+
+/// The `[reconnect]` section of the configuration file, see [ReconnectPolicy].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReconnectSection {
+    pub initial_interval_ms: u64,
+    pub max_interval_ms: u64,
+    pub multiplier: f64,
+    pub jitter: f64,
+    pub max_failures: u32,
+    pub circuit_open_interval_ms: u64,
+    pub min_connected_time_ms: u64,
+}
+
+/// An `[[exchanges]]` entry of the configuration file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExchangeSection {
+    /// The [Exchange], e.g. `Binance`.
+    pub name: String,
+    /// The base url of the WebSocket api of the exchange.
+    pub url: String,
+    /// The number of levels the client subscribes to. Defaults to 10, or to the depth of the
+    /// aggregator for the Binance diff depth stream.
+    #[serde(default)]
+    pub depth: Option<usize>,
+    /// The [DepthStream], e.g. `partial`.
+    #[serde(default)]
+    pub depth_stream: Option<String>,
+    /// The base url of the REST api of the exchange.
+    #[serde(default)]
+    pub rest_url: Option<String>,
+}
+
+impl Default for ServerConfig {
+    /// Listens on `[::1]:50051` and starts a Bitstamp and a Binance client per symbol.
+    fn default() -> Self {
+        ServerConfig {
+            address: String::from("[::1]:50051"),
+            symbols: vec![],
+            aggregator: AggregatorSection::default(),
+            reconnect: ReconnectSection::default(),
+            exchanges: vec![
+                ExchangeSection::new(Exchange::Bitstamp, "wss://ws.bitstamp.net"),
+                ExchangeSection::new(Exchange::Binance, "wss://stream.binance.com:9443/ws"),
+            ],
+        }
+    }
+}
+
+impl Default for AggregatorSection {
+    fn default() -> Self {
+        AggregatorSection {
+            aggregator_type: AggregatorType::HashMapOrderbookAggegator.to_string(),
+            depth: 10,
+            staleness_timeout_ms: 30_000,
+            crossed_book_policy: CrossedBookPolicy::PassThrough.to_string(),
+        }
+    }
+}
+
+impl Default for ReconnectSection {
+    fn default() -> Self {
+        let policy = ReconnectPolicy::default();
+        ReconnectSection {
+            initial_interval_ms: policy.initial_interval.as_millis() as u64,
+            max_interval_ms: policy.max_interval.as_millis() as u64,
+            multiplier: policy.multiplier,
+            jitter: policy.jitter,
+            max_failures: policy.max_failures,
+            circuit_open_interval_ms: policy.circuit_open_interval.as_millis() as u64,
+            min_connected_time_ms: policy.min_connected_time.as_millis() as u64,
+        }
+    }
+}
+
+impl ExchangeSection {
+    fn new(exchange: Exchange, url: &str) -> Self {
+        ExchangeSection {
+            name: exchange.to_string(),
+            url: url.to_string(),
+            depth: None,
+            depth_stream: None,
+            rest_url: None,
+        }
+    }
+}
+
+/// The validated configuration the server is started with.
+pub struct Settings {
+    pub address: String,
+    pub instruments: Vec<Instrument>,
+    pub aggregator_type: AggregatorType,
+    pub aggregator_config: AggregatorConfig,
+    pub reconnect_policy: ReconnectPolicy,
+    pub exchanges: Vec<ExchangeSettings>,
+}
+
+/// The validated configuration of the clients of an exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeSettings {
+    pub exchange: Exchange,
+    pub url: String,
+    pub depth: usize,
+    pub depth_stream: DepthStream,
+    pub rest_url: Option<String>,
+}
+
+impl ExchangeSettings {
+    /// Builds the [ExchangeClientConfig] of the client of the given instrument.
+    ///
+    /// # Arguments
+    ///
+    /// * `instrument` - The [Instrument] the client subscribes to.
+    /// * `reconnect_policy` - The [ReconnectPolicy] of the client.
+    pub fn client_config(&self, instrument: &Instrument, reconnect_policy: &ReconnectPolicy) -> ExchangeClientConfig {
+        let client_config = ExchangeClientConfig::new(self.url.clone(), self.depth, instrument.clone())
+            .with_depth_stream(self.depth_stream)
+            .with_reconnect_policy(reconnect_policy.clone());
+        match &self.rest_url {
+            Some(rest_url) => client_config.with_rest_url(rest_url.clone()),
+            None => client_config,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Reads the configuration file, or returns the default configuration if no file is given.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the TOML configuration file.
+    pub fn load(path: Option<&str>) -> Result<ServerConfig, ConfigError> {
+        match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path).map_err(|error| ConfigError::Read(path.to_string(), error))?;
+                ServerConfig::parse(path, &content)
+            }
+            None => Ok(ServerConfig::default()),
+        }
+    }
+
+    /// Parses the content of a configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file, used in the error.
+    /// * `content` - The TOML content of the file.
+    pub fn parse(path: &str, content: &str) -> Result<ServerConfig, ConfigError> {
+        toml::from_str(content).map_err(|error| ConfigError::Parse(path.to_string(), error))
+    }
+
+    /// Validates the configuration. Returns [ConfigError::Invalid] with the first invalid value.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - The [InstrumentRegistry] the symbols are looked up in.
+    pub fn validate(&self, registry: &InstrumentRegistry) -> Result<Settings, ConfigError> {
+        let invalid = ConfigError::Invalid;
+
+        if self.address.to_socket_addrs().map(|mut addrs| addrs.next().is_none()).unwrap_or(true) {
+            return Err(invalid(format!("The address `{}` is not a valid socket address", self.address)));
+        }
+
+        if self.symbols.is_empty() {
+            return Err(invalid("No symbols are configured".to_string()));
+        }
+        let mut instruments: Vec<Instrument> = Vec::with_capacity(self.symbols.len());
+        for symbol in self.symbols.iter() {
+            match registry.get(symbol) {
+                Some(instrument) if !instruments.contains(instrument) => instruments.push(instrument.clone()),
+                Some(_) => continue,
+                None => return Err(invalid(format!("Unsupported symbol `{}`. Supported symbols: {}",
+                                                   symbol, registry.symbols().join(", ")))),
+            }
+        }
+
+        let aggregator = &self.aggregator;
+        let aggregator_type = AggregatorType::from_str(&aggregator.aggregator_type)
+            .map_err(|_| invalid(format!("Unsupported aggregator `{}`. Supported aggregators: {}, {}, {}", aggregator.aggregator_type,
+                                         AggregatorType::HashMapOrderbookAggegator, AggregatorType::PriceConsolidatedAggregator,
+                                         AggregatorType::SortedMapAggregator)))?;
+        let crossed_book_policy = CrossedBookPolicy::from_str(&aggregator.crossed_book_policy)
+            .map_err(|_| invalid(format!("Unsupported crossed book policy `{}`. Supported policies: {}, {}, {}", aggregator.crossed_book_policy,
+                                         CrossedBookPolicy::PassThrough, CrossedBookPolicy::TrimCrossed, CrossedBookPolicy::DropStaler)))?;
+        if aggregator.depth == 0 {
+            return Err(invalid("The depth of the aggregator must be positive".to_string()));
+        }
+        let aggregator_config = AggregatorConfig::new(aggregator.depth).with_crossed_book_policy(crossed_book_policy);
+        let aggregator_config = match aggregator.staleness_timeout_ms {
+            0 => aggregator_config,
+            timeout => aggregator_config.with_staleness_timeout(Duration::from_millis(timeout)),
+        };
+
+        let reconnect_policy = self.reconnect.validate()?;
+
+        if self.exchanges.is_empty() {
+            return Err(invalid("No exchanges are configured".to_string()));
+        }
+        let mut exchanges: Vec<ExchangeSettings> = Vec::with_capacity(self.exchanges.len());
+        for section in self.exchanges.iter() {
+            let settings = section.validate(aggregator.depth)?;
+            if exchanges.iter().any(|exchange| exchange.exchange == settings.exchange) {
+                return Err(invalid(format!("The exchange `{}` is configured more than once", settings.exchange)));
+            }
+            exchanges.push(settings);
+        }
+
+        Ok(Settings {
+            address: self.address.clone(),
+            instruments,
+            aggregator_type,
+            aggregator_config,
+            reconnect_policy,
+            exchanges,
+        })
+    }
+}
+
+impl ReconnectSection {
+    fn validate(&self) -> Result<ReconnectPolicy, ConfigError> {
+        if self.initial_interval_ms == 0 || self.initial_interval_ms > self.max_interval_ms {
+            return Err(ConfigError::Invalid(format!("The initial reconnect interval of {}ms must be positive and at most the maximum interval of {}ms",
+                                                    self.initial_interval_ms, self.max_interval_ms)));
+        }
+        if self.multiplier.is_nan() || self.multiplier < 1.0 {
+            return Err(ConfigError::Invalid(format!("The reconnect multiplier {} must be at least 1", self.multiplier)));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(ConfigError::Invalid(format!("The reconnect jitter {} must be between 0 and 1", self.jitter)));
+        }
+        if self.max_failures == 0 {
+            return Err(ConfigError::Invalid("The reconnect max failures must be positive".to_string()));
+        }
+        if self.circuit_open_interval_ms < self.max_interval_ms {
+            return Err(ConfigError::Invalid(format!("The circuit open interval of {}ms must be at least the maximum interval of {}ms",
+                                                    self.circuit_open_interval_ms, self.max_interval_ms)));
+        }
+        if self.min_connected_time_ms == 0 {
+            return Err(ConfigError::Invalid("The reconnect min connected time must be positive".to_string()));
+        }
+
+        Ok(ReconnectPolicy::new(
+            Duration::from_millis(self.initial_interval_ms),
+            Duration::from_millis(self.max_interval_ms),
+            self.multiplier,
+            self.jitter,
+            self.max_failures,
+            Duration::from_millis(self.circuit_open_interval_ms),
+        ).with_min_connected_time(Duration::from_millis(self.min_connected_time_ms)))
+    }
+}
+
+impl ExchangeSection {
+    fn validate(&self, aggregator_depth: usize) -> Result<ExchangeSettings, ConfigError> {
+        let invalid = |reason: String| ConfigError::Invalid(format!("Exchange `{}` : {}", self.name, reason));
+
+        let exchange = Exchange::from_str(&self.name)
+            .map_err(|_| ConfigError::Invalid(format!("Unsupported exchange `{}`. Supported exchanges: {}, {}, {}, {}, {}, {}, {}",
+                                                      self.name, Exchange::Binance, Exchange::Bitfinex, Exchange::Bitstamp,
+                                                      Exchange::Coinbase, Exchange::Huobi, Exchange::Kraken, Exchange::Kucoin)))?;
+
+        match Url::parse(&self.url) {
+            Ok(url) if url.scheme() == "ws" || url.scheme() == "wss" => {}
+            _ => return Err(invalid(format!("The url `{}` is not a valid ws:// or wss:// url", self.url))),
+        }
+        if let Some(rest_url) = &self.rest_url {
+            match Url::parse(rest_url) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                _ => return Err(invalid(format!("The rest url `{}` is not a valid http:// or https:// url", rest_url))),
+            }
+        }
+
+        let depth_stream = match &self.depth_stream {
+            Some(depth_stream) => DepthStream::from_str(depth_stream)
+                .map_err(|_| invalid(format!("Unsupported depth stream `{}`. Supported streams: {}, {}",
+                                             depth_stream, DepthStream::Partial, DepthStream::Diff)))?,
+            None => DepthStream::Partial,
+        };
+        if depth_stream == DepthStream::Diff && exchange != Exchange::Binance {
+            return Err(invalid(format!("The {} depth stream is only supported by {}", DepthStream::Diff, Exchange::Binance)));
+        }
+
+        let depth = match (self.depth, depth_stream) {
+            (Some(depth), _) => depth,
+            // The local orderbook of the diff depth stream is not limited to the depths of the partial stream
+            (None, DepthStream::Diff) => aggregator_depth,
+            (None, DepthStream::Partial) => DEFAULT_EXCHANGE_DEPTH,
+        };
+        if depth == 0 {
+            return Err(invalid("The depth must be positive".to_string()));
+        }
+        if exchange == Exchange::Binance && depth_stream == DepthStream::Partial && !BINANCE_PARTIAL_DEPTHS.contains(&depth) {
+            return Err(invalid(format!("The {} depth stream supports the depths {:?}, not {}", DepthStream::Partial, BINANCE_PARTIAL_DEPTHS, depth)));
+        }
+
+        Ok(ExchangeSettings {
+            exchange,
+            url: self.url.clone(),
+            depth,
+            depth_stream,
+            rest_url: self.rest_url.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_models::exchange::Exchange;
+    use data_models::instrument_registry::InstrumentRegistry;
+    use exchange_client::api::configuration::DepthStream;
+    use orderbook::api::provider::AggregatorType;
+
+    use crate::config::{ConfigError, ExchangeSettings, ServerConfig};
+
+    fn validate(content: &str) -> Result<Vec<ExchangeSettings>, String> {
+        ServerConfig::parse("test.toml", content)
+            .and_then(|config| config.validate(&InstrumentRegistry::default()))
+            .map(|settings| settings.exchanges)
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn example_config_test() {
+        let config = ServerConfig::parse("config.toml", include_str!("../config.toml")).unwrap();
+        let settings = config.validate(&InstrumentRegistry::default()).unwrap();
+
+        assert_eq!(config.exchanges, ServerConfig::default().exchanges);
+        assert_eq!(config.reconnect, ServerConfig::default().reconnect);
+        assert_eq!(settings.aggregator_type, AggregatorType::HashMapOrderbookAggegator);
+        assert_eq!(settings.instruments.iter().map(|instrument| instrument.symbol()).collect::<Vec<_>>(), vec!["ethbtc"]);
+    }
+
+    #[test]
+    fn exchange_settings_test() {
+        let exchanges = validate(r#"
+            symbols = ["ethbtc"]
+            aggregator = { depth = 25 }
+
+            [[exchanges]]
+            name = "Binance"
+            url = "ws://localhost:9001/ws"
+            depth_stream = "diff"
+            rest_url = "http://localhost:9002"
+
+            [[exchanges]]
+            name = "Bitstamp"
+            url = "ws://localhost:9003"
+            depth = 20
+        "#).unwrap();
+
+        assert_eq!(exchanges, vec![
+            ExchangeSettings {
+                exchange: Exchange::Binance,
+                url: "ws://localhost:9001/ws".to_string(),
+                depth: 25,
+                depth_stream: DepthStream::Diff,
+                rest_url: Some("http://localhost:9002".to_string()),
+            },
+            ExchangeSettings {
+                exchange: Exchange::Bitstamp,
+                url: "ws://localhost:9003".to_string(),
+                depth: 20,
+                depth_stream: DepthStream::Partial,
+                rest_url: None,
+            },
+        ]);
+    }
+
+    #[test]
+    fn invalid_config_test() {
+        let exchange = |entry: &str| format!("symbols = [\"ethbtc\"]\n[[exchanges]]\n{}", entry);
+
+        let cases = [
+            ("symbols = [\"ethbtc\"]\nport = 1", "Could not parse the config file `test.toml`"),
+            ("symbols = [\"ethbtc\"]\naggregator = { depth = \"10\" }", "Could not parse the config file `test.toml`"),
+            ("", "No symbols are configured"),
+            ("symbols = [\"dogeusd\"]", "Unsupported symbol `dogeusd`"),
+            ("symbols = [\"ethbtc\"]\naddress = \"localhost\"", "The address `localhost` is not a valid socket address"),
+            ("symbols = [\"ethbtc\"]\naggregator = { type = \"btree\" }", "Unsupported aggregator `btree`"),
+            ("symbols = [\"ethbtc\"]\naggregator = { crossed_book_policy = \"cancel\" }", "Unsupported crossed book policy `cancel`"),
+            ("symbols = [\"ethbtc\"]\nreconnect = { jitter = 1.5 }", "The reconnect jitter 1.5 must be between 0 and 1"),
+            ("symbols = [\"ethbtc\"]\nreconnect = { circuit_open_interval_ms = 0 }", "The circuit open interval of 0ms must be at least the maximum interval of 30000ms"),
+            ("symbols = [\"ethbtc\"]\nreconnect = { max_interval_ms = 5000, circuit_open_interval_ms = 4999 }", "The circuit open interval of 4999ms must be at least the maximum interval of 5000ms"),
+            ("symbols = [\"ethbtc\"]\nreconnect = { min_connected_time_ms = 0 }", "The reconnect min connected time must be positive"),
+            ("symbols = [\"ethbtc\"]\nexchanges = []", "No exchanges are configured"),
+            (&exchange("name = \"Bybit\"\nurl = \"wss://stream.bybit.com\""), "Unsupported exchange `Bybit`"),
+            (&exchange("name = \"Bitstamp\"\nurl = \"https://www.bitstamp.net\""), "Exchange `Bitstamp` : The url `https://www.bitstamp.net` is not a valid ws:// or wss:// url"),
+            (&exchange("name = \"Bitstamp\"\nurl = \"wss://ws.bitstamp.net\"\ndepth_stream = \"diff\""), "Exchange `Bitstamp` : The diff depth stream is only supported by Binance"),
+            (&exchange("name = \"Binance\"\nurl = \"wss://stream.binance.com:9443/ws\"\ndepth = 15"), "Exchange `Binance` : The partial depth stream supports the depths [5, 10, 20], not 15"),
+            (&format!("{}\n[[exchanges]]\nname = \"Binance\"\nurl = \"ws://localhost\"",
+                      exchange("name = \"Binance\"\nurl = \"ws://localhost\"")), "The exchange `Binance` is configured more than once"),
+        ];
+
+        for (content, expected) in cases {
+            let error = validate(content).unwrap_err();
+            assert!(error.contains(expected), "`{}` does not contain `{}`", error, expected);
+        }
+        assert!(matches!(ServerConfig::load(Some("missing.toml")), Err(ConfigError::Read(_, _))));
+    }
+}
@@ -1,26 +1,42 @@
 use std::pin::Pin;
+use std::time::Duration;
 
+use rust_decimal::prelude::ToPrimitive;
 use tokio::sync::broadcast;
 use tokio::sync::broadcast::error::RecvError;
-use tokio::sync::mpsc::UnboundedReceiver;
 use tonic::{Request, Response, Status};
 use tonic::async_trait;
 use tonic::codegen::tokio_stream::Stream;
 
 /// GRPC server implementation
+use data_models::aggregated_orderbook::AggregatedOrderbook;
+use data_models::book_condition::BookCondition;
+use data_models::consolidated_level::ConsolidatedLevel;
+use data_models::exchange_event::ExchangeEvent;
 use data_models::exchange_level::ExchangeLevel;
-use data_models::exchange_orderbook::OrderbookSnapshot;
-use orderbook::api::provider::AggregatorType;
-use orderbook::api::provider::OrderbookSnapshotAggregator;
-use grpc_orderbook::{Empty, Level, Summary};
+use data_models::exchange_summary::ExchangeSummary;
+use data_models::level::Decimal;
+use data_models::timestamp;
+use exchange_client::api::mailbox::MailboxReceiver;
+use orderbook::api::provider::{AggregatorConfig, AggregatorType};
+use grpc_orderbook::{BookSummaryRequest, Level, LevelBreakdown, Summary};
+use grpc_orderbook::ExchangeSummary as GrpcExchangeSummary;
 use grpc_orderbook::orderbook_aggregator_server::OrderbookAggregator as GrpcOrderbookAggregator;
 
 pub mod grpc_orderbook {
     tonic::include_proto!("orderbook");
 }
 
+/// The number of levels per side used to calculate the orderbook imbalance.
+const IMBALANCE_DEPTH: usize = 5;
+/// How often the aggregator checks for exchanges that stopped publishing snapshots.
+const STALENESS_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// How often the number of snapshots the mailbox dropped because the aggregator fell behind is reported.
+const DROPPED_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
 pub struct Grpc {
     sender: broadcast::Sender<Result<Summary, Status>>,
+    symbols: Vec<String>,
 }
 
 impl Grpc {
@@ -28,36 +44,103 @@ impl Grpc {
     ///
     /// # Arguments
     ///
-    /// * `orderbook_rx` - The [`UnboundedReceiver<OrderbookSnapshot>`] that receives the orderbook updates from
-    /// exchange clients.
+    /// * `orderbook_rx` - The [MailboxReceiver] of the mailbox that receives the orderbook updates and the
+    ///   connection events from exchange clients. The snapshots it dropped are reported every [DROPPED_REPORT_INTERVAL].
+    /// * `symbols` - The canonical symbols of the instruments the exchange clients subscribed to.
+    /// * `aggregator_type` - The [AggregatorType] of the orderbook aggregator.
+    /// * `aggregator_config` - The [AggregatorConfig] of the orderbook aggregator.
     ///
     /// Calling this method will spawn a [`tokio::task`] that will publish the aggregated orderbook to the connected clients.
-    pub fn new(mut orderbook_rx: UnboundedReceiver<OrderbookSnapshot>) -> Grpc {
+    pub fn new(mut orderbook_rx: MailboxReceiver,
+               symbols: Vec<String>,
+               aggregator_type: AggregatorType,
+               aggregator_config: AggregatorConfig) -> Grpc {
         let (tx, _) = broadcast::channel::<Result<Summary, Status>>(1000);
         let sender = tx.clone();
 
         tokio::spawn(async move {
-            let mut aggregator = orderbook::api::provider::get(AggregatorType::HashMapOrderbookAggegator);
+            let mut aggregator = orderbook::api::provider::get(aggregator_type, aggregator_config);
 
-            while let Some(orderbook_snapshot) = orderbook_rx.recv().await {
-                let aggregated_orderbook = aggregator.on_orderbook_snapshot(orderbook_snapshot);
+            let mut staleness_check = tokio::time::interval(STALENESS_CHECK_INTERVAL);
+            let mut dropped_report = tokio::time::interval(DROPPED_REPORT_INTERVAL);
+            let mut reported = 0;
 
-                let summary = Summary {
-                    spread: aggregated_orderbook.spread(),
-                    bids: Self::transform(&aggregated_orderbook.bids),
-                    asks: Self::transform(&aggregated_orderbook.asks),
+            loop {
+                let aggregated_orderbooks = tokio::select! {
+                    exchange_event = orderbook_rx.recv() => match exchange_event {
+                        Some(ExchangeEvent::Snapshot(orderbook_snapshot)) => aggregator.on_orderbook_snapshot(orderbook_snapshot).into_iter().collect(),
+                        Some(ExchangeEvent::Connection(connection_event)) => {
+                            println!("{}", connection_event);
+                            aggregator.on_connection_event(&connection_event).into_iter().collect()
+                        }
+                        None => break,
+                    },
+                    _ = staleness_check.tick() => aggregator.on_timer(timestamp::now_micros()),
+                    _ = dropped_report.tick() => {
+                        let dropped = orderbook_rx.dropped_all();
+                        let total: u64 = dropped.iter().map(|(_, _, count)| count).sum();
+                        if total > reported {
+                            reported = total;
+                            for (exchange, symbol, count) in dropped {
+                                println!("Dropped {} outdated snapshots of `{}` : `{}`", count, exchange, symbol);
+                            }
+                        }
+                        continue;
+                    }
                 };
 
-                match sender.send(Ok(summary)) {
-                    Ok(_) => {}
+                // An empty vector means that the snapshot was rejected or that no exchange
+                // became stale, the aggregated orderbook has not changed
+                for aggregated_orderbook in aggregated_orderbooks {
+                    let summary = Self::summarize(&aggregated_orderbook);
+
                     // A send error will only occur if there are no active receivers
                     // https://docs.rs/tokio/latest/tokio/sync/broadcast/error/struct.SendError.html
-                    Err(_) => {}
+                    let _ = sender.send(Ok(summary));
                 }
             }
         });
 
-        Grpc { sender: tx.clone() }
+        Grpc { sender: tx.clone(), symbols }
+    }
+
+    /// Converts the given [AggregatedOrderbook] into the streaming gRPC [Summary].
+    /// Analytics that cannot be calculated, e.g. the spread of a one sided orderbook, are set to zero.
+    /// The exact decimal prices and amounts are kept in the string fields of each [Level].
+    /// If the aggregator consolidates levels by price, the consolidated levels are streamed
+    /// and the analytics are calculated from them.
+    ///
+    /// # Arguments
+    ///
+    /// * `aggregated_orderbook` - The [AggregatedOrderbook] returned by the aggregator.
+    fn summarize(aggregated_orderbook: &AggregatedOrderbook) -> Summary {
+        let (bids, asks) = match &aggregated_orderbook.consolidated {
+            Some(consolidated) => (
+                Self::transform_consolidated(&consolidated.bids),
+                Self::transform_consolidated(&consolidated.asks),
+            ),
+            None => (
+                Self::transform(&aggregated_orderbook.bids),
+                Self::transform(&aggregated_orderbook.asks),
+            ),
+        };
+
+        Summary {
+            symbol: aggregated_orderbook.symbol.clone(),
+            spread: Self::to_f64(aggregated_orderbook.spread()),
+            bids,
+            asks,
+            mid_price: Self::to_f64(aggregated_orderbook.mid_price()),
+            microprice: Self::to_f64(aggregated_orderbook.microprice()),
+            imbalance: Self::to_f64(aggregated_orderbook.imbalance(IMBALANCE_DEPTH)),
+            exchanges: aggregated_orderbook
+                .exchanges
+                .iter()
+                .map(Self::transform_exchange_summary)
+                .collect(),
+            crossed: aggregated_orderbook.condition == BookCondition::Crossed,
+            locked: aggregated_orderbook.condition == BookCondition::Locked,
+        }
     }
 
     /// Transform function that converts the given asks or bids into the streaming gRPC data model
@@ -68,17 +151,84 @@ impl Grpc {
     ///
     /// Returns the exchange_levels mapped into [Vec<Level>]
     #[inline]
-    fn transform(exchange_levels: &Vec<ExchangeLevel>) -> Vec<Level> {
+    fn transform(exchange_levels: &[ExchangeLevel]) -> Vec<Level> {
         exchange_levels
-            .into_iter()
-            .map(|exchange_level| Level {
-                exchange: exchange_level.exchange.to_string(),
-                price: exchange_level.level.price,
-                amount: exchange_level.level.amount,
+            .iter()
+            .map(Self::transform_level)
+            .collect()
+    }
+
+    #[inline]
+    fn transform_level(exchange_level: &ExchangeLevel) -> Level {
+        Level {
+            exchange: exchange_level.exchange.to_string(),
+            price: Self::to_f64(Some(exchange_level.level.price)),
+            amount: Self::to_f64(Some(exchange_level.level.amount)),
+            exact_price: exchange_level.level.price.to_string(),
+            exact_amount: exchange_level.level.amount.to_string(),
+            breakdown: vec![],
+        }
+    }
+
+    /// Transform function that converts the given asks or bids consolidated by price into the streaming gRPC data model
+    ///
+    /// # Arguments
+    ///
+    /// * `consolidated_levels` - The [ConsolidatedLevel]s containing the bids or asks
+    fn transform_consolidated(consolidated_levels: &[ConsolidatedLevel]) -> Vec<Level> {
+        consolidated_levels
+            .iter()
+            .map(|consolidated_level| Level {
+                exchange: consolidated_level.breakdown
+                    .iter()
+                    .map(|exchange_level| exchange_level.exchange.to_string())
+                    .collect::<Vec<String>>()
+                    .join(","),
+                price: Self::to_f64(Some(consolidated_level.level.price)),
+                amount: Self::to_f64(Some(consolidated_level.level.amount)),
+                exact_price: consolidated_level.level.price.to_string(),
+                exact_amount: consolidated_level.level.amount.to_string(),
+                breakdown: consolidated_level.breakdown
+                    .iter()
+                    .map(|exchange_level| LevelBreakdown {
+                        exchange: exchange_level.exchange.to_string(),
+                        amount: Self::to_f64(Some(exchange_level.level.amount)),
+                        exact_amount: exchange_level.level.amount.to_string(),
+                    })
+                    .collect(),
             })
             .collect()
     }
 
+    #[inline]
+    fn to_f64(value: Option<Decimal>) -> f64 {
+        value.and_then(|value| value.to_f64()).unwrap_or_default()
+    }
+
+    /// Transform function that converts the top of the book of an exchange into the streaming gRPC data model
+    fn transform_exchange_summary(exchange_summary: &ExchangeSummary) -> GrpcExchangeSummary {
+        let to_level = |level| Self::transform_level(&ExchangeLevel::new(exchange_summary.exchange, level));
+
+        GrpcExchangeSummary {
+            exchange: exchange_summary.exchange.to_string(),
+            best_bid: exchange_summary.best_bid.map(to_level),
+            best_ask: exchange_summary.best_ask.map(to_level),
+            sequence: exchange_summary.sequence.unwrap_or_default(),
+            exchange_timestamp: exchange_summary.exchange_timestamp.unwrap_or_default(),
+            received_timestamp: exchange_summary.received_timestamp.unwrap_or_default(),
+            stale: exchange_summary.stale,
+            connection_state: exchange_summary.connection_state.to_string(),
+        }
+    }
+
+    /// Truncates the bids and asks of the given [Summary] to the depth requested by a client.
+    #[inline]
+    fn truncate(mut summary: Summary, depth: usize) -> Summary {
+        summary.bids.truncate(depth);
+        summary.asks.truncate(depth);
+        summary
+    }
+
     fn get_receiver(&self) -> broadcast::Receiver<Result<Summary, Status>> {
         self.sender.subscribe()
     }
@@ -90,15 +240,27 @@ impl GrpcOrderbookAggregator for Grpc {
     type BookSummaryStream = Pin<Box<dyn Stream<Item=Result<Summary, Status>> + Send>>;
 
     async fn book_summary(&self,
-        _request: Request<Empty>,
+        request: Request<BookSummaryRequest>,
     ) -> Result<Response<Self::BookSummaryStream>, Status> {
-        let mut receiver: broadcast::Receiver<Result<Summary, Status>> = self.get_receiver();
+        let request = request.into_inner();
+        let symbol = request.symbol.to_lowercase();
+        if !symbol.is_empty() && !self.symbols.contains(&symbol) {
+            return Err(Status::not_found(format!("Unsupported symbol `{}`. Supported symbols: {}", symbol, self.symbols.join(", "))));
+        }
+        let depth = match request.depth {
+            0 => usize::MAX,
+            depth => depth as usize,
+        };
 
+        let mut receiver: broadcast::Receiver<Result<Summary, Status>> = self.get_receiver();
         let output = async_stream::stream! {
             loop {
                 let result: Result<Result<Summary, Status>, RecvError> = receiver.recv().await;
                 match result {
-                    Ok(message) => yield message,
+                    Ok(Ok(summary)) if symbol.is_empty() || summary.symbol == symbol => yield Ok(Self::truncate(summary, depth)),
+                    // The summary of another symbol
+                    Ok(Ok(_)) => continue,
+                    Ok(Err(status)) => yield Err(status),
                     //TODO: handle the two possible errors
                     //https://docs.rs/tokio/latest/tokio/sync/broadcast/error/enum.RecvError.html
                     Err(err) => println!("{}", err)
@@ -109,3 +271,41 @@ impl GrpcOrderbookAggregator for Grpc {
         Ok(Response::new(Box::pin(output) as Self::BookSummaryStream))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal_macros::dec;
+
+    use data_models::aggregated_orderbook::AggregatedOrderbook;
+    use data_models::consolidated_level::ConsolidatedLevel;
+    use data_models::consolidated_levels::ConsolidatedLevels;
+    use data_models::exchange::Exchange;
+    use data_models::exchange_level::ExchangeLevel;
+    use data_models::level::Level;
+
+    use super::Grpc;
+
+    #[test]
+    fn summarize_consolidated_test() {
+        let bids = vec![
+            ExchangeLevel::new(Exchange::Binance, Level::new(dec!(100.0), dec!(1.0))),
+            ExchangeLevel::new(Exchange::Kraken, Level::new(dec!(100.0), dec!(3.0))),
+        ];
+        let asks = vec![ExchangeLevel::new(Exchange::Kraken, Level::new(dec!(101.0), dec!(4.0)))];
+        let consolidated = ConsolidatedLevels::new(
+            vec![ConsolidatedLevel::new(bids.clone())],
+            vec![ConsolidatedLevel::new(asks.clone())],
+        );
+        let aggregated_orderbook = AggregatedOrderbook::new(bids, asks).with_consolidated(consolidated);
+
+        let summary = Grpc::summarize(&aggregated_orderbook);
+
+        assert_eq!(summary.bids.len(), 1);
+        assert_eq!(summary.bids[0].exact_amount, "4.0");
+        assert_eq!(summary.spread, 1.0);
+        assert_eq!(summary.mid_price, 100.5);
+        // (100 * 4 + 101 * (1 + 3)) / (1 + 3 + 4), both exchanges at the best bid count
+        assert_eq!(summary.microprice, 100.5);
+        assert_eq!(summary.imbalance, 0.0);
+    }
+}
@@ -4,10 +4,11 @@
 
 use std::net::ToSocketAddrs;
 
-use tokio::sync::mpsc::UnboundedReceiver;
 use tonic::transport::Server;
 
-use data_models::exchange_orderbook::OrderbookSnapshot;
+use exchange_client::api::mailbox::MailboxReceiver;
+use orderbook::api::configuration::AggregatorConfig;
+use orderbook::api::provider::AggregatorType;
 
 use super::grpc_server::Grpc;
 use super::grpc_server::grpc_orderbook::orderbook_aggregator_server::OrderbookAggregatorServer;
@@ -20,17 +21,140 @@ pub mod grpc_orderbook {
 ///
 /// # Arguments
 ///
-/// * `receiver` - The [`UnboundedReceiver<OrderbookSnapshot>`] that receives the orderbook updates from
-/// exchange clients.
+/// * `receiver` - The [MailboxReceiver] of the mailbox that receives the orderbook updates and the connection events from
+///   exchange clients.
 /// * `addr` - The address of the server.
+/// * `symbols` - The canonical symbols of the instruments the exchange clients subscribed to.
+/// * `aggregator_type` - The [AggregatorType] of the orderbook aggregator.
+/// * `aggregator_config` - The [AggregatorConfig] of the orderbook aggregator.
 pub async fn start(
-    receiver: UnboundedReceiver<OrderbookSnapshot>,
+    receiver: MailboxReceiver,
     addr: &str,
+    symbols: Vec<String>,
+    aggregator_type: AggregatorType,
+    aggregator_config: AggregatorConfig,
 ) -> Result<(), tonic::transport::Error> {
     let addr = addr.to_socket_addrs().unwrap().next().unwrap();
 
     Server::builder()
-        .add_service(OrderbookAggregatorServer::new(Grpc::new(receiver)))
+        .add_service(OrderbookAggregatorServer::new(Grpc::new(receiver, symbols, aggregator_type, aggregator_config)))
         .serve(addr)
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::Streaming;
+
+    use data_models::exchange::Exchange;
+    use data_models::instrument_registry::InstrumentRegistry;
+    use exchange_client::api::configuration::ExchangeClientConfig;
+    use orderbook::api::configuration::AggregatorConfig;
+    use orderbook::api::provider::AggregatorType;
+    use test_support::{binance, bitstamp};
+    use test_support::mock_exchange::{MockExchange, Step};
+
+    use super::grpc_orderbook::{BookSummaryRequest, Summary};
+    use super::grpc_orderbook::orderbook_aggregator_client::OrderbookAggregatorClient;
+
+    /// Starts the server on a free local port and returns its address.
+    async fn start_server(receiver: exchange_client::api::mailbox::MailboxReceiver) -> String {
+        let address = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+        let server_address = address.clone();
+        tokio::spawn(async move {
+            super::start(receiver, &server_address, vec!["ethbtc".to_string()],
+                         AggregatorType::HashMapOrderbookAggegator, AggregatorConfig::new(10)).await
+        });
+        address
+    }
+
+    /// Connects to the server, retrying until it accepts connections.
+    async fn book_summary(address: &str) -> Streaming<Summary> {
+        for _ in 0..50 {
+            if let Ok(mut client) = OrderbookAggregatorClient::connect(format!("http://{}", address)).await {
+                let request = BookSummaryRequest { symbol: "ethbtc".to_string(), depth: 0 };
+                return client.book_summary(request).await.unwrap().into_inner();
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("Could not connect to the server at `{}`", address);
+    }
+
+    /// Receives summaries until one matches `until`.
+    async fn receive_until(stream: &mut Streaming<Summary>, until: impl Fn(&Summary) -> bool) -> Summary {
+        loop {
+            let summary = tokio::time::timeout(Duration::from_secs(5), stream.message()).await
+                .expect("The server did not stream the expected summary")
+                .unwrap()
+                .unwrap();
+            if until(&summary) {
+                return summary;
+            }
+        }
+    }
+
+    fn exchanges(levels: &[super::grpc_orderbook::Level]) -> Vec<&str> {
+        levels.iter().map(|level| level.exchange.as_str()).collect()
+    }
+
+    fn connection_state<'a>(summary: &'a Summary, exchange: &str) -> Option<&'a str> {
+        summary.exchanges.iter()
+            .find(|exchange_summary| exchange_summary.exchange == exchange)
+            .map(|exchange_summary| exchange_summary.connection_state.as_str())
+    }
+
+    #[tokio::test]
+    async fn streams_mock_exchanges_test() {
+        let binance = MockExchange::start(Exchange::Binance, vec![vec![
+            Step::Send(binance::partial_depth(1, &[("0.0687", "1.0")], &[("0.0690", "1.0")])),
+        ]]).await;
+        let bitstamp = MockExchange::start(Exchange::Bitstamp, vec![
+            vec![
+                Step::Send(bitstamp::order_book("ethbtc", 1, &[("0.0688", "2.0")], &[("0.0689", "2.0")])),
+                Step::SendMalformed,
+                Step::Delay(Duration::from_millis(300)),
+                Step::Disconnect,
+            ],
+            vec![
+                Step::Delay(Duration::from_millis(300)),
+                Step::Send(bitstamp::order_book("ethbtc", 2, &[("0.0686", "3.0")], &[("0.0691", "3.0")])),
+            ],
+        ]).await;
+
+        let (sender, receiver) = exchange_client::api::mailbox::channel(2);
+        let address = start_server(receiver).await;
+        let mut stream = book_summary(&address).await;
+
+        let instrument = InstrumentRegistry::default().get("ethbtc").unwrap().clone();
+        let clients = [
+            exchange_client::api::provider::start(Exchange::Binance,
+                ExchangeClientConfig::new(format!("{}/ws", binance.url()), 1, instrument.clone()), sender.clone()),
+            exchange_client::api::provider::start(Exchange::Bitstamp,
+                ExchangeClientConfig::new(bitstamp.url(), 1, instrument), sender),
+        ];
+
+        let summary = receive_until(&mut stream, |summary| summary.bids.len() == 2).await;
+        assert_eq!(exchanges(&summary.bids), vec!["Bitstamp", "Binance"]);
+        assert_eq!(summary.bids[0].exact_price, "0.0688");
+        assert_eq!(summary.asks[0].exact_price, "0.0689");
+
+        // The levels of Bitstamp are cleared as soon as its connection drops
+        let summary = receive_until(&mut stream, |summary| connection_state(summary, "Bitstamp") == Some("Disconnected")).await;
+        assert_eq!(exchanges(&summary.bids), vec!["Binance"]);
+        assert_eq!(exchanges(&summary.asks), vec!["Binance"]);
+        assert_eq!(connection_state(&summary, "Binance"), Some("Subscribed"));
+
+        // and streamed again once it reconnected
+        let summary = receive_until(&mut stream, |summary| summary.bids.len() == 2).await;
+        assert_eq!(exchanges(&summary.bids), vec!["Binance", "Bitstamp"]);
+        assert_eq!(summary.bids[1].exact_price, "0.0686");
+        assert_eq!(connection_state(&summary, "Bitstamp"), Some("Subscribed"));
+        assert_eq!(bitstamp.connections(), 2);
+
+        for client in clients {
+            client.abort();
+        }
+    }
+}
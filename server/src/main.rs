@@ -1,51 +1,231 @@
-use clap::{arg, Parser};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use data_models::{exchange::Exchange, exchange_orderbook::OrderbookSnapshot};
-use exchange_client::api::configuration::ExchangeClientConfig;
+use clap::Parser;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
+use data_models::exchange::Exchange;
+use data_models::instrument_registry::InstrumentRegistry;
+use exchange_client::api::capture::CaptureWriter;
+use exchange_client::api::error::ExchangeClientError;
+use exchange_client::api::heartbeat::RoundTripTime;
+use exchange_client::api::replay::{ReplaySource, ReplaySpeed};
+
+use crate::config::ServerConfig;
+
+mod config;
 mod grpc;
 
-/// The command line arguments the server can parse.
+/// How often the round trip time of the pings of each exchange client is reported.
+const ROUND_TRIP_TIME_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The command line arguments the server can parse. The arguments override the values of the `--config` file.
 #[derive(Parser, Debug)]
 struct Args {
+    /// The TOML configuration file that declares the exchanges, symbols, aggregator and listen address,
+    /// e.g. `server/config.toml`. The defaults are used if no file is given.
+    #[arg(long)]
+    config: Option<String>,
+
+    /// The comma separated canonical symbols of the instruments, e.g. `ethbtc,btcusdt`.
+    #[arg(short, long, alias = "symbol", value_delimiter = ',')]
+    symbols: Option<Vec<String>>,
+
+    /// The address the server listens on, `[::1]:50051` by default.
+    #[arg(short, long)]
+    address: Option<String>,
+
+    /// The maximum number of merged levels per side of the aggregated orderbook, 10 by default.
     #[arg(short, long)]
-    symbol: String,
+    depth: Option<usize>,
+
+    /// The aggregator type: `hashmap` (the default) keeps one row per exchange level,
+    /// `consolidated` merges the levels of all exchanges with the same price,
+    /// `sortedmap` keeps one row per exchange level and updates it incrementally.
+    #[arg(long)]
+    aggregator: Option<String>,
+
+    /// The number of milliseconds after which an exchange that stopped publishing snapshots
+    /// is considered stale and its levels are dropped from the aggregated orderbook. 0 disables it, 30000 by default.
+    #[arg(long)]
+    staleness_timeout_ms: Option<u64>,
+
+    /// How a crossed orderbook is resolved: `passthrough` (the default) publishes the crossed levels,
+    /// `trim` trims the crossed amounts, `dropstaler` drops the side of the staler exchange.
+    #[arg(long)]
+    crossed_book_policy: Option<String>,
+
+    /// The Binance orderbook stream: `partial` (the default) streams snapshots of the top 10 levels,
+    /// `diff` keeps a local orderbook from a REST snapshot and the diff depth stream, streaming up to `depth` levels.
+    #[arg(long)]
+    binance_depth_stream: Option<String>,
+
+    /// Writes every raw frame and REST response the exchange clients receive to the given file, so that the session can be replayed.
+    #[arg(long)]
+    capture: Option<String>,
+
+    /// Replays the connections of the given capture file instead of connecting to the exchanges.
+    #[arg(long, conflicts_with = "capture")]
+    replay: Option<String>,
+
+    /// The speed of the replay: `original`, a multiple of the original speed, e.g. `10`, or `max` to replay as fast as possible.
+    #[arg(long, default_value_t = String::from("original"))]
+    replay_speed: String,
+}
 
-    #[arg(short, long, default_value_t = String::from("[::1]:50051"))]
-    address: String,
+impl Args {
+    /// Overrides the values of the configuration file with the given arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The [ServerConfig] read from the `--config` file.
+    fn apply(&self, config: &mut ServerConfig) {
+        if let Some(symbols) = &self.symbols {
+            config.symbols = symbols.clone();
+        }
+        if let Some(address) = &self.address {
+            config.address = address.clone();
+        }
+        if let Some(depth) = self.depth {
+            config.aggregator.depth = depth;
+        }
+        if let Some(aggregator) = &self.aggregator {
+            config.aggregator.aggregator_type = aggregator.clone();
+        }
+        if let Some(staleness_timeout_ms) = self.staleness_timeout_ms {
+            config.aggregator.staleness_timeout_ms = staleness_timeout_ms;
+        }
+        if let Some(crossed_book_policy) = &self.crossed_book_policy {
+            config.aggregator.crossed_book_policy = crossed_book_policy.clone();
+        }
+        if let Some(binance_depth_stream) = &self.binance_depth_stream {
+            config.exchanges.iter_mut()
+                .filter(|exchange| exchange.name == Exchange::Binance.to_string())
+                .for_each(|exchange| exchange.depth_stream = Some(binance_depth_stream.clone()));
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
 
-    let (tx_exchange, rx_exchange) = tokio::sync::mpsc::unbounded_channel::<OrderbookSnapshot>();
-    let depth = 10;
-
-    exchange_client::api::provider::start(
-        Exchange::Bitstamp,
-        ExchangeClientConfig::new(
-            String::from("wss://ws.bitstamp.net"),
-            depth,
-            args.symbol.clone(),
-        ),
-        tx_exchange.clone(),
-    );
-
-    exchange_client::api::provider::start(
-        Exchange::Binance,
-        ExchangeClientConfig::new(
-            String::from("wss://stream.binance.com:9443/ws"),
-            depth,
-            args.symbol.clone(),
-        ),
-        tx_exchange.clone(),
-    );
-
-    let server = grpc::provider::start(rx_exchange, &args.address);
+    let mut config = match ServerConfig::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    args.apply(&mut config);
+    let settings = match config.validate(&InstrumentRegistry::default()) {
+        Ok(settings) => settings,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+
+    let replay_speed = match ReplaySpeed::from_str(&args.replay_speed) {
+        Ok(replay_speed) => replay_speed,
+        Err(_) => {
+            println!("Unsupported replay speed `{}`. Supported speeds: {}, {}, a positive multiple", args.replay_speed,
+                     ReplaySpeed::Original, ReplaySpeed::AsFastAsPossible);
+            return;
+        }
+    };
+
+    let capture = match &args.capture {
+        Some(path) => match CaptureWriter::create(path) {
+            Ok(capture) => Some(Arc::new(capture)),
+            Err(error) => {
+                println!("Could not create the capture `{}` : `{}`", path, error);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let instruments = settings.instruments;
+    // Keeps only the latest snapshot of each exchange client, so the aggregator always works on the freshest orderbooks
+    let (tx_exchange, rx_exchange) = exchange_client::api::mailbox::channel(instruments.len() * settings.exchanges.len());
+    let mut round_trip_times = Vec::new();
+
+    for instrument in instruments.iter() {
+        for exchange_settings in settings.exchanges.iter() {
+            let exchange = exchange_settings.exchange;
+            let client_config = exchange_settings.client_config(instrument, &settings.reconnect_policy);
+            let exchange_client = match &args.replay {
+                Some(path) => match ReplaySource::open(path, exchange, &instrument.symbol(), replay_speed) {
+                    Ok(source) => exchange_client::api::provider::replay(exchange, client_config, tx_exchange.clone(), source),
+                    Err(error) => {
+                        println!("Could not read the capture `{}` : `{}`", path, error);
+                        return;
+                    }
+                },
+                None => {
+                    round_trip_times.push((exchange, instrument.symbol(), client_config.round_trip_time.clone()));
+                    exchange_client::api::provider::start(exchange, client_config.with_capture(capture.clone()), tx_exchange.clone())
+                }
+            };
+            watch(exchange, instrument.symbol(), exchange_client);
+        }
+    }
+    report_round_trip_times(round_trip_times);
+
+    let server = grpc::provider::start(rx_exchange, &settings.address,
+        instruments.iter().map(|instrument| instrument.symbol()).collect(), settings.aggregator_type, settings.aggregator_config);
 
     match server.await {
         Ok(_) => println!("Server stopped"),
-        Err(e) => println!("Server terminated with error {}", e.to_string()),
+        Err(e) => println!("Server terminated with error {}", e),
     }
 }
+
+/// Reports when an exchange client stops, so that a client that stopped on a fatal
+/// [ExchangeClientError] or panicked is not lost silently. The levels of the stopped
+/// client are dropped by the aggregator once they become stale. A client that replays
+/// a capture stops once the capture has been replayed.
+///
+/// # Arguments
+///
+/// * `exchange` - The [Exchange] of the client.
+/// * `symbol` - The canonical symbol the client subscribed to.
+/// * `exchange_client` - The [JoinHandle] returned by [exchange_client::api::provider::start].
+fn watch(exchange: Exchange, symbol: String, exchange_client: JoinHandle<Result<(), ExchangeClientError>>) {
+    tokio::spawn(async move {
+        match exchange_client.await {
+            Ok(Ok(())) => println!("Exchange client `{}` : `{}` stopped", exchange, symbol),
+            Ok(Err(error)) => println!("Exchange client `{}` : `{}` stopped with error `{}`", exchange, symbol, error),
+            Err(error) => println!("Exchange client `{}` : `{}` panicked `{}`", exchange, symbol, error),
+        }
+    });
+}
+
+/// Reports the round trip time of the latest answered ping of each exchange client every
+/// [ROUND_TRIP_TIME_REPORT_INTERVAL], so that slow or unresponsive connections can be monitored.
+/// Nothing is reported for the clients that replay a capture, since they do not ping.
+///
+/// # Arguments
+///
+/// * `round_trip_times` - The [Exchange], the canonical symbol and the [RoundTripTime] of each client.
+fn report_round_trip_times(round_trip_times: Vec<(Exchange, String, Arc<RoundTripTime>)>) {
+    if round_trip_times.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut report = tokio::time::interval_at(Instant::now() + ROUND_TRIP_TIME_REPORT_INTERVAL, ROUND_TRIP_TIME_REPORT_INTERVAL);
+        loop {
+            report.tick().await;
+            for (exchange, symbol, round_trip_time) in round_trip_times.iter() {
+                match round_trip_time.last() {
+                    Some(last) => println!("Round trip time of `{}` : `{}` is {:?} after {} pongs", exchange, symbol, last, round_trip_time.pongs()),
+                    None => println!("No ping of `{}` : `{}` has been answered", exchange, symbol),
+                }
+            }
+        }
+    });
+}
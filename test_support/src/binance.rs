@@ -0,0 +1,18 @@
+//! Builders of the messages of the Binance WebSocket streams.
+
+use serde_json::json;
+
+/// Builds a message of the partial book depth stream.
+///
+/// # Arguments
+///
+/// * `last_update_id` - The `lastUpdateId` of the orderbook.
+/// * `bids` - The prices and amounts of the bids.
+/// * `asks` - The prices and amounts of the asks.
+pub fn partial_depth(last_update_id: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    json!({
+        "lastUpdateId": last_update_id,
+        "bids": bids,
+        "asks": asks,
+    }).to_string()
+}
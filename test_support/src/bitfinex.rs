@@ -0,0 +1,77 @@
+//! Builders of the messages of the Bitfinex websocket v2 api.
+//! The channel messages are positional arrays keyed by the channel id the mock exchange assigns, see [CHANNEL_ID].
+//! The prices and amounts are JSON numbers, which are written as given, so that the checksum of a book can be
+//! computed from their exact representation.
+
+use serde_json::json;
+
+/// The channel id the mock exchange assigns to every subscription.
+pub const CHANNEL_ID: u64 = 17082;
+
+/// Builds the confirmation Bitfinex sends once a subscription to the book channel succeeded.
+///
+/// # Arguments
+///
+/// * `symbol` - The Bitfinex symbol of the instrument, e.g. `tETHBTC`.
+/// * `len` - The subscribed depth.
+pub fn subscribed(symbol: &str, len: &str) -> String {
+    json!({
+        "event": "subscribed",
+        "channel": "book",
+        "chanId": CHANNEL_ID,
+        "symbol": symbol,
+        "prec": "P0",
+        "freq": "F0",
+        "len": len,
+        "pair": symbol.trim_start_matches('t'),
+    }).to_string()
+}
+
+/// Builds the snapshot of the book channel.
+///
+/// # Arguments
+///
+/// * `entries` - The prices, counts and amounts of the levels. The amounts of the asks are negative.
+pub fn book_snapshot(entries: &[(&str, u64, &str)]) -> String {
+    let entries: Vec<String> = entries.iter().map(entry).collect();
+    format!("[{},[{}]]", CHANNEL_ID, entries.join(","))
+}
+
+/// Builds an update of the book channel.
+///
+/// # Arguments
+///
+/// * `update` - The price, count and amount of the level that changed. A count of 0 deletes the level,
+///   with an amount of 1 for a bid and -1 for an ask.
+pub fn book_update(update: (&str, u64, &str)) -> String {
+    format!("[{},{}]", CHANNEL_ID, entry(&update))
+}
+
+/// Builds the checksum message of the book channel.
+///
+/// # Arguments
+///
+/// * `checksum` - The checksum of the book, see [checksum].
+pub fn book_checksum(checksum: i32) -> String {
+    format!(r#"[{},"cs",{}]"#, CHANNEL_ID, checksum)
+}
+
+/// Computes the signed CRC32 checksum Bitfinex publishes for the top 25 levels per side of a book.
+///
+/// # Arguments
+///
+/// * `bids` - The prices and amounts of the bids of the book, from the highest price.
+/// * `asks` - The prices and negative amounts of the asks of the book, from the lowest price.
+pub fn checksum(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> i32 {
+    let mut values = vec![];
+    for rank in 0..25 {
+        for (price, amount) in [bids.get(rank), asks.get(rank)].into_iter().flatten() {
+            values.push(format!("{}:{}", price, amount));
+        }
+    }
+    crc32fast::hash(values.join(":").as_bytes()) as i32
+}
+
+fn entry((price, count, amount): &(&str, u64, &str)) -> String {
+    format!("[{},{},{}]", price, count, amount)
+}
@@ -0,0 +1,38 @@
+//! Builders of the messages of the Bitstamp WebSocket API.
+
+use serde_json::{json, Value};
+
+/// Builds the confirmation Bitstamp sends once a subscription succeeded.
+///
+/// # Arguments
+///
+/// * `channel` - The subscribed channel, e.g. `order_book_ethbtc`.
+pub fn subscription_succeeded(channel: &str) -> String {
+    event("bts:subscription_succeeded", channel, json!({}))
+}
+
+/// Builds a message of the live order book channel.
+///
+/// # Arguments
+///
+/// * `symbol` - The Bitstamp symbol of the instrument, e.g. `ethbtc`.
+/// * `microtimestamp` - The time of the orderbook in microseconds since the unix epoch.
+/// * `bids` - The prices and amounts of the bids.
+/// * `asks` - The prices and amounts of the asks.
+pub fn order_book(symbol: &str, microtimestamp: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    event("data", &format!("order_book_{}", symbol), json!({
+        "timestamp": (microtimestamp / 1_000_000).to_string(),
+        "microtimestamp": microtimestamp.to_string(),
+        "bids": bids,
+        "asks": asks,
+    }))
+}
+
+/// Builds the request Bitstamp sends before it closes a connection for maintenance.
+pub fn request_reconnect() -> String {
+    event("bts:request_reconnect", "", json!({}))
+}
+
+fn event(event: &str, channel: &str, data: Value) -> String {
+    json!({"event": event, "channel": channel, "data": data}).to_string()
+}
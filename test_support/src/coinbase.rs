@@ -0,0 +1,47 @@
+//! Builders of the messages of the Coinbase Exchange websocket feed.
+
+use serde_json::json;
+
+/// Builds the `subscriptions` message Coinbase sends once a subscription succeeded.
+///
+/// # Arguments
+///
+/// * `product_ids` - The Coinbase product ids of the subscribed instruments, e.g. `ETH-BTC`.
+/// * `channels` - The names of the subscribed channels.
+pub fn subscriptions(product_ids: &[&str], channels: &[&str]) -> String {
+    let channels: Vec<_> = channels.iter().map(|name| json!({"name": name, "product_ids": product_ids})).collect();
+    json!({"type": "subscriptions", "channels": channels}).to_string()
+}
+
+/// Builds the snapshot of the level2 channel.
+///
+/// # Arguments
+///
+/// * `product_id` - The Coinbase product id of the instrument, e.g. `ETH-BTC`.
+/// * `bids` - The prices and sizes of the bids, from the highest price.
+/// * `asks` - The prices and sizes of the asks, from the lowest price.
+pub fn snapshot(product_id: &str, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> String {
+    json!({"type": "snapshot", "product_id": product_id, "bids": bids, "asks": asks}).to_string()
+}
+
+/// Builds an `l2update` of the level2 channel.
+///
+/// # Arguments
+///
+/// * `product_id` - The Coinbase product id of the instrument, e.g. `ETH-BTC`.
+/// * `changes` - The sides, `buy` or `sell`, prices and sizes of the levels that changed, a zero size removes the level.
+/// * `time` - The time of the update, e.g. `2023-09-21T08:12:30.250000Z`.
+pub fn l2update(product_id: &str, changes: &[(&str, &str, &str)], time: &str) -> String {
+    json!({"type": "l2update", "product_id": product_id, "changes": changes, "time": time}).to_string()
+}
+
+/// Builds a message of the heartbeat channel.
+///
+/// # Arguments
+///
+/// * `product_id` - The Coinbase product id of the instrument, e.g. `ETH-BTC`.
+/// * `sequence` - The latest sequence number of the product.
+/// * `time` - The time of the heartbeat, e.g. `2023-09-21T08:12:30.100000Z`.
+pub fn heartbeat(product_id: &str, sequence: u64, time: &str) -> String {
+    json!({"type": "heartbeat", "last_trade_id": 0, "product_id": product_id, "sequence": sequence, "time": time}).to_string()
+}
@@ -0,0 +1,57 @@
+//! Builders of the messages of the Huobi market websocket. Huobi compresses every message with gzip,
+//! so the builders return the payloads of binary frames, see [crate::mock_exchange::Step::SendBinary].
+
+use std::io::Write;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+
+/// Builds the confirmation Huobi sends once a subscription succeeded.
+///
+/// # Arguments
+///
+/// * `topic` - The subscribed topic, e.g. `market.ethbtc.depth.step0`.
+/// * `id` - The id of the subscribe request.
+pub fn subscribed(topic: &str, id: &str) -> Vec<u8> {
+    gzip(&json!({"id": id, "status": "ok", "subbed": topic, "ts": 1630983549401u64}).to_string())
+}
+
+/// Builds the ping Huobi sends every 5 seconds, which the client answers with `{"pong": <ts>}`.
+///
+/// # Arguments
+///
+/// * `ts` - The timestamp of the ping.
+pub fn ping(ts: u64) -> Vec<u8> {
+    gzip(&json!({"ping": ts}).to_string())
+}
+
+/// Builds a message of the depth channel of the given symbol.
+///
+/// # Arguments
+///
+/// * `symbol` - The Huobi symbol of the instrument, e.g. `ethbtc`.
+/// * `version` - The version of the orderbook.
+/// * `bids` - The prices and amounts of the bids, from the highest price.
+/// * `asks` - The prices and amounts of the asks, from the lowest price.
+pub fn depth(symbol: &str, version: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> Vec<u8> {
+    let levels = |levels: &[(&str, &str)]| -> String {
+        let levels: Vec<String> = levels.iter().map(|(price, amount)| format!("[{},{}]", price, amount)).collect();
+        format!("[{}]", levels.join(","))
+    };
+    gzip(&format!(
+        r#"{{"ch":"market.{}.depth.step0","ts":1630983549503,"tick":{{"bids":{},"asks":{},"version":{},"ts":1630983549500}}}}"#,
+        symbol, levels(bids), levels(asks), version
+    ))
+}
+
+/// Compresses a message with gzip.
+///
+/// # Arguments
+///
+/// * `message` - The text of the message.
+pub fn gzip(message: &str) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(message.as_bytes()).expect("Could not compress the message");
+    encoder.finish().expect("Could not compress the message")
+}
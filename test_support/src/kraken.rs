@@ -0,0 +1,56 @@
+//! Builders of the messages of the Kraken WebSocket v2 api.
+//! The prices and quantities of the book channel are JSON numbers, which are written as given.
+//! Like the live feed, they should be written without trailing zeros, e.g. `10.0`.
+//! The checksums are given by the tests rather than computed here, so that they are not
+//! computed by the same algorithm as the one under test.
+
+use serde_json::json;
+
+/// Builds the confirmation Kraken sends once a subscription to the book channel succeeded.
+///
+/// # Arguments
+///
+/// * `symbol` - The Kraken symbol of the instrument, e.g. `ETH/BTC`.
+/// * `depth` - The subscribed depth.
+pub fn subscribed(symbol: &str, depth: u64) -> String {
+    json!({
+        "method": "subscribe",
+        "result": {"channel": "book", "depth": depth, "snapshot": true, "symbol": symbol},
+        "success": true,
+    }).to_string()
+}
+
+/// Builds the snapshot of the book channel.
+///
+/// # Arguments
+///
+/// * `symbol` - The Kraken symbol of the instrument, e.g. `ETH/BTC`.
+/// * `bids` - The prices and quantities of the bids, from the highest price.
+/// * `asks` - The prices and quantities of the asks, from the lowest price.
+/// * `checksum` - The checksum of the top 10 levels per side of the snapshot.
+pub fn book_snapshot(symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)], checksum: u32) -> String {
+    book("snapshot", symbol, bids, asks, checksum)
+}
+
+/// Builds an update of the book channel.
+///
+/// # Arguments
+///
+/// * `symbol` - The Kraken symbol of the instrument, e.g. `ETH/BTC`.
+/// * `bids` - The prices and quantities of the bids that changed, a zero quantity removes the level.
+/// * `asks` - The prices and quantities of the asks that changed.
+/// * `checksum` - The checksum of the top 10 levels per side of the book after the update.
+pub fn book_update(symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)], checksum: u32) -> String {
+    book("update", symbol, bids, asks, checksum)
+}
+
+fn book(message_type: &str, symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)], checksum: u32) -> String {
+    let levels = |levels: &[(&str, &str)]| -> String {
+        let levels: Vec<String> = levels.iter().map(|(price, qty)| format!(r#"{{"price":{},"qty":{}}}"#, price, qty)).collect();
+        format!("[{}]", levels.join(","))
+    };
+    format!(
+        r#"{{"channel":"book","type":"{}","data":[{{"symbol":"{}","bids":{},"asks":{},"checksum":{}}}]}}"#,
+        message_type, symbol, levels(bids), levels(asks), checksum
+    )
+}
@@ -0,0 +1,45 @@
+//! Builders of the messages of the KuCoin websocket.
+
+use serde_json::json;
+
+/// Builds the `welcome` KuCoin sends once a connection was opened, before the client subscribes.
+///
+/// # Arguments
+///
+/// * `connect_id` - The `connectId` of the connection url.
+pub fn welcome(connect_id: &str) -> String {
+    json!({"id": connect_id, "type": "welcome"}).to_string()
+}
+
+/// Builds the `ack` KuCoin sends once a subscription succeeded.
+///
+/// # Arguments
+///
+/// * `id` - The id of the subscribe request.
+pub fn ack(id: &str) -> String {
+    json!({"id": id, "type": "ack"}).to_string()
+}
+
+/// Builds an update of the level2 channel. Every change carries its own sequence number.
+///
+/// # Arguments
+///
+/// * `symbol` - The KuCoin symbol of the instrument, e.g. `ETH-BTC`.
+/// * `sequence_start` - The sequence number of the first change.
+/// * `sequence_end` - The sequence number of the last change.
+/// * `bids` - The prices, sizes and sequence numbers of the bids that changed, a zero size removes the level.
+/// * `asks` - The prices, sizes and sequence numbers of the asks that changed.
+pub fn l2update(symbol: &str, sequence_start: u64, sequence_end: u64, bids: &[(&str, &str, &str)], asks: &[(&str, &str, &str)]) -> String {
+    json!({
+        "type": "message",
+        "topic": format!("/market/level2:{}", symbol),
+        "subject": "trade.l2update",
+        "data": {
+            "changes": {"asks": asks, "bids": bids},
+            "sequenceEnd": sequence_end,
+            "sequenceStart": sequence_start,
+            "symbol": symbol,
+            "time": 1663747970273u64,
+        },
+    }).to_string()
+}
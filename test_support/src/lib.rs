@@ -0,0 +1,13 @@
+#![crate_name = "test_support"]
+
+//! This crate includes the test support of the workspaces: a scriptable local WebSocket server that
+//! imitates the supported exchanges and builders of the messages they publish.
+
+pub mod binance;
+pub mod bitfinex;
+pub mod bitstamp;
+pub mod coinbase;
+pub mod huobi;
+pub mod kraken;
+pub mod kucoin;
+pub mod mock_exchange;
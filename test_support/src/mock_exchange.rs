@@ -0,0 +1,294 @@
+//! A local WebSocket server that imitates an exchange, so that the exchange clients can be tested
+//! without connecting to the live endpoints.
+//!
+//! Every accepted connection first completes the subscribe handshake of the exchange and then plays
+//! the next [Script] step by step. Once its script has been played, or if no script is left, the connection
+//! stays open and idle until the client disconnects. The frames the clients send are recorded.
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use futures_util::{Sink, SinkExt, Stream, StreamExt};
+use serde_json::Value;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::{Error, Message};
+
+use data_models::exchange::Exchange;
+
+use crate::{bitfinex, bitstamp, coinbase, huobi, kraken, kucoin};
+
+/// The text frame sent by [Step::SendMalformed], which is not valid JSON.
+pub const MALFORMED_FRAME: &str = "{\"bids\": [[";
+
+/// A step of the [Script] the mock exchange plays on a connection.
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// Sends a text frame, e.g. a message built with [crate::binance] or [crate::bitstamp].
+    Send(String),
+    /// Sends the [MALFORMED_FRAME].
+    SendMalformed,
+    /// Sends a binary frame.
+    SendBinary(Vec<u8>),
+    /// Sends a ping with the given payload. The pong of the client is recorded, see [MockExchange::pongs].
+    Ping(Vec<u8>),
+    /// Waits before the next step.
+    Delay(Duration),
+    /// Sends a close frame and waits for the client to close the connection.
+    Close,
+    /// Drops the connection without a close frame, like a network failure.
+    Disconnect,
+}
+
+/// The steps played on one connection.
+pub type Script = Vec<Step>;
+
+/// How a played [Script] ended.
+enum End {
+    Played,
+    Disconnected,
+}
+
+#[derive(Default)]
+struct Recorded {
+    paths: Vec<String>,
+    received: Vec<String>,
+    pongs: Vec<Vec<u8>>,
+}
+
+/// A running mock exchange. The server and its connections are stopped when it is dropped.
+pub struct MockExchange {
+    address: SocketAddr,
+    recorded: Arc<Mutex<Recorded>>,
+    server: JoinHandle<()>,
+}
+
+impl MockExchange {
+    /// Starts a new [MockExchange] on a free local port.
+    ///
+    /// # Arguments
+    ///
+    /// * `exchange` - The [Exchange] whose subscribe handshake is imitated. Binance subscribes through the
+    ///   path of the url and has no handshake, the other exchanges confirm the subscribe request of the client,
+    ///   e.g. the `bts:subscribe` of Bitstamp, with the confirmation built by their module, e.g. [crate::huobi::subscribed].
+    ///   KuCoin welcomes every connection before the client subscribes, see [crate::kucoin::welcome].
+    /// * `scripts` - The [Script] of each connection, in the order the connections are accepted.
+    ///
+    /// This method will panic if no local port can be bound.
+    pub async fn start(exchange: Exchange, scripts: Vec<Script>) -> MockExchange {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Could not bind the mock exchange");
+        let address = listener.local_addr().expect("Could not read the address of the mock exchange");
+        let recorded = Arc::new(Mutex::new(Recorded::default()));
+
+        let server = tokio::spawn(serve(listener, exchange, scripts, recorded.clone()));
+
+        MockExchange { address, recorded, server }
+    }
+
+    /// Returns the `ws://` url of the mock exchange, without a path.
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+
+    /// Returns the number of connections the mock exchange accepted.
+    pub fn connections(&self) -> usize {
+        self.recorded().paths.len()
+    }
+
+    /// Returns the request path of every accepted connection, e.g. the streams of a Binance client.
+    pub fn paths(&self) -> Vec<String> {
+        self.recorded().paths.clone()
+    }
+
+    /// Returns the text frames the clients sent, e.g. their subscribe requests.
+    pub fn received(&self) -> Vec<String> {
+        self.recorded().received.clone()
+    }
+
+    /// Returns the payloads of the pongs the clients sent.
+    pub fn pongs(&self) -> Vec<Vec<u8>> {
+        self.recorded().pongs.clone()
+    }
+
+    fn recorded(&self) -> MutexGuard<'_, Recorded> {
+        self.recorded.lock().unwrap()
+    }
+}
+
+impl Drop for MockExchange {
+    fn drop(&mut self) {
+        // Dropping the connections of the aborted server closes them
+        self.server.abort();
+    }
+}
+
+async fn serve(listener: TcpListener, exchange: Exchange, scripts: Vec<Script>, recorded: Arc<Mutex<Recorded>>) {
+    let mut scripts = scripts.into_iter();
+    let mut connections = JoinSet::new();
+
+    while let Ok((stream, _)) = listener.accept().await {
+        let script = scripts.next().unwrap_or_default();
+        connections.spawn(handle(stream, exchange, script, recorded.clone()));
+    }
+}
+
+async fn handle(stream: TcpStream, exchange: Exchange, script: Script, recorded: Arc<Mutex<Recorded>>) {
+    let mut path = String::new();
+    // The error response is defined by the callback of tungstenite
+    #[allow(clippy::result_large_err)]
+    let record_path = |request: &Request, response: Response| {
+        path = request.uri().path().to_string();
+        Ok(response)
+    };
+    let Ok(ws_stream) = tokio_tungstenite::accept_hdr_async(stream, record_path).await else {
+        return;
+    };
+    recorded.lock().unwrap().paths.push(path);
+
+    let (mut sink, mut stream) = ws_stream.split();
+    if exchange == Exchange::Kucoin && sink.send(Message::text(kucoin::welcome("welcome"))).await.is_err() {
+        return;
+    }
+    if exchange != Exchange::Binance && !confirm_subscription(&mut sink, &mut stream, &recorded).await {
+        return;
+    }
+
+    // The frames of the client are read, and its pings answered, while the script is played
+    let reading = read(&mut stream, &recorded);
+    tokio::pin!(reading);
+    tokio::select! {
+        _ = &mut reading => return,
+        end = play(&mut sink, script) => if let End::Disconnected = end {
+            return;
+        }
+    }
+    reading.await;
+}
+
+/// Waits for the subscribe request of the client and confirms it.
+/// Returns false if the client disconnected before it subscribed.
+async fn confirm_subscription(sink: &mut (impl Sink<Message, Error=Error> + Unpin),
+                              stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin),
+                              recorded: &Mutex<Recorded>) -> bool {
+    while let Some(Ok(message)) = stream.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        recorded.lock().unwrap().received.push(text.clone());
+
+        let request: Value = serde_json::from_str(&text).unwrap_or_default();
+        let confirmation = if request["event"] == "bts:subscribe" {
+            Message::text(bitstamp::subscription_succeeded(request["data"]["channel"].as_str().unwrap_or_default()))
+        } else if request["method"] == "subscribe" {
+            Message::text(kraken::subscribed(request["params"]["symbol"][0].as_str().unwrap_or_default(), request["params"]["depth"].as_u64().unwrap_or_default()))
+        } else if request["event"] == "subscribe" {
+            Message::text(bitfinex::subscribed(request["symbol"].as_str().unwrap_or_default(), request["len"].as_str().unwrap_or_default()))
+        } else if request["type"] == "subscribe" && request["topic"].is_string() {
+            Message::text(kucoin::ack(request["id"].as_str().unwrap_or_default()))
+        } else if request["type"] == "subscribe" {
+            let product_ids: Vec<&str> = request["product_ids"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            let channels: Vec<&str> = request["channels"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            Message::text(coinbase::subscriptions(&product_ids, &channels))
+        } else if let Some(topic) = request["sub"].as_str() {
+            Message::binary(huobi::subscribed(topic, request["id"].as_str().unwrap_or_default()))
+        } else {
+            continue;
+        };
+        return sink.send(confirmation).await.is_ok();
+    }
+    false
+}
+
+async fn play(sink: &mut (impl Sink<Message, Error=Error> + Unpin), script: Script) -> End {
+    for step in script {
+        let result = match step {
+            Step::Send(text) => sink.send(Message::text(text)).await,
+            Step::SendMalformed => sink.send(Message::text(MALFORMED_FRAME)).await,
+            Step::SendBinary(data) => sink.send(Message::binary(data)).await,
+            Step::Ping(payload) => sink.send(Message::Ping(payload)).await,
+            Step::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Step::Close => sink.send(Message::Close(None)).await,
+            Step::Disconnect => return End::Disconnected,
+        };
+        if result.is_err() {
+            return End::Disconnected;
+        }
+    }
+    End::Played
+}
+
+/// Records the frames of the client until it disconnects.
+async fn read(stream: &mut (impl Stream<Item=Result<Message, Error>> + Unpin), recorded: &Mutex<Recorded>) {
+    while let Some(Ok(message)) = stream.next().await {
+        match message {
+            Message::Text(text) => recorded.lock().unwrap().received.push(text),
+            Message::Pong(payload) => recorded.lock().unwrap().pongs.push(payload),
+            _ => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::connect_async;
+    use tokio_tungstenite::tungstenite::Message;
+
+    use data_models::exchange::Exchange;
+
+    use crate::bitstamp;
+    use crate::mock_exchange::{MALFORMED_FRAME, MockExchange, Step};
+
+    #[tokio::test]
+    async fn bitstamp_script_test() {
+        let order_book = bitstamp::order_book("ethbtc", 1, &[("0.0687", "1.0")], &[]);
+        let mock_exchange = MockExchange::start(Exchange::Bitstamp, vec![vec![
+            Step::Send(order_book.clone()),
+            Step::Delay(Duration::from_millis(10)),
+            Step::SendMalformed,
+            Step::Ping(vec![1]),
+            Step::Close,
+        ]]).await;
+
+        let (mut ws_stream, _) = connect_async(mock_exchange.url()).await.unwrap();
+        let subscribe = r#"{"event":"bts:subscribe","data":{"channel":"order_book_ethbtc"}}"#;
+        ws_stream.send(Message::text(subscribe)).await.unwrap();
+
+        let mut messages = vec![];
+        while let Some(Ok(message)) = ws_stream.next().await {
+            messages.push(message);
+        }
+
+        assert_eq!(messages, vec![
+            Message::text(bitstamp::subscription_succeeded("order_book_ethbtc")),
+            Message::text(order_book),
+            Message::text(MALFORMED_FRAME),
+            Message::Ping(vec![1]),
+            Message::Close(None),
+        ]);
+        assert_eq!(mock_exchange.connections(), 1);
+        assert_eq!(mock_exchange.received(), vec![subscribe.to_string()]);
+        assert_eq!(mock_exchange.pongs(), vec![vec![1]]);
+    }
+
+    #[tokio::test]
+    async fn disconnect_test() {
+        let mock_exchange = MockExchange::start(Exchange::Binance, vec![vec![Step::Disconnect]]).await;
+
+        let (mut ws_stream, _) = connect_async(format!("{}/ws/ethbtc@depth10@100ms", mock_exchange.url())).await.unwrap();
+        assert!(matches!(ws_stream.next().await, None | Some(Err(_))));
+
+        // The connections without a script stay open
+        let (mut ws_stream, _) = connect_async(mock_exchange.url()).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(50), ws_stream.next()).await.is_err());
+
+        assert_eq!(mock_exchange.paths(), vec!["/ws/ethbtc@depth10@100ms".to_string(), "/".to_string()]);
+    }
+}